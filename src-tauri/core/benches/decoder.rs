@@ -0,0 +1,88 @@
+// 解码器基准测试：模拟 921600 波特率下饱和的数据流
+// 921600 波特率约 92 KB/s，按 1% CPU 计算，每 KB 的解析时间应低于约 110 µs
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serial_joystick_core::config::{ChecksumType, ProtocolConfig};
+use serial_joystick_core::matrix::{FrameDecoder, ParsedData};
+
+const BYTES_PER_SECOND: usize = 921_600 / 10;
+
+// 按协议生成一帧
+fn frame(protocol: &ProtocolConfig, index: u8) -> Vec<u8> {
+    let payload = protocol.key_count.div_ceil(8) + protocol.adc_count + protocol.led_count.div_ceil(8);
+    let mut frame = vec![0xAA, index];
+    frame.extend((0..payload).map(|i| (i as u8).wrapping_mul(37).wrapping_add(index)));
+    let checksum = match protocol.checksum {
+        ChecksumType::Xor => frame.iter().fold(0u8, |acc, b| acc ^ b),
+        ChecksumType::Sum => frame.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)),
+        ChecksumType::None => 0,
+    };
+    frame.push(checksum);
+    frame.push(0xBF);
+    frame
+}
+
+// 连续的帧组成的数据流，长度约为 len
+fn stream(protocol: &ProtocolConfig, len: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(len + 64);
+    let mut index = 0u8;
+    while data.len() < len {
+        data.extend(frame(protocol, index));
+        index = index.wrapping_add(1);
+    }
+    data
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for checksum in [ChecksumType::Xor, ChecksumType::Sum] {
+        let protocol = ProtocolConfig { checksum, ..ProtocolConfig::default() };
+        let decoder = FrameDecoder::new(&protocol);
+        // 每次读取最多 1 KB，以及一秒的完整数据量
+        for len in [1024, BYTES_PER_SECOND] {
+            let data = stream(&protocol, len);
+            group.throughput(Throughput::Bytes(data.len() as u64));
+            group.bench_with_input(BenchmarkId::new(format!("{:?}", checksum), len), &data, |b, data| {
+                b.iter(|| decoder.decode(black_box(data)))
+            });
+        }
+    }
+    group.finish();
+}
+
+// 串口轮询的实际路径：每次读取 128 字节，解析到复用的 ParsedData 中，稳态下不分配内存
+fn bench_decode_into(c: &mut Criterion) {
+    let protocol = ProtocolConfig::default();
+    let decoder = FrameDecoder::new(&protocol);
+    let data = stream(&protocol, 128);
+    let mut parsed = ParsedData::new(&protocol);
+    let mut group = c.benchmark_group("decode_into");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("read_128", |b| {
+        b.iter(|| decoder.decode_into(black_box(&data[..128]), &mut parsed))
+    });
+    group.finish();
+}
+
+fn bench_checksum_errors(c: &mut Criterion) {
+    let protocol = ProtocolConfig::default();
+    let decoder = FrameDecoder::new(&protocol);
+    let data = stream(&protocol, BYTES_PER_SECOND);
+    let mut group = c.benchmark_group("find_checksum_errors");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("clean_stream", |b| b.iter(|| decoder.find_checksum_errors(black_box(&data))));
+    group.finish();
+}
+
+// 没有帧头的乱码（波特率错误时）不应比正常数据更慢
+fn bench_noise(c: &mut Criterion) {
+    let decoder = FrameDecoder::new(&ProtocolConfig::default());
+    let noise: Vec<u8> = (0..BYTES_PER_SECOND).map(|i| (i * 131 % 251) as u8 | 0x01).collect();
+    let mut group = c.benchmark_group("sync_search");
+    group.throughput(Throughput::Bytes(noise.len() as u64));
+    group.bench_function("noise", |b| b.iter(|| decoder.has_frame(black_box(&noise))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode, bench_decode_into, bench_checksum_errors, bench_noise);
+criterion_main!(benches);
@@ -0,0 +1,593 @@
+// STM32 Bootloader 固件下载
+// 协议说明见 docs/Firmware_Upgrade_Protocol.md
+
+use serde::Serialize;
+use serialport::SerialPort;
+use std::fs;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+// ========== 协议定义 ==========
+
+const DEVICE_ADDR: u8 = 0x01;
+const FUNC_SEND_DATA: u8 = 0x01;
+const FUNC_QUERY_INFO: u8 = 0x05;
+const FUNC_SEND_CRC: u8 = 0x06;
+const MAX_DATA_LEN: usize = 512;  // 每次最大512字节
+const MAX_RETRIES: usize = 3;  // 单帧最大重发次数
+const BAUD_RATE: u32 = 115200;
+const FRAME_OVERHEAD: usize = 6;  // 帧头4字节 + 校验和2字节
+const BITS_PER_BYTE: u64 = 10;  // 起始位 + 8数据位 + 停止位
+const DEVICE_TURNAROUND_MS: u64 = 20;  // 设备写入Flash并回复的估计耗时
+const THROUGHPUT_WINDOW: usize = 8;  // 计算速度时取最近的帧数
+const SUPPORTED_MAJOR: u8 = 1;  // 支持的Bootloader主版本
+
+// 查询设备信息响应中的功能标志
+const FLAG_CRC: u8 = 0x01;
+const FLAG_RESUME: u8 = 0x02;
+const FLAG_ERASE: u8 = 0x04;
+
+// ========== 诊断事件 ==========
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+// 下载过程中产生的事件，由调用方转发给前端
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BootloaderEvent {
+    Log {
+        level: LogLevel,
+        message: String,
+        frame: Option<String>,  // 相关帧的十六进制内容
+    },
+    Version(BootloaderInfo),  // 版本协商的结果
+    Progress {
+        sent: usize,
+        total: usize,
+        bytes_per_sec: f64,  // 最近几帧的平均速度，包括重发耗费的时间
+        eta_ms: Option<u64>,  // 按当前速度估计的剩余时间
+        retries: usize,  // 累计重发次数
+    },
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn func_name(func: u8) -> &'static str {
+    match func {
+        FUNC_SEND_DATA => "文件数据",
+        FUNC_QUERY_INFO => "查询设备信息",
+        FUNC_SEND_CRC => "CRC校验值",
+        _ => "未知功能",
+    }
+}
+
+// ========== 版本协商 ==========
+
+// 查询设备信息的响应数据：[主版本][次版本][每帧最大数据长度][功能标志]，
+// 后两个字节可省略；不响应查询的旧版Bootloader按 legacy 处理
+#[derive(Debug, Clone, Serialize)]
+pub struct BootloaderInfo {
+    pub major: u8,
+    pub minor: u8,
+    pub legacy: bool,  // 设备未响应版本查询
+    pub max_data_len: usize,
+    pub crc: bool,
+    pub resume: bool,  // 支持断点续传
+    pub erase: bool,  // 支持擦除命令
+}
+
+impl BootloaderInfo {
+    fn legacy() -> Self {
+        Self {
+            major: 1,
+            minor: 0,
+            legacy: true,
+            max_data_len: MAX_DATA_LEN,
+            crc: true,
+            resume: false,
+            erase: false,
+        }
+    }
+
+    fn parse(data: &[u8]) -> Result<Self, String> {
+        let [major, minor, rest @ ..] = data else {
+            return Err(format!("设备信息长度不足: {} 字节", data.len()));
+        };
+        let info = Self {
+            major: *major,
+            minor: *minor,
+            legacy: false,
+            max_data_len: match rest.first() {
+                Some(&len) if len > 0 => len as usize,
+                _ => MAX_DATA_LEN,
+            },
+            crc: rest.get(1).is_none_or(|flags| flags & FLAG_CRC != 0),
+            resume: rest.get(1).is_some_and(|flags| flags & FLAG_RESUME != 0),
+            erase: rest.get(1).is_some_and(|flags| flags & FLAG_ERASE != 0),
+        };
+        if info.major != SUPPORTED_MAJOR {
+            return Err(format!(
+                "不兼容的Bootloader版本 {}.{}，当前只支持 {}.x",
+                info.major, info.minor, SUPPORTED_MAJOR
+            ));
+        }
+        Ok(info)
+    }
+}
+
+// ========== 下载计划 ==========
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkPlan {
+    pub seq: u8,
+    pub offset: usize,
+    pub len: usize,
+}
+
+// 试运行的结果：只解析文件、分片和计算CRC，不打开串口
+#[derive(Debug, Clone, Serialize)]
+pub struct FlashPlan {
+    pub file: String,
+    pub size: usize,
+    pub chunks: Vec<ChunkPlan>,
+    pub crc: Option<u32>,
+    pub frames: usize,  // 包括CRC帧和结束帧
+    pub wire_bytes: usize,  // 发送和接收的总字节数（不含重发）
+    pub estimated_ms: u64,
+}
+
+pub fn plan_firmware(file_path: &Path, use_crc: bool) -> Result<FlashPlan, String> {
+    let firmware = fs::read(file_path)
+        .map_err(|e| format!("读取固件文件失败: {}", e))?;
+    if firmware.is_empty() {
+        return Err(format!("固件文件为空: {:?}", file_path));
+    }
+
+    let chunks: Vec<ChunkPlan> = firmware
+        .chunks(MAX_DATA_LEN)
+        .enumerate()
+        .map(|(i, chunk)| ChunkPlan {
+            seq: i as u8,
+            offset: i * MAX_DATA_LEN,
+            len: chunk.len(),
+        })
+        .collect();
+    let crc = use_crc.then(|| calc_crc32(&firmware));
+
+    // 每帧发送数据加帧开销，设备回复一个不带数据的响应帧
+    let mut payloads: Vec<usize> = chunks.iter().map(|c| c.len).collect();
+    if crc.is_some() {
+        payloads.push(4);
+    }
+    payloads.push(0);
+    let wire_bytes: usize = payloads.iter().map(|len| len + FRAME_OVERHEAD * 2).sum();
+    let transfer_ms = wire_bytes as u64 * BITS_PER_BYTE * 1000 / BAUD_RATE as u64;
+
+    Ok(FlashPlan {
+        file: file_path.to_string_lossy().into_owned(),
+        size: firmware.len(),
+        frames: payloads.len(),
+        chunks,
+        crc,
+        wire_bytes,
+        estimated_ms: transfer_ms + payloads.len() as u64 * DEVICE_TURNAROUND_MS,
+    })
+}
+
+// ========== 速度估计 ==========
+
+// 按最近几帧完成的时间计算速度，低波特率或频繁重发时剩余时间随之变长
+struct Throughput {
+    samples: VecDeque<(Instant, usize)>,
+}
+
+impl Throughput {
+    fn new() -> Self {
+        let mut samples = VecDeque::with_capacity(THROUGHPUT_WINDOW + 1);
+        samples.push_back((Instant::now(), 0));
+        Self { samples }
+    }
+
+    // 记录累计发送的字节数，返回每秒字节数
+    fn record(&mut self, sent: usize) -> f64 {
+        self.samples.push_back((Instant::now(), sent));
+        if self.samples.len() > THROUGHPUT_WINDOW + 1 {
+            self.samples.pop_front();
+        }
+        let (first_time, first_sent) = self.samples[0];
+        let elapsed = first_time.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            (sent - first_sent) as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+}
+
+// ========== 协议帧 ==========
+
+struct ProtocolFrame {
+    device_addr: u8,
+    func_type: u8,
+    seq: u8,
+    data: Vec<u8>,
+}
+
+impl ProtocolFrame {
+    fn new(device_addr: u8, func_type: u8, seq: u8, data: Vec<u8>) -> Self {
+        Self {
+            device_addr,
+            func_type,
+            seq,
+            data,
+        }
+    }
+
+    // 打包成字节数组
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(4 + self.data.len() + 2);
+
+        // [设备地址][功能码][序列号][数据长度]
+        frame.push(self.device_addr);
+        frame.push(self.func_type);
+        frame.push(self.seq);
+        frame.push(self.data.len() as u8);
+
+        // 数据内容
+        frame.extend(&self.data);
+
+        // 计算校验和（累加和）
+        let checksum = calc_checksum(&frame);
+        frame.push((checksum >> 8) as u8);  // 高字节
+        frame.push(checksum as u8);         // 低字节
+
+        frame
+    }
+}
+
+// 设备响应帧，格式与发送帧相同
+struct ResponseFrame {
+    device_addr: u8,
+    func_type: u8,
+    seq: u8,
+    data: Vec<u8>,
+}
+
+impl ResponseFrame {
+    // 解析响应，失败时返回可读的原因
+    fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 6 {
+            return Err(format!("响应长度不足: {} 字节", bytes.len()));
+        }
+        let data_len = bytes[3] as usize;
+        if bytes.len() < 6 + data_len {
+            return Err(format!(
+                "响应不完整: 数据长度字段为 {}，实际只收到 {} 字节",
+                data_len,
+                bytes.len()
+            ));
+        }
+
+        let body = &bytes[..4 + data_len];
+        let expected = calc_checksum(body);
+        let received = ((bytes[4 + data_len] as u16) << 8) | bytes[5 + data_len] as u16;
+        if expected != received {
+            return Err(format!(
+                "响应校验和错误: 期望 0x{:04X}，收到 0x{:04X}",
+                expected, received
+            ));
+        }
+
+        Ok(Self {
+            device_addr: bytes[0],
+            func_type: bytes[1],
+            seq: bytes[2],
+            data: bytes[4..4 + data_len].to_vec(),
+        })
+    }
+
+    // 检查响应是否与请求对应，返回错误原因
+    fn check(&self, func_type: u8, seq: u8, expects_data: bool) -> Result<(), String> {
+        if self.device_addr != DEVICE_ADDR {
+            return Err(format!("设备地址不匹配: 0x{:02X}", self.device_addr));
+        }
+        if self.func_type != func_type {
+            return Err(format!(
+                "功能码不匹配: 期望 0x{:02X}（{}），收到 0x{:02X}（{}）",
+                func_type,
+                func_name(func_type),
+                self.func_type,
+                func_name(self.func_type)
+            ));
+        }
+        if self.seq != seq {
+            return Err(format!("帧序列不匹配: 期望 {}，收到 {}", seq, self.seq));
+        }
+        // 正常响应的数据长度为0，携带数据时首字节为设备返回的状态码；查询类响应除外
+        if expects_data {
+            return Ok(());
+        }
+        if let Some(&status) = self.data.first() {
+            return Err(format!("设备返回错误状态码 0x{:02X}", status));
+        }
+        Ok(())
+    }
+}
+
+// ========== 校验函数 ==========
+
+// 计算校验和（累加和，与Bootloader一致）
+fn calc_checksum(data: &[u8]) -> u16 {
+    let mut sum: u16 = 0;
+    for &byte in data {
+        sum = sum.wrapping_add(byte as u16);
+    }
+    (256 - (sum % 256)) & 0x00FF
+}
+
+// 计算CRC32（与Bootloader一致）
+fn calc_crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFFFFFF;
+
+    // 按32位字（小端序）处理，末尾不足4字节补0
+    for chunk in data.chunks(4) {
+        let mut word: u32 = 0;
+        for (j, &byte) in chunk.iter().enumerate() {
+            word |= (byte as u32) << (j * 8);
+        }
+
+        crc ^= word;
+        for _ in 0..32 {
+            if crc & 0x80000000 != 0 {
+                crc = (crc << 1) ^ 0x04C11DB7;
+            } else {
+                crc <<= 1;
+            }
+        }
+    }
+
+    !crc
+}
+
+// ========== Bootloader客户端 ==========
+
+pub struct BootloaderClient<F: FnMut(BootloaderEvent)> {
+    port: Box<dyn SerialPort>,
+    seq: u8,
+    use_crc: bool,
+    retries: usize,
+    on_event: F,
+}
+
+impl<F: FnMut(BootloaderEvent)> BootloaderClient<F> {
+    // 创建客户端，on_event 接收诊断日志和进度
+    pub fn new(port_name: &str, use_crc: bool, on_event: F) -> Result<Self, String> {
+        let port = serialport::new(port_name, BAUD_RATE)
+            .data_bits(serialport::DataBits::Eight)
+            .stop_bits(serialport::StopBits::One)
+            .parity(serialport::Parity::None)
+            .timeout(Duration::from_millis(1000))
+            .open()
+            .map_err(|e| format!("无法打开串口 {}: {}", port_name, e))?;
+
+        Self::from_port(port, use_crc, on_event)
+    }
+
+    // 使用已打开的串口（如矩阵设备连接时的句柄），按Bootloader要求调整波特率和超时
+    pub fn from_port(mut port: Box<dyn SerialPort>, use_crc: bool, on_event: F) -> Result<Self, String> {
+        if port.baud_rate().ok() != Some(BAUD_RATE) {
+            port.set_baud_rate(BAUD_RATE)
+                .map_err(|e| format!("设置波特率失败: {}", e))?;
+        }
+        port.set_timeout(Duration::from_millis(1000))
+            .map_err(|e| format!("设置超时失败: {}", e))?;
+        let _ = port.clear(serialport::ClearBuffer::All);
+
+        Ok(Self {
+            port,
+            seq: 0,
+            use_crc,
+            retries: 0,
+            on_event,
+        })
+    }
+
+    fn log(&mut self, level: LogLevel, message: String, frame: Option<&[u8]>) {
+        (self.on_event)(BootloaderEvent::Log {
+            level,
+            message,
+            frame: frame.map(to_hex),
+        });
+    }
+
+    // 接收一帧响应：先读4字节帧头，再按数据长度读剩余部分
+    fn recv(&mut self) -> Result<Vec<u8>, String> {
+        let mut header = [0u8; 4];
+        self.port
+            .read_exact(&mut header)
+            .map_err(|e| format!("接收响应失败: {}", e))?;
+
+        let mut rest = vec![0u8; header[3] as usize + 2];
+        self.port
+            .read_exact(&mut rest)
+            .map_err(|e| format!("接收响应失败: {}", e))?;
+
+        let mut frame = header.to_vec();
+        frame.extend(rest);
+        Ok(frame)
+    }
+
+    // 发送一帧并等待匹配的响应，失败时重发
+    fn transact(&mut self, func_type: u8, data: Vec<u8>) -> Result<(), String> {
+        self.exchange(func_type, data, MAX_RETRIES, false).map(|_| ())
+    }
+
+    // 发送一帧，返回响应携带的数据
+    fn exchange(&mut self, func_type: u8, data: Vec<u8>, attempts: usize, expects_data: bool) -> Result<Vec<u8>, String> {
+        let frame = ProtocolFrame::new(DEVICE_ADDR, func_type, self.next_seq(), data);
+        let bytes = frame.to_bytes();
+
+        for attempt in 1..=attempts {
+            // 清掉残留数据，避免把上一帧的响应当成本帧的
+            let _ = self.port.clear(serialport::ClearBuffer::Input);
+            self.port
+                .write_all(&bytes)
+                .map_err(|e| format!("发送数据失败: {}", e))?;
+
+            let reason = match self.recv() {
+                Ok(resp) => {
+                    match ResponseFrame::parse(&resp).and_then(|r| r.check(frame.func_type, frame.seq, expects_data).map(|_| r)) {
+                        Ok(response) => {
+                            let message = format!(
+                                "收到{}响应（序列 {}）",
+                                func_name(frame.func_type),
+                                frame.seq
+                            );
+                            self.log(LogLevel::Info, message, Some(&resp));
+                            return Ok(response.data);
+                        }
+                        Err(reason) => {
+                            self.log(LogLevel::Warn, format!("响应异常: {}", reason), Some(&resp));
+                            reason
+                        }
+                    }
+                }
+                Err(reason) => reason,
+            };
+
+            let message = format!(
+                "{}帧（序列 {}）第 {}/{} 次尝试失败: {}",
+                func_name(frame.func_type),
+                frame.seq,
+                attempt,
+                attempts,
+                reason
+            );
+            self.log(LogLevel::Warn, message, Some(&bytes));
+            if attempt < attempts {
+                self.retries += 1;
+            }
+        }
+
+        Err(format!(
+            "{}帧（序列 {}）重试 {} 次后仍失败",
+            func_name(frame.func_type),
+            frame.seq,
+            attempts
+        ))
+    }
+
+    // 查询Bootloader版本，据此选择每帧数据长度和可用功能；
+    // 旧版Bootloader不响应查询，按原有协议继续
+    pub fn negotiate(&mut self) -> Result<BootloaderInfo, String> {
+        let info = match self.exchange(FUNC_QUERY_INFO, Vec::new(), 1, true) {
+            Ok(data) => BootloaderInfo::parse(&data)?,
+            Err(_) => {
+                self.log(LogLevel::Warn, "Bootloader未响应版本查询，按旧版协议下载".to_string(), None);
+                // 查询帧占用的序列号不影响旧版设备，重新从0开始
+                self.seq = 0;
+                BootloaderInfo::legacy()
+            }
+        };
+        self.log(
+            LogLevel::Info,
+            format!(
+                "Bootloader版本 {}.{}，每帧 {} 字节，CRC {}，续传 {}，擦除 {}",
+                info.major,
+                info.minor,
+                info.max_data_len,
+                if info.crc { "支持" } else { "不支持" },
+                if info.resume { "支持" } else { "不支持" },
+                if info.erase { "支持" } else { "不支持" },
+            ),
+            None,
+        );
+        (self.on_event)(BootloaderEvent::Version(info.clone()));
+        Ok(info)
+    }
+
+    // 获取下一个序列号
+    fn next_seq(&mut self) -> u8 {
+        let s = self.seq;
+        self.seq = self.seq.wrapping_add(1);
+        s
+    }
+
+    // ========== 核心函数：下载固件 ==========
+
+    pub fn download_firmware(&mut self, file_path: &Path) -> Result<(), String> {
+        let firmware = fs::read(file_path)
+            .map_err(|e| format!("读取固件文件失败: {}", e))?;
+        let total = firmware.len();
+        let info = match self.negotiate() {
+            Ok(info) => info,
+            Err(e) => {
+                self.log(LogLevel::Error, e.clone(), None);
+                return Err(e);
+            }
+        };
+        let chunk_size = info.max_data_len.min(MAX_DATA_LEN);
+        let use_crc = self.use_crc && info.crc;
+        if self.use_crc && !info.crc {
+            self.log(LogLevel::Warn, "Bootloader不支持CRC校验，跳过发送CRC".to_string(), None);
+        }
+        self.log(
+            LogLevel::Info,
+            format!("开始下载固件 {:?}，大小 {} 字节", file_path, total),
+            None,
+        );
+
+        // 分片发送固件数据
+        let mut sent = 0;
+        let mut throughput = Throughput::new();
+        for chunk in firmware.chunks(chunk_size) {
+            if let Err(e) = self.transact(FUNC_SEND_DATA, chunk.to_vec()) {
+                self.log(LogLevel::Error, e.clone(), None);
+                return Err(e);
+            }
+            sent += chunk.len();
+            let bytes_per_sec = throughput.record(sent);
+            let eta_ms = (bytes_per_sec > 0.0)
+                .then(|| ((total - sent) as f64 / bytes_per_sec * 1000.0) as u64);
+            (self.on_event)(BootloaderEvent::Progress {
+                sent,
+                total,
+                bytes_per_sec,
+                eta_ms,
+                retries: self.retries,
+            });
+        }
+        self.log(LogLevel::Info, "固件数据发送完成".to_string(), None);
+
+        // 发送CRC值（如果启用），小端序
+        if use_crc {
+            let crc = calc_crc32(&firmware);
+            self.log(LogLevel::Info, format!("发送CRC值: 0x{:08X}", crc), None);
+            if let Err(e) = self.transact(FUNC_SEND_CRC, crc.to_le_bytes().to_vec()) {
+                self.log(LogLevel::Error, e.clone(), None);
+                return Err(e);
+            }
+        }
+
+        // 发送结束标志（数据长度为0）
+        if let Err(e) = self.transact(FUNC_SEND_DATA, Vec::new()) {
+            self.log(LogLevel::Error, e.clone(), None);
+            return Err(e);
+        }
+
+        self.log(LogLevel::Info, "固件下载完成".to_string(), None);
+        Ok(())
+    }
+}
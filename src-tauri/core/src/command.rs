@@ -0,0 +1,168 @@
+// 设备命令构造
+// 帧格式与校准命令一致：帧头 命令字 数据长度 数据... 0x00 校验和（默认帧头到数据段求和，范围可在协议配置中修改）
+// 上报间隔、PWM和设备信息的命令字由固件决定，取自协议配置
+
+use std::collections::HashMap;
+use crate::config::{ChecksumCoverage, ProtocolConfig};
+use crate::messages::{self, AppError};
+
+const FRAME_HEADER: u8 = 0x81;
+
+// 固件支持的上报频率（Hz）
+pub const SUPPORTED_REPORT_RATES: [u32; 8] = [10, 20, 25, 50, 100, 200, 500, 1000];
+
+pub fn build_frame(command: u8, data: &[u8], coverage: &ChecksumCoverage) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(data.len() + 5);
+    frame.push(FRAME_HEADER);
+    frame.push(command);
+    frame.push(data.len() as u8);
+    frame.extend_from_slice(data);
+
+    let crc = frame[coverage.range(frame.len())].iter().fold(0u8, |acc, &b| acc.wrapping_add(b));
+    frame.push(0x00);
+    frame.push(crc);
+    frame
+}
+
+fn not_configured(name: &str) -> AppError {
+    AppError::new(messages::COMMAND_NOT_CONFIGURED, format!("协议配置中未设置 {} 命令的命令字", name))
+        .param("command", name)
+}
+
+// LED控制命令：CC 编号 状态 BF，编号从1开始；固件支持调光时状态字节为亮度（PWM占空比），0 表示熄灭
+pub fn led_command(index: usize, on: bool, brightness: u8, protocol: &ProtocolConfig) -> Vec<u8> {
+    let level = match (on, protocol.commands.led_dimming) {
+        (false, _) => 0,
+        (true, true) => brightness,
+        (true, false) => 1,
+    };
+    vec![0xCC, (index + 1) as u8, level, 0xBF]
+}
+
+// PWM命令，数据为 通道编号（从1开始） 占空比（0-255）
+pub fn pwm_command(channel: usize, duty: u8, protocol: &ProtocolConfig) -> Result<Vec<u8>, AppError> {
+    let opcode = protocol.commands.pwm.ok_or_else(|| not_configured("pwm"))?;
+    Ok(build_frame(opcode, &[(channel + 1) as u8, duty], &protocol.command_checksum))
+}
+
+// 上报频率命令，数据为上报间隔（毫秒，小端序）
+pub fn report_rate_command(hz: u32, protocol: &ProtocolConfig) -> Result<Vec<u8>, AppError> {
+    let opcode = protocol.commands.report_interval.ok_or_else(|| not_configured("report_interval"))?;
+    if !SUPPORTED_REPORT_RATES.contains(&hz) {
+        let message = format!("不支持的上报频率 {} Hz，可选: {:?}", hz, SUPPORTED_REPORT_RATES);
+        return Err(AppError::new(messages::UNSUPPORTED_REPORT_RATE, message)
+            .param("hz", hz)
+            .param("supported", format!("{:?}", SUPPORTED_REPORT_RATES)));
+    }
+    let interval_ms = (1000 / hz) as u16;
+    Ok(build_frame(opcode, &interval_ms.to_le_bytes(), &protocol.command_checksum))
+}
+
+// 设备信息查询命令，没有数据；应答按 info_response 指定的布局解码
+pub fn info_command(protocol: &ProtocolConfig) -> Result<Vec<u8>, AppError> {
+    let opcode = protocol.commands.info.ok_or_else(|| not_configured("info"))?;
+    Ok(build_frame(opcode, &[], &protocol.command_checksum))
+}
+
+// 自定义命令模板：十六进制字节和占位符以空格分隔，如 "81 12 02 {channel} {duty} 00 {sum}"
+//   {name}         参数，1字节
+//   {name:u16le}   参数，2字节小端序（u16be 为大端序）
+//   {sum}          从第一个字节到此处的累加和（低8位）
+//   {xor}          从第一个字节到此处的异或校验
+// 新固件功能在界面支持之前即可通过模板使用
+pub fn render_template(pattern: &str, params: &HashMap<String, i64>) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    for token in pattern.split_whitespace() {
+        let Some(placeholder) = token.strip_prefix('{').and_then(|t| t.strip_suffix('}')) else {
+            let byte = u8::from_str_radix(token.trim_start_matches("0x"), 16)
+                .map_err(|_| format!("无效的字节: {}", token))?;
+            bytes.push(byte);
+            continue;
+        };
+        match placeholder {
+            "sum" => bytes.push(bytes.iter().fold(0u8, |acc, &b| acc.wrapping_add(b))),
+            "xor" => bytes.push(bytes.iter().fold(0u8, |acc, &b| acc ^ b)),
+            _ => {
+                let (name, format) = placeholder.split_once(':').unwrap_or((placeholder, "u8"));
+                let value = *params.get(name).ok_or_else(|| format!("缺少参数: {}", name))?;
+                match format {
+                    "u8" => bytes.push(u8::try_from(value).map_err(|_| format!("参数 {} 超出范围: {}", name, value))?),
+                    "u16le" | "u16be" => {
+                        let value = u16::try_from(value).map_err(|_| format!("参数 {} 超出范围: {}", name, value))?;
+                        if format == "u16le" {
+                            bytes.extend(value.to_le_bytes());
+                        } else {
+                            bytes.extend(value.to_be_bytes());
+                        }
+                    }
+                    _ => return Err(format!("不支持的参数格式: {}", format)),
+                }
+            }
+        }
+    }
+    if bytes.is_empty() {
+        return Err("命令模板为空".to_string());
+    }
+    Ok(bytes)
+}
+
+// 模板中用到的参数名
+pub fn template_params(pattern: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    for token in pattern.split_whitespace() {
+        let Some(placeholder) = token.strip_prefix('{').and_then(|t| t.strip_suffix('}')) else {
+            continue;
+        };
+        let name = placeholder.split(':').next().unwrap_or_default();
+        if name != "sum" && name != "xor" && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(values: &[(&str, i64)]) -> HashMap<String, i64> {
+        values.iter().map(|(name, value)| (name.to_string(), *value)).collect()
+    }
+
+    #[test]
+    fn info_command_requires_an_opcode() {
+        let mut protocol = ProtocolConfig::default();
+        assert!(info_command(&protocol).is_err());
+        protocol.commands.info = Some(0x20);
+        assert_eq!(info_command(&protocol).unwrap(), [0x81, 0x20, 0x00, 0x00, 0xA1]);
+    }
+
+    #[test]
+    fn renders_bytes_params_and_checksums() {
+        let bytes = render_template("81 12 02 {channel} {duty} 00 {sum}", &params(&[("channel", 1), ("duty", 0x80)])).unwrap();
+        assert_eq!(bytes, [0x81, 0x12, 0x02, 0x01, 0x80, 0x00, 0x16]);
+
+        let bytes = render_template("0xAA 0x0F {xor}", &HashMap::new()).unwrap();
+        assert_eq!(bytes, [0xAA, 0x0F, 0xA5]);
+    }
+
+    #[test]
+    fn renders_u16_params() {
+        let values = params(&[("ms", 0x1234)]);
+        assert_eq!(render_template("01 {ms:u16le}", &values).unwrap(), [0x01, 0x34, 0x12]);
+        assert_eq!(render_template("01 {ms:u16be}", &values).unwrap(), [0x01, 0x12, 0x34]);
+    }
+
+    #[test]
+    fn rejects_bad_templates() {
+        let values = params(&[("big", 256), ("neg", -1), ("huge", 0x1_0000)]);
+        for pattern in ["", "GG", "{missing}", "{big}", "{neg}", "{huge:u16le}", "{big:i32}"] {
+            assert!(render_template(pattern, &values).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn lists_params_once() {
+        assert_eq!(template_params("{a} {b:u16le} {a} {sum} {xor}"), ["a", "b"]);
+    }
+}
@@ -0,0 +1,2128 @@
+use chrono::NaiveTime;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::command;
+use crate::response;
+use crate::scheduler::CronExpr;
+use crate::watch::WatchExpr;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::time::Duration;
+
+// 串口流控
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowControl {
+    #[default]
+    None,
+    Hardware,  // RTS/CTS
+    Software,  // XON/XOFF
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SerialConfig {
+    pub port: String,
+    pub baud_rate: u32,
+    pub data_bits: u8,
+    pub stop_bits: u8,
+    pub parity: String,
+    #[serde(default)]
+    pub flow_control: FlowControl,
+    #[serde(default)]
+    pub dtr: Option<bool>,  // 打开后设置的 DTR 电平，None 保持驱动默认
+    #[serde(default)]
+    pub rts: Option<bool>,  // 打开后设置的 RTS 电平，None 保持驱动默认
+    #[serde(default = "default_read_timeout")]
+    pub read_timeout_ms: u64,  // 单次读取等待数据的时间，超时不算错误
+    #[serde(default = "default_read_size")]
+    pub read_size: usize,  // 单次读取的最大字节数，高速设备可调大以免数据积压
+    #[serde(default = "default_max_pending")]
+    pub max_pending: usize,  // 间隔/包分帧和转义时未结束数据的上限，超过后丢弃
+}
+
+fn default_read_timeout() -> u64 {
+    10
+}
+
+fn default_read_size() -> usize {
+    128
+}
+
+fn default_max_pending() -> usize {
+    1024
+}
+
+impl SerialConfig {
+    // 数据位 5~8，停止位 1 或 2，校验 None/Odd/Even（不区分大小写），读取设置在合理范围内
+    pub fn validate(&self) -> Result<(), String> {
+        if !(5..=8).contains(&self.data_bits) {
+            return Err(format!("数据位应为 5~8，当前为 {}", self.data_bits));
+        }
+        if self.stop_bits != 1 && self.stop_bits != 2 {
+            return Err(format!("停止位应为 1 或 2，当前为 {}", self.stop_bits));
+        }
+        if !["none", "odd", "even"].contains(&self.parity.to_ascii_lowercase().as_str()) {
+            return Err(format!("校验方式应为 None、Odd 或 Even，当前为 {}", self.parity));
+        }
+        if !(1..=5000).contains(&self.read_timeout_ms) {
+            return Err(format!("读取超时应为 1~5000 毫秒，当前为 {}", self.read_timeout_ms));
+        }
+        if !(1..=65536).contains(&self.read_size) {
+            return Err(format!("单次读取字节数应为 1~65536，当前为 {}", self.read_size));
+        }
+        if self.max_pending == 0 {
+            return Err("未结束数据的上限不能为 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SerialScreenConfig {
+    pub enabled: bool,
+    pub port: String,
+    pub baud_rate: u32,
+    pub data_bits: u8,
+    pub stop_bits: u8,
+    pub parity: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MatrixMapping {
+    pub last_received: String,
+    pub mute_status: bool,
+}
+
+// 一次读取至少要能容纳两帧
+pub const MAX_FRAME_LEN: usize = 64;
+
+// 帧校验方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumType {
+    Xor,  // 帧头到LED数据逐字节异或
+    Sum,  // 帧头到LED数据逐字节求和（取低8位）
+    None,  // 不校验
+}
+
+// 校验覆盖的字节范围，为相对帧首的偏移 [start, end)；end 为空时到校验字节之前
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ChecksumCoverage {
+    #[serde(default)]
+    pub start: usize,
+    #[serde(default)]
+    pub end: Option<usize>,
+}
+
+impl ChecksumCoverage {
+    // 长度为 len 的内容中被校验的范围，超出时截断
+    pub fn range(&self, len: usize) -> std::ops::Range<usize> {
+        let end = self.end.unwrap_or(len).min(len);
+        self.start.min(end)..end
+    }
+}
+
+// 帧的分隔方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Framing {
+    #[default]
+    Delimited,  // AA 帧头、BF 帧尾
+    Gap { idle_ms: u64 },  // 没有帧头帧尾，帧之间至少静默 idle_ms；需要连续读取串口才能可靠分帧
+    Cobs,  // COBS 编码的包，以 0x00 结束；发出的命令同样编码
+    Slip,  // SLIP 编码的包，以 0xC0 结束；发出的命令同样编码
+}
+
+impl Framing {
+    // 帧头帧尾各占的字节数
+    pub fn delimiter_len(&self) -> usize {
+        match self {
+            Framing::Delimited => 1,
+            Framing::Gap { .. } | Framing::Cobs | Framing::Slip => 0,
+        }
+    }
+
+    // 是否为 COBS/SLIP 包分帧
+    pub fn is_packet(&self) -> bool {
+        matches!(self, Framing::Cobs | Framing::Slip)
+    }
+}
+
+// 位图中字节内的位序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BitOrder {
+    #[default]
+    LsbFirst,  // 最低位为编号最小的输入
+    MsbFirst,  // 最高位为编号最小的输入
+}
+
+// 多字节位图的字节序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ByteOrder {
+    #[default]
+    Little,  // 第一个字节为编号 0~7
+    Big,  // 最后一个字节为编号 0~7
+}
+
+// 按键或LED位图的排列方式，固件改线后只需修改配置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BitLayout {
+    #[serde(default)]
+    pub bit_order: BitOrder,
+    #[serde(default)]
+    pub byte_order: ByteOrder,
+}
+
+impl BitLayout {
+    // 第 index 个输入在位图中的（字节，位）位置
+    pub fn position(&self, index: usize, bytes: usize) -> (usize, u8) {
+        let byte = match self.byte_order {
+            ByteOrder::Little => index / 8,
+            ByteOrder::Big => bytes - 1 - index / 8,
+        };
+        let bit = match self.bit_order {
+            BitOrder::LsbFirst => index % 8,
+            BitOrder::MsbFirst => 7 - index % 8,
+        };
+        (byte, bit as u8)
+    }
+}
+
+// 自定义字段的类型
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    Bitfield,  // length 为位数，按位解出布尔值
+    U8,
+    U16,
+    I16,
+}
+
+// 自定义字段：从帧中按偏移取出，解码为具名的值，供尚未内置的传感器数据使用
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct FieldSpec {
+    pub name: String,
+    pub offset: usize,  // 相对帧首（定界分帧时含 AA）的字节偏移
+    #[serde(default = "default_field_length")]
+    pub length: usize,  // 值的个数，大于1时解码为数组；位域为位数
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    #[serde(default)]
+    pub bits: BitLayout,  // 位域的位序，以及 u16/i16 的字节序
+}
+
+fn default_field_length() -> usize {
+    1
+}
+
+impl FieldSpec {
+    // 字段占用的字节数，长度过大溢出时为 None
+    pub fn byte_len(&self) -> Option<usize> {
+        match self.field_type {
+            FieldType::Bitfield => Some(self.length.div_ceil(8)),
+            FieldType::U8 => Some(self.length),
+            FieldType::U16 | FieldType::I16 => self.length.checked_mul(2),
+        }
+    }
+}
+
+// 固件扩展命令的命令字。协议文档只规定了LED命令，这些命令字由固件决定，
+// 未填写的命令不会发送
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct CommandCodes {
+    pub report_interval: Option<u8>,  // 设置上报间隔
+    pub pwm: Option<u8>,  // 设置PWM输出占空比
+    pub led_dimming: bool,  // LED命令的状态字节改为亮度（PWM占空比）；关闭时为 0/1
+    pub info: Option<u8>,  // 查询设备信息，无数据；连接后自动发送
+    pub info_response: Option<String>,  // 解码设备信息应答的布局名称（responses 中的一项）
+}
+
+// 矩阵数据帧结构：AA 序号 按键位图 ADC LED位图 校验 BF；间隔分帧和包分帧时没有 AA 和 BF
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ProtocolConfig {
+    pub key_count: usize,
+    pub adc_count: usize,
+    pub led_count: usize,
+    pub checksum: ChecksumType,
+    #[serde(default)]
+    pub framing: Framing,
+    #[serde(default)]
+    pub key_bits: BitLayout,
+    #[serde(default)]
+    pub led_bits: BitLayout,
+    #[serde(default)]
+    pub fields: Vec<FieldSpec>,
+    #[serde(default)]
+    pub checksum_coverage: ChecksumCoverage,  // 数据帧的校验范围，默认从帧首到校验字节之前
+    #[serde(default)]
+    pub command_checksum: ChecksumCoverage,  // 发出的命令帧的校验范围，默认从帧头到数据结束
+    #[serde(default)]
+    pub escape: bool,  // 帧内的 AA/BF/10 以 10 为前缀转义（原字节异或 0x20），仅用于定界分帧；内置的命令帧（LED、PWM、上报频率）同样转义，命令模板和校准命令原样发送
+    #[serde(default)]
+    pub commands: CommandCodes,
+}
+
+impl ProtocolConfig {
+    // 整帧长度；数量过大溢出时为 usize::MAX，由 validate 拒绝
+    pub fn frame_len(&self) -> usize {
+        self.checked_frame_len().unwrap_or(usize::MAX)
+    }
+
+    fn checked_frame_len(&self) -> Option<usize> {
+        let delimiters = 2 * self.framing.delimiter_len();
+        [self.key_count.div_ceil(8), self.adc_count, self.led_count.div_ceil(8), 1]
+            .into_iter()
+            .try_fold(delimiters + 1, usize::checked_add)
+    }
+
+    // 校验字节相对帧首的偏移，其后只有帧尾
+    pub fn checksum_offset(&self) -> usize {
+        self.frame_len() - 1 - self.framing.delimiter_len()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Framing::Gap { idle_ms: 0 } = self.framing {
+            return Err("间隔分帧的静默时间不能为 0".to_string());
+        }
+        if self.escape && self.framing != Framing::Delimited {
+            return Err("转义只能用于 AA/BF 定界分帧".to_string());
+        }
+        let frame_len = self.checked_frame_len().ok_or("按键、ADC 或 LED 数量过大，帧长度溢出")?;
+        if frame_len > MAX_FRAME_LEN {
+            return Err(format!(
+                "帧长度 {} 字节超过上限 {} 字节",
+                frame_len,
+                MAX_FRAME_LEN
+            ));
+        }
+        // 校验字节之后是帧尾，字段和校验范围只能位于校验字节之前
+        let payload_end = self.checksum_offset();
+        let coverage = &self.checksum_coverage;
+        if coverage.end.is_some_and(|end| end > payload_end) || coverage.start >= coverage.end.unwrap_or(payload_end) {
+            return Err(format!("校验范围无效，应满足 start < end <= {}", payload_end));
+        }
+        if self.command_checksum.end.is_some_and(|end| end <= self.command_checksum.start) {
+            return Err("命令帧的校验范围无效，应满足 start < end".to_string());
+        }
+        let opcodes = [self.commands.report_interval, self.commands.pwm, self.commands.info];
+        let opcodes: Vec<u8> = opcodes.into_iter().flatten().collect();
+        if opcodes.iter().enumerate().any(|(i, opcode)| opcodes[..i].contains(opcode)) {
+            return Err("上报间隔、PWM和设备信息的命令字不能相同".to_string());
+        }
+        if self.commands.info.is_some() != self.commands.info_response.is_some() {
+            return Err("设备信息命令字和应答布局需要同时设置".to_string());
+        }
+        let mut names = HashSet::new();
+        for field in &self.fields {
+            if field.name.is_empty() || !names.insert(field.name.as_str()) {
+                return Err(format!("字段名称为空或重复: \"{}\"", field.name));
+            }
+            if field.length == 0 {
+                return Err(format!("字段 {} 的长度不能为 0", field.name));
+            }
+            let end = field.byte_len().and_then(|len| field.offset.checked_add(len));
+            if end.is_none_or(|end| end > payload_end) {
+                return Err(format!("字段 {} 超出帧的数据部分（{} 字节）", field.name, payload_end));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for ProtocolConfig {
+    fn default() -> Self {
+        Self {
+            key_count: 24,
+            adc_count: 14,
+            led_count: 20,
+            checksum: ChecksumType::Xor,
+            framing: Framing::Delimited,
+            key_bits: BitLayout::default(),
+            led_bits: BitLayout::default(),
+            fields: Vec::new(),
+            checksum_coverage: ChecksumCoverage::default(),
+            command_checksum: ChecksumCoverage::default(),
+            escape: false,
+            commands: CommandCodes::default(),
+        }
+    }
+}
+
+// 单个输入的显示属性，均为可选
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct InputMeta {
+    #[serde(default)]
+    pub color: Option<String>,  // #RGB 或 #RRGGBB
+    #[serde(default)]
+    pub icon: Option<String>,  // 图标名称
+    #[serde(default)]
+    pub group: Option<String>,  // 分组/类别
+}
+
+fn is_valid_color(color: &str) -> bool {
+    color.strip_prefix('#').is_some_and(|hex| {
+        (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+// 界面显示用的名称，数量需与协议结构一致
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LabelsConfig {
+    pub keys: Vec<String>,  // 按键名称
+    pub adcs: Vec<String>,  // ADC名称
+    pub leds: Vec<String>,  // LED名称
+    // 显示属性，按编号（从0开始）记录，未设置的输入不出现
+    #[serde(default)]
+    pub key_meta: HashMap<usize, InputMeta>,
+    #[serde(default)]
+    pub adc_meta: HashMap<usize, InputMeta>,
+    #[serde(default)]
+    pub led_meta: HashMap<usize, InputMeta>,
+}
+
+impl LabelsConfig {
+    pub fn for_protocol(protocol: &ProtocolConfig) -> Self {
+        let mut labels = Self {
+            keys: Vec::new(),
+            adcs: Vec::new(),
+            leds: Vec::new(),
+            key_meta: HashMap::new(),
+            adc_meta: HashMap::new(),
+            led_meta: HashMap::new(),
+        };
+        labels.resize(protocol);
+        labels
+    }
+
+    // 按协议数量截断或补齐默认名称
+    pub fn resize(&mut self, protocol: &ProtocolConfig) {
+        let fit = |names: &mut Vec<String>, count: usize, prefix: &str| {
+            names.truncate(count);
+            for i in names.len()..count {
+                names.push(format!("{} {}", prefix, i + 1));
+            }
+        };
+        fit(&mut self.keys, protocol.key_count, "按键");
+        fit(&mut self.adcs, protocol.adc_count, "ADC");
+        fit(&mut self.leds, protocol.led_count, "LED");
+        self.key_meta.retain(|&i, _| i < protocol.key_count);
+        self.adc_meta.retain(|&i, _| i < protocol.adc_count);
+        self.led_meta.retain(|&i, _| i < protocol.led_count);
+    }
+
+    // 检查名称数量是否与协议一致
+    pub fn check(&self, protocol: &ProtocolConfig) -> Result<(), String> {
+        let counts = [
+            ("labels.keys", self.keys.len(), protocol.key_count),
+            ("labels.adcs", self.adcs.len(), protocol.adc_count),
+            ("labels.leds", self.leds.len(), protocol.led_count),
+        ];
+        let mut problems: Vec<String> = counts
+            .iter()
+            .filter(|(_, len, expected)| len != expected)
+            .map(|(field, len, expected)| format!("{} 数量为 {}，应为 {}", field, len, expected))
+            .collect();
+        let metas = [
+            ("labels.key_meta", &self.key_meta, protocol.key_count),
+            ("labels.adc_meta", &self.adc_meta, protocol.adc_count),
+            ("labels.led_meta", &self.led_meta, protocol.led_count),
+        ];
+        for (field, metas, count) in metas {
+            let mut indices: Vec<_> = metas.keys().collect();
+            indices.sort();
+            for index in indices {
+                if *index >= count {
+                    problems.push(format!("{} 引用了不存在的编号 {}", field, index + 1));
+                }
+                if let Some(color) = &metas[index].color {
+                    if !is_valid_color(color) {
+                        problems.push(format!("{} 编号 {} 的颜色无效: {}", field, index + 1, color));
+                    }
+                }
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("；"))
+        }
+    }
+}
+
+impl Default for LabelsConfig {
+    fn default() -> Self {
+        Self::for_protocol(&ProtocolConfig::default())
+    }
+}
+
+// 面板上一个按键/LED的位置，单位为网格
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LayoutCell {
+    pub row: u32,
+    pub col: u32,
+    #[serde(default = "default_cell_size")]
+    pub width: u32,
+    #[serde(default = "default_cell_size")]
+    pub height: u32,
+}
+
+fn default_cell_size() -> u32 {
+    1
+}
+
+impl LayoutCell {
+    fn overlaps(&self, other: &LayoutCell) -> bool {
+        self.row < other.row + other.height
+            && other.row < self.row + self.height
+            && self.col < other.col + other.width
+            && other.col < self.col + self.width
+    }
+}
+
+// 默认布局每行的格数
+const LAYOUT_COLUMNS: usize = 8;
+
+// 面板物理布局，按编号排列，数量需与协议结构一致
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LayoutConfig {
+    pub keys: Vec<LayoutCell>,
+    pub leds: Vec<LayoutCell>,
+}
+
+impl LayoutConfig {
+    pub fn for_protocol(protocol: &ProtocolConfig) -> Self {
+        let mut layout = Self {
+            keys: Vec::new(),
+            leds: Vec::new(),
+        };
+        layout.resize(protocol);
+        layout
+    }
+
+    // 按协议数量截断，新增的格子接在已有布局下方按行排列
+    pub fn resize(&mut self, protocol: &ProtocolConfig) {
+        let fit = |cells: &mut Vec<LayoutCell>, count: usize| {
+            cells.truncate(count);
+            let start_row = cells.iter().map(|c| c.row + c.height).max().unwrap_or(0);
+            let existing = cells.len();
+            for i in existing..count {
+                let n = i - existing;
+                cells.push(LayoutCell {
+                    row: start_row + (n / LAYOUT_COLUMNS) as u32,
+                    col: (n % LAYOUT_COLUMNS) as u32,
+                    width: 1,
+                    height: 1,
+                });
+            }
+        };
+        fit(&mut self.keys, protocol.key_count);
+        fit(&mut self.leds, protocol.led_count);
+    }
+
+    // 检查数量、尺寸以及同类格子是否重叠
+    pub fn check(&self, protocol: &ProtocolConfig) -> Result<(), String> {
+        let mut problems = Vec::new();
+        let groups = [
+            ("layout.keys", &self.keys, protocol.key_count),
+            ("layout.leds", &self.leds, protocol.led_count),
+        ];
+        for (field, cells, count) in groups {
+            if cells.len() != count {
+                problems.push(format!("{} 数量为 {}，应为 {}", field, cells.len(), count));
+            }
+            for (i, cell) in cells.iter().enumerate() {
+                if cell.width == 0 || cell.height == 0 {
+                    problems.push(format!("{} 编号 {} 的尺寸不能为0", field, i + 1));
+                }
+                for (j, other) in cells.iter().enumerate().skip(i + 1) {
+                    if cell.overlaps(other) {
+                        problems.push(format!("{} 编号 {} 与 {} 位置重叠", field, i + 1, j + 1));
+                    }
+                }
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("；"))
+        }
+    }
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self::for_protocol(&ProtocolConfig::default())
+    }
+}
+
+// 摇杆轴参数：中心值与死区，所有轴相关的绑定共用
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AxisSettings {
+    pub center: u8,
+    pub deadzone: u8,
+    #[serde(default)]
+    pub min: u8,  // 行程的最小读数
+    #[serde(default = "default_axis_max")]
+    pub max: u8,  // 行程的最大读数
+}
+
+fn default_axis_max() -> u8 {
+    255
+}
+
+impl Default for AxisSettings {
+    fn default() -> Self {
+        Self {
+            center: 0x80,
+            deadzone: 8,
+            min: 0,
+            max: default_axis_max(),
+        }
+    }
+}
+
+// 行程学习：使用中记录各轴实际到达的范围，与配置相差 threshold 以上时提示更新
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RangeLearningConfig {
+    pub enabled: bool,
+    pub threshold: u8,  // 提示所需的最小差值（ADC计数）
+    pub min_samples: u64,  // 至少观测这么多帧后才提示
+    pub min_span: u8,  // 读数变化小于此值的通道视为未使用
+}
+
+impl Default for RangeLearningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 10,
+            min_samples: 1000,
+            min_span: 64,
+        }
+    }
+}
+
+// 温漂补偿：按键全部松开且轴在死区内时缓慢跟踪零点漂移
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DriftConfig {
+    pub enabled: bool,
+    pub rate: f32,  // 每帧向当前读数靠拢的比例
+    pub max_offset: u8,  // 零点偏移的上限（ADC计数）
+}
+
+impl Default for DriftConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rate: 0.001,
+            max_offset: 12,
+        }
+    }
+}
+
+// ADC直方图的区间数量，查询时把 0-255 的读数均分为若干区间
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HistogramConfig {
+    pub bins: usize,
+}
+
+impl Default for HistogramConfig {
+    fn default() -> Self {
+        Self { bins: 32 }
+    }
+}
+
+// LED跟随按键：按键按下时点亮对应的LED，松开时熄灭，完全由上位机控制；
+// 未配置对应关系时第 n 个按键对应第 n 个LED
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct LedMirrorConfig {
+    pub enabled: bool,
+    #[serde(default)]
+    pub mapping: Vec<LedMirror>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LedMirror {
+    pub key: usize,
+    pub led: usize,
+}
+
+impl LedMirrorConfig {
+    // 实际生效的对应关系
+    pub fn pairs(&self, protocol: &ProtocolConfig) -> Vec<LedMirror> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        if !self.mapping.is_empty() {
+            return self.mapping.clone();
+        }
+        (0..protocol.key_count.min(protocol.led_count))
+            .map(|index| LedMirror { key: index, led: index })
+            .collect()
+    }
+}
+
+// 自定义设备命令，模板格式见 command::render_template
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CommandTemplate {
+    pub name: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+// 设备应答的布局：以 header 开头、共 length 字节的数据按 fields 解码为具名数值，
+// header 为十六进制字节，如 "AA 21"；command 为对应的命令模板名称，仅用于界面展示
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ResponseLayout {
+    pub name: String,
+    pub header: String,
+    pub length: usize,
+    pub fields: Vec<ResponseField>,
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+// 应答字段：value = raw * scale + bias；format 为 u8/i8/u16le/u16be/i16le/i16be/u32le/u32be/i32le/i32be
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ResponseField {
+    pub name: String,
+    pub offset: usize,
+    #[serde(default = "default_field_format")]
+    pub format: String,
+    #[serde(default = "default_field_scale")]
+    pub scale: f64,
+    #[serde(default)]
+    pub bias: f64,
+    #[serde(default)]
+    pub unit: String,
+}
+
+fn default_field_format() -> String {
+    "u8".to_string()
+}
+
+fn default_field_scale() -> f64 {
+    1.0
+}
+
+// 游戏状态来源：定时读取 HTTP 接口返回的 JSON，或接收发到 UDP 端口的 JSON 数据报，
+// 按 JSON Pointer 取出字段，字段以 "来源.字段" 命名，例如由模拟飞行网关提供起落架、襟翼状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct GameStateConfig {
+    #[serde(default)]
+    pub sources: Vec<GameStateSource>,
+    #[serde(default)]
+    pub leds: Vec<GameStateLed>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GameStateSource {
+    pub name: String,
+    pub transport: GameStateTransport,
+    #[serde(default = "default_game_state_interval")]
+    pub interval_ms: u64,  // HTTP 轮询间隔
+    pub fields: Vec<GameStateField>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GameStateTransport {
+    Http { url: String },  // 如 "http://127.0.0.1:8080/state"
+    Udp { bind: String },  // 监听地址，如 "127.0.0.1:9000"
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GameStateField {
+    pub name: String,
+    pub pointer: String,  // JSON Pointer，如 "/gear/down"
+}
+
+// 字段值为真（true、非零数字、非空字符串）时点亮LED
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GameStateLed {
+    pub value: String,  // "来源.字段"
+    pub led: usize,
+}
+
+fn default_game_state_interval() -> u64 {
+    250
+}
+
+// PWM输出：设备有 channels 个PWM输出，每个通道每秒最多发送 max_rate_hz 次命令；
+// sources 中的通道跟随ADC读数，占空比 = min_duty + (max_duty - min_duty) * ADC / 255
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PwmConfig {
+    pub channels: usize,
+    pub max_rate_hz: u32,
+    #[serde(default)]
+    pub sources: Vec<PwmSource>,
+}
+
+impl Default for PwmConfig {
+    fn default() -> Self {
+        Self {
+            channels: 2,
+            max_rate_hz: 20,
+            sources: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PwmSource {
+    pub channel: usize,
+    pub adc: usize,
+    #[serde(default)]
+    pub min_duty: u8,
+    #[serde(default = "default_max_duty")]
+    pub max_duty: u8,
+}
+
+fn default_max_duty() -> u8 {
+    255
+}
+
+impl PwmSource {
+    pub fn duty(&self, adc: u8) -> u8 {
+        let span = self.max_duty as i32 - self.min_duty as i32;
+        (self.min_duty as i32 + span * adc as i32 / 255) as u8
+    }
+}
+
+// 暂停输出：按下 key 切换暂停，暂停期间串口保持连接，但不产生按键等系统输出；
+// leds 为 true 时LED也停止更新
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct PauseConfig {
+    pub key: Option<usize>,
+    pub leds: bool,
+}
+
+// 安全键：标记为 guarded 的绑定只有在按住安全键时才执行，
+// 或在按下安全键后 arm_window_ms 内执行一次（为 0 时必须按住）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SafetyConfig {
+    pub key: Option<usize>,
+    #[serde(default)]
+    pub arm_window_ms: u64,
+}
+
+// 鬼键检测：按键编号按 行 * columns + 列 对应到矩阵位置，未设置列数时不检测
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GhostingConfig {
+    pub columns: Option<usize>,
+    pub threshold: u32,  // 同一组合出现多少次后提示
+}
+
+impl Default for GhostingConfig {
+    fn default() -> Self {
+        Self {
+            columns: None,
+            threshold: 3,
+        }
+    }
+}
+
+// ADC物理量换算：value = c0 + c1*raw + c2*raw^2 + ...
+// 线性换算只需两个系数，例如电池电压 [0.0, 0.0322]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AdcScaling {
+    pub channel: usize,
+    pub coefficients: Vec<f64>,
+    #[serde(default)]
+    pub unit: String,  // 单位，如 "V"、"°C"
+}
+
+impl AdcScaling {
+    pub fn apply(&self, raw: u8) -> f64 {
+        // 霍纳法则计算多项式
+        let x = raw as f64;
+        self.coefficients.iter().rev().fold(0.0, |acc, c| acc * x + c)
+    }
+}
+
+// 历史记录保留策略
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    pub adc_sample_interval_ms: u64,  // ADC采样记录间隔
+    pub key_retention_days: u32,  // 各类记录的保留天数
+    pub connection_retention_days: u32,
+    pub adc_retention_days: u32,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            adc_sample_interval_ms: 1000,
+            key_retention_days: 30,
+            connection_retention_days: 90,
+            adc_retention_days: 7,
+        }
+    }
+}
+
+// 绑定触发后执行的动作
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActionConfig {
+    KeyCombo { keys: String },  // 发送组合键，如 "Ctrl+Shift+M"
+    ToggleWindow,  // 显示/隐藏主窗口
+    CopySelection,  // 复制当前选中内容
+    PasteClipboard,  // 粘贴剪贴板内容
+    PasteSnippet { index: usize },  // 粘贴预存的文本片段
+    FocusApp { name: String },  // 聚焦标题/名称包含 name 的应用窗口
+    MinimizeWindow,  // 最小化前台窗口
+    MaximizeWindow,  // 最大化前台窗口
+    SwitchDesktop { next: bool },  // 切换到下一个/上一个虚拟桌面
+    PlayMacro { index: usize },  // 按录制时的间隔回放宏
+    SetLed { index: usize, on: bool },  // 点亮/熄灭指定LED
+    SetPwm { channel: usize, duty: u8 },  // 设置PWM输出的占空比
+    Scroll { lines: i32 },  // 滚动鼠标滚轮，正数向下
+    SendCommand {
+        name: String,
+        #[serde(default)]
+        params: HashMap<String, i64>,
+    },  // 发送自定义命令模板
+    AllLedsOff,  // 熄灭所有LED
+    Sequence { steps: Vec<SequenceStep> },  // 按顺序执行多个动作，步骤之间可以等待
+    SetVariable { name: String, value: i64 },  // 设置变量
+    AddVariable { name: String, delta: i64 },  // 变量加上 delta（计数器），不存在时从 0 开始
+    ToggleVariable { name: String },  // 变量在 0 和 1 之间切换
+}
+
+// 序列中的一步：等待 delay_ms 后检查条件，满足时执行动作，不满足时中止整个序列
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SequenceStep {
+    #[serde(default)]
+    pub delay_ms: u64,
+    pub action: ActionConfig,
+    #[serde(default)]
+    pub condition: Option<Condition>,
+}
+
+// 序列步骤和定时任务的执行条件：设备输入、时间段和主机状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    KeyHeld { key: usize },  // 按键仍按住，如松开按键即中止
+    KeyReleased { key: usize },
+    TimeBetween { start: String, end: String },  // 本地时间 "HH:MM"，含 start 不含 end；start 晚于 end 时跨午夜
+    OnAcPower,
+    OnBattery,
+    NetworkOnline,
+    NetworkOffline,
+    MicrophoneInUse,
+    MicrophoneIdle,
+}
+
+// 主机状态，由应用定期采样；无法获取的项为 None，相关条件视为不满足
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct HostState {
+    pub on_battery: Option<bool>,
+    pub network_online: Option<bool>,
+    pub microphone_in_use: Option<bool>,
+}
+
+pub fn parse_time_of_day(text: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(text.trim(), "%H:%M").map_err(|_| format!("无效的时间: {}，应为 HH:MM", text))
+}
+
+impl Condition {
+    pub fn key(&self) -> Option<usize> {
+        match self {
+            Condition::KeyHeld { key } | Condition::KeyReleased { key } => Some(*key),
+            _ => None,
+        }
+    }
+
+    // 按当前的按键状态、主机状态和本地时间判断，按键不存在时视为未按下
+    pub fn is_met(&self, keys: &[bool], host: &HostState, now: NaiveTime) -> bool {
+        let pressed = |key: &usize| keys.get(*key).copied().unwrap_or(false);
+        match self {
+            Condition::KeyHeld { key } => pressed(key),
+            Condition::KeyReleased { key } => !pressed(key),
+            Condition::TimeBetween { start, end } => match (parse_time_of_day(start), parse_time_of_day(end)) {
+                (Ok(start), Ok(end)) if start <= end => start <= now && now < end,
+                (Ok(start), Ok(end)) => now >= start || now < end,
+                _ => false,
+            },
+            Condition::OnAcPower => host.on_battery == Some(false),
+            Condition::OnBattery => host.on_battery == Some(true),
+            Condition::NetworkOnline => host.network_online == Some(true),
+            Condition::NetworkOffline => host.network_online == Some(false),
+            Condition::MicrophoneInUse => host.microphone_in_use == Some(true),
+            Condition::MicrophoneIdle => host.microphone_in_use == Some(false),
+        }
+    }
+}
+
+impl ActionConfig {
+    // 是否回放宏，包括序列中的步骤
+    pub fn plays_macro(&self) -> bool {
+        match self {
+            ActionConfig::PlayMacro { .. } => true,
+            ActionConfig::Sequence { steps } => steps.iter().any(|step| step.action.plays_macro()),
+            _ => false,
+        }
+    }
+
+    // 动作类型名称，与配置中的 type 一致
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ActionConfig::KeyCombo { .. } => "key_combo",
+            ActionConfig::ToggleWindow => "toggle_window",
+            ActionConfig::CopySelection => "copy_selection",
+            ActionConfig::PasteClipboard => "paste_clipboard",
+            ActionConfig::PasteSnippet { .. } => "paste_snippet",
+            ActionConfig::FocusApp { .. } => "focus_app",
+            ActionConfig::MinimizeWindow => "minimize_window",
+            ActionConfig::MaximizeWindow => "maximize_window",
+            ActionConfig::SwitchDesktop { .. } => "switch_desktop",
+            ActionConfig::PlayMacro { .. } => "play_macro",
+            ActionConfig::SetLed { .. } => "set_led",
+            ActionConfig::SetPwm { .. } => "set_pwm",
+            ActionConfig::Scroll { .. } => "scroll",
+            ActionConfig::SendCommand { .. } => "send_command",
+            ActionConfig::AllLedsOff => "all_leds_off",
+            ActionConfig::Sequence { .. } => "sequence",
+            ActionConfig::SetVariable { .. } => "set_variable",
+            ActionConfig::AddVariable { .. } => "add_variable",
+            ActionConfig::ToggleVariable { .. } => "toggle_variable",
+        }
+    }
+}
+
+pub const ACTION_KINDS: [&str; 19] = [
+    "key_combo", "toggle_window", "copy_selection", "paste_clipboard", "paste_snippet",
+    "focus_app", "minimize_window", "maximize_window", "switch_desktop", "play_macro",
+    "set_led", "set_pwm", "scroll", "send_command", "all_leds_off", "sequence",
+    "set_variable", "add_variable", "toggle_variable",
+];
+
+// 变量存储：动作可以设置、累加和切换的整数变量，
+// 监视表达式中写作 $name，命令模板中写作 {$name}
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct VariablesConfig {
+    #[serde(default)]
+    pub persist: bool,  // 变量变化时写回 values 并保存配置，下次启动时恢复
+    #[serde(default)]
+    pub values: BTreeMap<String, i64>,  // 启动时的初始值
+}
+
+// 变量名只能包含字母、数字和下划线，且不以数字开头，才能在表达式和模板中引用
+pub fn is_variable_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// 动作的冷却和限速，防止抖动的开关在短时间内反复触发动作
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ActionLimits {
+    #[serde(default)]
+    pub cooldown_ms: u64,  // 同一绑定两次执行同类动作的最小间隔，0 表示不限制
+    #[serde(default)]
+    pub per_action: HashMap<String, u64>,  // 按动作类型覆盖冷却时间，如 {"play_macro": 1000}
+    #[serde(default)]
+    pub max_per_second: u32,  // 全局每秒最多执行的动作数，0 表示不限制
+}
+
+impl ActionLimits {
+    // 某类动作的冷却时间
+    pub fn cooldown(&self, kind: &str) -> Duration {
+        Duration::from_millis(self.per_action.get(kind).copied().unwrap_or(self.cooldown_ms))
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_led_brightness() -> u8 {
+    255
+}
+
+// Simon 质检模式的轮数和每轮限时
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SimonConfig {
+    pub rounds: u32,
+    pub timeout_ms: u64,
+}
+
+impl Default for SimonConfig {
+    fn default() -> Self {
+        Self {
+            rounds: 10,
+            timeout_ms: 3000,
+        }
+    }
+}
+
+// 重连间隔的增长方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Backoff {
+    Fixed,  // 固定为初始间隔
+    Linear,  // 初始间隔 × 次数
+    Exponential,  // 初始间隔 × 2^(次数-1)
+}
+
+// 达到最大次数后的处理
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GiveUpBehavior {
+    StayDisconnected,  // 停止重连
+    KeepPolling,  // 继续以最大间隔重试
+}
+
+// 连接意外断开后的自动重连策略
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RetryPolicy {
+    pub enabled: bool,
+    pub max_attempts: u32,
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub backoff: Backoff,
+    pub give_up: GiveUpBehavior,
+}
+
+impl RetryPolicy {
+    // 第 attempt 次（从1开始）重试前的等待时间
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = match self.backoff {
+            Backoff::Fixed => 1,
+            Backoff::Linear => attempt.max(1) as u64,
+            Backoff::Exponential => 1u64 << attempt.saturating_sub(1).min(16),
+        };
+        let delay_ms = self.initial_delay_ms.saturating_mul(factor).min(self.max_delay_ms);
+        Duration::from_millis(delay_ms)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_attempts: 10,
+            initial_delay_ms: 500,
+            max_delay_ms: 10_000,
+            backoff: Backoff::Exponential,
+            give_up: GiveUpBehavior::StayDisconnected,
+        }
+    }
+}
+
+// 老化测试的时长与通过阈值
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SoakConfig {
+    pub duration_secs: u64,
+    pub min_frames: u64,
+    pub max_frame_errors: u64,
+    pub max_reconnects: u64,
+    pub max_gap_ms: u64,
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        Self {
+            duration_secs: 3600,
+            min_frames: 1,
+            max_frame_errors: 0,
+            max_reconnects: 0,
+            max_gap_ms: 200,
+        }
+    }
+}
+
+// 监视表达式，如 "keys[3] && adc[1] > 128"
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WatchConfig {
+    pub name: String,
+    pub expr: String,
+}
+
+// 定时任务
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScheduleConfig {
+    pub name: String,
+    pub cron: String,  // 分 时 日 月 周，如 "0 23 * * *"
+    pub action: ActionConfig,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub condition: Option<Condition>,  // 到时间时条件不满足则跳过本次执行
+}
+
+// Webhook：events 中的事件发生时向 url POST JSON。body 为空时发送事件的全部变量；
+// 否则作为模板，{event}、{key}、{port}、{bank}、{timestamp} 等变量按 JSON 字符串转义后替换。
+// 请求失败时每隔 retry_delay_ms 重试，共尝试 attempts 次
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookConfig {
+    pub name: String,
+    pub url: String,
+    pub events: Vec<WebhookTrigger>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default = "default_webhook_attempts")]
+    pub attempts: u32,
+    #[serde(default = "default_webhook_retry_delay")]
+    pub retry_delay_ms: u64,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub cooldown_ms: u64,  // 两次发送的最小间隔，间隔内的事件被丢弃；0 表示不限制
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WebhookTrigger {
+    KeyPressed { key: usize },
+    KeyReleased { key: usize },
+    Connected,
+    Disconnected,  // 任何原因的断开
+    ConnectionLost,  // 仅意外断开
+    BankChanged,
+    ProfileChanged,  // 整个配置被替换，如切换配置方案
+}
+
+fn default_webhook_attempts() -> u32 {
+    3
+}
+
+fn default_webhook_retry_delay() -> u64 {
+    2000
+}
+
+// Discord 状态：在 Discord 个人资料中显示设备连接状态和当前分组，
+// app_id 为在 Discord 开发者后台创建的应用 ID
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DiscordConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub app_id: String,
+}
+
+// 绑定分组：由旋钮选择当前生效的一组，叠加在全局绑定之上
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BankConfig {
+    pub name: String,
+    #[serde(default)]
+    pub bindings: Vec<BindingConfig>,
+    #[serde(default)]
+    pub led: Option<usize>,  // 该分组生效时点亮的LED
+}
+
+// 宏的一步：按下的按键及距上一步的间隔
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct MacroStep {
+    pub key: usize,
+    pub delay_ms: u64,
+}
+
+// 从设备录制的按键序列，回放时依次触发各按键绑定的动作
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct MacroConfig {
+    pub name: String,
+    pub steps: Vec<MacroStep>,
+}
+
+// 输入绑定：把按键/ADC映射为系统输出
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BindingConfig {
+    // 按键按下时执行动作
+    KeyPress {
+        key: usize,
+        action: ActionConfig,
+        #[serde(default)]
+        guarded: bool,  // 需要同时按住安全键（或刚按过安全键）才执行
+    },
+    // 轴偏移越大，按键重复频率越高（例如推得越远缩放越快）
+    AxisRepeat {
+        channel: usize,
+        #[serde(default)]
+        positive_key: Option<String>,  // 正向偏移时发送的按键，如 "Ctrl+Plus"
+        #[serde(default)]
+        negative_key: Option<String>,  // 负向偏移时发送的按键
+        min_rate: f32,  // 刚离开死区时的频率（次/秒）
+        max_rate: f32,  // 满偏时的频率（次/秒）
+    },
+    // 两段式扳机：偏移量越过第一阈值触发动作1，越过第二阈值触发动作2（类似相机半按/全按）
+    DualStage {
+        channel: usize,
+        stage1_threshold: f32,  // 偏移量阈值 0.0 ~ 1.0
+        stage2_threshold: f32,
+        #[serde(default = "default_hysteresis")]
+        hysteresis: f32,  // 回落超过该值才退出当前阶段，避免抖动
+        #[serde(default)]
+        stage1_action: Option<ActionConfig>,
+        #[serde(default)]
+        stage2_action: Option<ActionConfig>,
+        #[serde(default)]
+        stage1_led: Option<usize>,  // 处于第一阶段及以上时点亮的LED
+        #[serde(default)]
+        stage2_led: Option<usize>,  // 处于第二阶段时点亮的LED
+        #[serde(default)]
+        guarded: bool,  // 动作需要安全键，LED不受影响
+    },
+    // 无限位编码器：读数按回绕计算有符号增量，每转过 step 个读数执行一次对应方向的动作
+    Encoder {
+        channel: usize,
+        #[serde(default = "default_encoder_step")]
+        step: u8,
+        #[serde(default)]
+        increment_action: Option<ActionConfig>,  // 读数增大（顺时针）
+        #[serde(default)]
+        decrement_action: Option<ActionConfig>,
+    },
+    // 快速拨动：window_ms 内读数朝 direction 变化超过 min_delta 时触发（如快速下拉音量推子静音）
+    Flick {
+        channel: usize,
+        direction: GestureDirection,
+        min_delta: u8,
+        #[serde(default = "default_gesture_window")]
+        window_ms: u64,
+        action: ActionConfig,
+        #[serde(default)]
+        guarded: bool,
+    },
+    // 快速旋转：无限位旋钮在 window_ms 内朝 direction 累计转过 min_travel 时触发，
+    // 读数从 255 回绕到 0 按最短方向计算
+    Spin {
+        channel: usize,
+        direction: GestureDirection,
+        min_travel: u32,
+        #[serde(default = "default_gesture_window")]
+        window_ms: u64,
+        action: ActionConfig,
+        #[serde(default)]
+        guarded: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GestureDirection {
+    Up,  // 读数增大（旋钮为顺时针）
+    Down,
+}
+
+fn default_encoder_step() -> u8 {
+    8
+}
+
+fn default_gesture_window() -> u64 {
+    200
+}
+
+impl BindingConfig {
+    pub fn is_guarded(&self) -> bool {
+        match self {
+            BindingConfig::KeyPress { guarded, .. }
+            | BindingConfig::DualStage { guarded, .. }
+            | BindingConfig::Flick { guarded, .. }
+            | BindingConfig::Spin { guarded, .. } => *guarded,
+            BindingConfig::AxisRepeat { .. } | BindingConfig::Encoder { .. } => false,
+        }
+    }
+
+    // 绑定会执行的动作
+    pub fn actions(&self) -> Vec<&ActionConfig> {
+        match self {
+            BindingConfig::KeyPress { action, .. }
+            | BindingConfig::Flick { action, .. }
+            | BindingConfig::Spin { action, .. } => vec![action],
+            BindingConfig::DualStage { stage1_action, stage2_action, .. } => {
+                stage1_action.iter().chain(stage2_action.iter()).collect()
+            }
+            BindingConfig::Encoder { increment_action, decrement_action, .. } => {
+                increment_action.iter().chain(decrement_action.iter()).collect()
+            }
+            BindingConfig::AxisRepeat { .. } => Vec::new(),
+        }
+    }
+}
+
+fn default_hysteresis() -> f32 {
+    0.05
+}
+
+// 多设备聚合：主设备之后依次拼接 devices 中各设备的按键和 ADC，合并为一个逻辑设备，
+// 例如两台 24 键的设备时按键 1-24 来自主设备、25-48 来自第一台附加设备。
+// 合并后的输入按主设备的绑定处理，聚合设备自己的绑定不再生效；LED 仍属于各自的设备
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AggregateConfig {
+    pub enabled: bool,
+    pub devices: Vec<String>,  // 参与聚合的设备 id，按拼接顺序
+}
+
+impl AggregateConfig {
+    // 合并的设备数量（含主设备）
+    pub fn sources(&self) -> usize {
+        if self.enabled {
+            1 + self.devices.len()
+        } else {
+            1
+        }
+    }
+}
+
+// 启动时配置文件的加载情况
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ConfigLoadStatus {
+    Loaded,
+    Missing,  // 没有配置文件，使用默认配置
+    Repaired { repairs: Vec<String> },  // 协议或串口设置无效，已换成默认值；其余问题由启动检查报告
+    RecoveredFromBackup { error: String, corrupted_copy: String },
+    Defaulted { error: String, corrupted_copy: String },  // 配置文件和备份都无法解析
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MatrixConfig {
+    #[serde(default)]
+    pub protocol: ProtocolConfig,  // 帧结构
+    pub serial_matrix: SerialConfig,
+    pub serial_screen: SerialScreenConfig,  // 屏幕串口配置
+    #[serde(default)]
+    pub labels: LabelsConfig,  // 界面名称
+    #[serde(default)]
+    pub layout: LayoutConfig,  // 面板物理布局
+    #[serde(default = "default_axis_settings")]
+    pub axis_settings: Vec<AxisSettings>,  // 每个ADC通道的轴参数
+    #[serde(default)]
+    pub drift: DriftConfig,  // 温漂补偿
+    #[serde(default)]
+    pub histogram: HistogramConfig,  // ADC直方图
+    #[serde(default)]
+    pub range_learning: RangeLearningConfig,  // 行程学习
+    #[serde(default)]
+    pub ghosting: GhostingConfig,  // 鬼键检测
+    #[serde(default)]
+    pub led_mirror: LedMirrorConfig,  // LED跟随按键
+    #[serde(default)]
+    pub pause: PauseConfig,  // 暂停输出
+    #[serde(default)]
+    pub safety: SafetyConfig,  // 安全键
+    #[serde(default)]
+    pub pwm: PwmConfig,  // PWM输出
+    #[serde(default)]
+    pub commands: Vec<CommandTemplate>,  // 自定义设备命令
+    #[serde(default)]
+    pub responses: Vec<ResponseLayout>,  // 设备应答的解码布局
+    #[serde(default)]
+    pub game_state: GameStateConfig,  // 游戏状态来源
+    #[serde(default)]
+    pub bindings: Vec<BindingConfig>,  // 输入绑定
+    #[serde(default)]
+    pub device_bindings: BTreeMap<String, Vec<BindingConfig>>,  // 同时连接的其他设备的绑定，按设备 id
+    #[serde(default)]
+    pub aggregate: AggregateConfig,  // 把多台设备合并为一个逻辑设备
+    #[serde(default)]
+    pub bank_channel: Option<usize>,  // 用作分组选择旋钮的ADC通道
+    #[serde(default)]
+    pub banks: Vec<BankConfig>,  // 旋钮按分组数量均分为若干档位
+    #[serde(default)]
+    pub report_rates: HashMap<String, u32>,  // 按串口记录的上报频率（Hz）
+    #[serde(default)]
+    pub wake_key: Option<usize>,  // 按下后弹出主窗口的按键
+    #[serde(default)]
+    pub record_key: Option<usize>,  // 开始/停止录制宏的按键
+    #[serde(default)]
+    pub record_led: Option<usize>,  // 录制期间闪烁的LED
+    #[serde(default)]
+    pub macros: Vec<MacroConfig>,  // 录制的宏
+    #[serde(default)]
+    pub schedules: Vec<ScheduleConfig>,  // 定时任务
+    #[serde(default)]
+    pub action_limits: ActionLimits,  // 动作的冷却和限速
+    #[serde(default)]
+    pub variables: VariablesConfig,  // 变量及其初始值
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,  // 事件 Webhook
+    #[serde(default)]
+    pub discord: DiscordConfig,  // Discord 状态
+    #[serde(default)]
+    pub watches: Vec<WatchConfig>,  // 监视表达式
+    #[serde(default)]
+    pub soak: SoakConfig,  // 老化测试
+    #[serde(default)]
+    pub retry: RetryPolicy,  // 自动重连
+    #[serde(default)]
+    pub simon: SimonConfig,  // Simon 质检模式
+    #[serde(default)]
+    pub snippets: Vec<String>,  // 预存的文本片段，供粘贴动作使用
+    #[serde(default)]
+    pub adc_scaling: Vec<AdcScaling>,  // ADC通道的单位与换算
+    #[serde(default)]
+    pub history: HistoryConfig,  // 历史记录
+    #[serde(default = "default_led_brightness")]
+    pub led_brightness: u8,  // LED点亮时的亮度（1-255），协议启用 led_dimming 时才可小于 255
+    #[serde(default = "default_enabled")]
+    pub leds_enabled: bool,  // LED总开关，关闭时所有LED保持熄灭
+    #[serde(default)]
+    pub performance_mode: bool,  // 提高进程优先级和计时器精度（仅 Windows）
+}
+
+fn default_axis_settings() -> Vec<AxisSettings> {
+    vec![AxisSettings::default(); 14]
+}
+
+impl MatrixConfig {
+    pub fn load() -> Self {
+        Self::load_with_status().0
+    }
+    
+    // 加载配置并返回加载情况：配置文件无法解析时先尝试备份，都不可用才使用默认配置，
+    // 损坏的文件另存一份，避免之后的保存覆盖用户数据。能解析但未通过检查的配置照常加载，
+    // 问题由启动检查报告，修改配置时不要求先修正已有的问题
+    pub fn load_with_status() -> (Self, ConfigLoadStatus) {
+        let config_path = Self::get_config_path();
+        let (config, status) = match fs::read_to_string(&config_path) {
+            Err(_) => (Self::default(), ConfigLoadStatus::Missing),
+            Ok(config_str) => match Self::parse(&config_str) {
+                Ok((config, repairs)) if repairs.is_empty() => (config, ConfigLoadStatus::Loaded),
+                Ok((config, repairs)) => {
+                    eprintln!("Config repaired: {}", repairs.join("; "));
+                    (config, ConfigLoadStatus::Repaired { repairs })
+                }
+                Err(error) => {
+                    let corrupted_copy = format!("{}.corrupt", config_path);
+                    if let Err(e) = fs::copy(&config_path, &corrupted_copy) {
+                        eprintln!("Failed to keep corrupted config: {}", e);
+                    }
+                    let backup = fs::read_to_string(format!("{}.bak", config_path))
+                        .map_err(|e| e.to_string())
+                        .and_then(|backup_str| Self::parse(&backup_str));
+                    match backup {
+                        Ok((config, _)) => {
+                            eprintln!("Config file corrupted ({}), recovered from backup", error);
+                            config.save();
+                            (config, ConfigLoadStatus::RecoveredFromBackup { error, corrupted_copy })
+                        }
+                        Err(_) => {
+                            eprintln!("Config file corrupted ({}), using defaults", error);
+                            (Self::default(), ConfigLoadStatus::Defaulted { error, corrupted_copy })
+                        }
+                    }
+                }
+            },
+        };
+        (config, status)
+    }
+    
+    // 解析配置并返回替换为默认值的部分；手动修改过协议数量时，名称和布局随之调整。
+    // 协议或串口设置无效时无法正确读取数据，换成默认值，其余内容原样保留
+    fn parse(config_str: &str) -> Result<(Self, Vec<String>), String> {
+        let mut value: Value = serde_json::from_str(config_str).map_err(|e| e.to_string())?;
+        Self::migrate_legacy(&mut value);
+        let mut config: Self = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        let mut repairs = Vec::new();
+        if let Err(e) = config.protocol.validate() {
+            repairs.push(format!("协议配置无效，已使用默认协议: {}", e));
+            config.protocol = ProtocolConfig::default();
+        }
+        let mut serial_valid = config.serial_matrix.validate();
+        if serial_valid.is_ok() && config.serial_matrix.max_pending < config.protocol.frame_len() {
+            serial_valid = Err(format!("未结束数据的上限 {} 小于帧长", config.serial_matrix.max_pending));
+        }
+        if let Err(e) = serial_valid {
+            repairs.push(format!("串口设置无效，已使用默认设置: {}", e));
+            config.serial_matrix = SerialConfig {
+                port: config.serial_matrix.port.clone(),
+                ..Self::default().serial_matrix
+            };
+        }
+        config.labels.resize(&config.protocol);
+        config.layout.resize(&config.protocol);
+        Ok((config, repairs))
+    }
+    
+    // 旧版配置的名称位于顶层 key_names/adc_names/led_names，迁移到 labels
+    fn migrate_legacy(value: &mut Value) {
+        let Some(object) = value.as_object_mut() else {
+            return;
+        };
+        if object.contains_key("labels") {
+            return;
+        }
+        let fields = [("key_names", "keys"), ("adc_names", "adcs"), ("led_names", "leds")];
+        let mut labels = serde_json::Map::new();
+        for (old, new) in fields {
+            if let Some(names) = object.remove(old) {
+                labels.insert(new.to_string(), names);
+            }
+        }
+        if labels.len() == fields.len() {
+            object.insert("labels".to_string(), Value::Object(labels));
+        }
+    }
+    
+    // 序列不能为空，条件引用的按键必须存在，变量名必须有效；嵌套的序列同样检查
+    fn check_action(&self, action: &ActionConfig, problems: &mut Vec<String>) {
+        let steps = match action {
+            ActionConfig::Sequence { steps } => steps,
+            ActionConfig::SetVariable { name, .. }
+            | ActionConfig::AddVariable { name, .. }
+            | ActionConfig::ToggleVariable { name } => {
+                if !is_variable_name(name) {
+                    problems.push(format!("变量名无效: {}", name));
+                }
+                return;
+            }
+            _ => return,
+        };
+        if steps.is_empty() {
+            problems.push("动作序列没有步骤".to_string());
+        }
+        for step in steps {
+            if let Some(condition) = &step.condition {
+                self.check_condition(condition, problems);
+            }
+            self.check_action(&step.action, problems);
+        }
+    }
+
+    // 主设备的绑定可引用的按键数量，启用聚合时包含聚合设备的按键
+    pub fn input_key_count(&self) -> usize {
+        self.protocol.key_count * self.aggregate.sources()
+    }
+
+    pub fn input_adc_count(&self) -> usize {
+        self.protocol.adc_count * self.aggregate.sources()
+    }
+
+    // 条件引用的按键必须存在，时间必须有效
+    fn check_condition(&self, condition: &Condition, problems: &mut Vec<String>) {
+        if let Some(key) = condition.key() {
+            if key >= self.input_key_count() {
+                problems.push(format!("条件引用了不存在的按键 {}", key + 1));
+            }
+        }
+        if let Condition::TimeBetween { start, end } = condition {
+            for time in [start, end] {
+                if let Err(e) = parse_time_of_day(time) {
+                    problems.push(e);
+                }
+            }
+        }
+    }
+
+    // 检查配置内容是否与协议结构一致，返回发现的问题
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Err(e) = self.protocol.validate() {
+            problems.push(e);
+        }
+        if let Err(e) = self.serial_matrix.validate() {
+            problems.push(e);
+        }
+        if self.serial_matrix.max_pending < self.protocol.frame_len() {
+            problems.push(format!(
+                "未结束数据的上限 {} 小于帧长 {}",
+                self.serial_matrix.max_pending,
+                self.protocol.frame_len()
+            ));
+        }
+        if let Err(e) = self.labels.check(&self.protocol) {
+            problems.push(e);
+        }
+        if let Err(e) = self.layout.check(&self.protocol) {
+            problems.push(e);
+        }
+        
+        let check_led = |led: &Option<usize>, problems: &mut Vec<String>| {
+            if let Some(led) = led {
+                if *led >= self.protocol.led_count {
+                    problems.push(format!("绑定引用了不存在的 LED {}", led + 1));
+                }
+            }
+        };
+        // 其他设备与主设备使用相同的协议，按相同的方式检查；安全键和宏只属于主设备
+        for (id, bindings) in &self.device_bindings {
+            if id.is_empty() {
+                problems.push("设备 id 不能为空".to_string());
+            }
+            for binding in bindings {
+                if binding.is_guarded() {
+                    problems.push(format!("设备 {} 的绑定不支持安全键", id));
+                }
+                if binding.actions().into_iter().any(ActionConfig::plays_macro) {
+                    problems.push(format!("设备 {} 的绑定不支持回放宏", id));
+                }
+            }
+        }
+        for (i, id) in self.aggregate.devices.iter().enumerate() {
+            if id.is_empty() || self.aggregate.devices[..i].contains(id) {
+                problems.push(format!("聚合的设备 id 为空或重复: \"{}\"", id));
+            }
+        }
+        let (key_count, adc_count) = (self.input_key_count(), self.input_adc_count());
+        let bank_bindings = self.banks.iter().flat_map(|bank| &bank.bindings);
+        let device_bindings = self.device_bindings.values().flatten();
+        for binding in self.bindings.iter().chain(bank_bindings).chain(device_bindings) {
+            match binding {
+                BindingConfig::KeyPress { key, .. } => {
+                    if *key >= key_count {
+                        problems.push(format!("绑定引用了不存在的按键 {}", key + 1));
+                    }
+                }
+                BindingConfig::AxisRepeat { channel, min_rate, max_rate, .. } => {
+                    if *channel >= adc_count {
+                        problems.push(format!("绑定引用了不存在的 ADC {}", channel + 1));
+                    }
+                    let valid = |rate: &f32| rate.is_finite() && *rate > 0.0;
+                    if !valid(min_rate) || !valid(max_rate) {
+                        problems.push(format!("ADC {} 的连发频率应为大于 0 的数", channel + 1));
+                    }
+                }
+                BindingConfig::DualStage { channel, .. }
+                | BindingConfig::Flick { channel, .. }
+                | BindingConfig::Spin { channel, .. } => {
+                    if *channel >= adc_count {
+                        problems.push(format!("绑定引用了不存在的 ADC {}", channel + 1));
+                    }
+                }
+                BindingConfig::Encoder { channel, step, .. } => {
+                    if *channel >= adc_count {
+                        problems.push(format!("绑定引用了不存在的 ADC {}", channel + 1));
+                    }
+                    // 相邻两帧的增量超过半圈时无法判断方向
+                    if *step == 0 || *step >= 128 {
+                        problems.push(format!("ADC {} 的编码器步长应在 1 ~ 127 之间", channel + 1));
+                    }
+                }
+            }
+            if let BindingConfig::DualStage { stage1_led, stage2_led, .. } = binding {
+                check_led(stage1_led, &mut problems);
+                check_led(stage2_led, &mut problems);
+            }
+            for action in binding.actions() {
+                self.check_action(action, &mut problems);
+            }
+            if binding.is_guarded() && self.safety.key.is_none() {
+                problems.push("有绑定需要安全键，但未设置安全键".to_string());
+            }
+        }
+        for (i, template) in self.commands.iter().enumerate() {
+            if self.commands[..i].iter().any(|other| other.name == template.name) {
+                problems.push(format!("命令名称 {} 重复", template.name));
+            }
+            // 参数取 0 检查模板格式
+            let params = command::template_params(&template.pattern)
+                .into_iter()
+                .map(|name| (name, 0))
+                .collect();
+            if let Err(e) = command::render_template(&template.pattern, &params) {
+                problems.push(format!("命令 {} 的模板无效: {}", template.name, e));
+            }
+        }
+        if let Some(name) = &self.protocol.commands.info_response {
+            if !self.responses.iter().any(|layout| &layout.name == name) {
+                problems.push(format!("设备信息的应答布局 {} 不存在", name));
+            }
+        }
+        for (i, layout) in self.responses.iter().enumerate() {
+            if self.responses[..i].iter().any(|other| other.name == layout.name) {
+                problems.push(format!("应答名称 {} 重复", layout.name));
+            }
+            match response::parse_header(&layout.header) {
+                Ok(header) if header.len() > layout.length => {
+                    problems.push(format!("应答 {} 的帧头长于应答长度", layout.name));
+                }
+                Ok(_) => {}
+                Err(e) => problems.push(format!("应答 {} 的帧头无效: {}", layout.name, e)),
+            }
+            if let Some(command) = &layout.command {
+                if !self.commands.iter().any(|template| &template.name == command) {
+                    problems.push(format!("应答 {} 对应的命令 {} 不存在", layout.name, command));
+                }
+            }
+            for field in &layout.fields {
+                match response::field_width(&field.format) {
+                    Some(width) if field.offset + width > layout.length => {
+                        problems.push(format!("应答 {} 的字段 {} 超出应答长度", layout.name, field.name));
+                    }
+                    Some(_) => {}
+                    None => problems.push(format!("应答 {} 的字段 {} 格式无效: {}", layout.name, field.name, field.format)),
+                }
+            }
+        }
+        let sources = &self.game_state.sources;
+        for (i, source) in sources.iter().enumerate() {
+            if sources[..i].iter().any(|other| other.name == source.name) {
+                problems.push(format!("游戏状态来源 {} 重复", source.name));
+            }
+            match &source.transport {
+                GameStateTransport::Http { url } => {
+                    // 只支持本机网关常用的 http，未启用 TLS
+                    if !url.starts_with("http://") {
+                        problems.push(format!("游戏状态来源 {} 的地址无效: {}", source.name, url));
+                    }
+                }
+                GameStateTransport::Udp { bind } => {
+                    if bind.parse::<std::net::SocketAddr>().is_err() {
+                        problems.push(format!("游戏状态来源 {} 的监听地址无效: {}", source.name, bind));
+                    }
+                }
+            }
+            for field in &source.fields {
+                if !field.pointer.is_empty() && !field.pointer.starts_with('/') {
+                    problems.push(format!("游戏状态字段 {}.{} 的 JSON Pointer 应以 / 开头", source.name, field.name));
+                }
+            }
+        }
+        for mapping in &self.game_state.leds {
+            let known = mapping.value.split_once('.').is_some_and(|(source, field)| {
+                sources.iter().any(|s| s.name == source && s.fields.iter().any(|f| f.name == field))
+            });
+            if !known {
+                problems.push(format!("LED {} 引用了不存在的游戏状态 {}", mapping.led + 1, mapping.value));
+            }
+            if mapping.led >= self.protocol.led_count {
+                problems.push(format!("游戏状态引用了不存在的 LED {}", mapping.led + 1));
+            }
+        }
+        if self.pwm.max_rate_hz == 0 {
+            problems.push("PWM发送频率必须大于 0".to_string());
+        }
+        if !self.pwm.sources.is_empty() && self.protocol.commands.pwm.is_none() {
+            problems.push("跟随ADC的PWM输出需要在协议中设置PWM命令字".to_string());
+        }
+        for source in &self.pwm.sources {
+            if source.channel >= self.pwm.channels {
+                problems.push(format!("PWM通道 {} 不存在", source.channel + 1));
+            }
+            if source.adc >= self.protocol.adc_count {
+                problems.push(format!("PWM通道 {} 引用了不存在的 ADC {}", source.channel + 1, source.adc + 1));
+            }
+        }
+        if let Some(key) = self.safety.key {
+            if key >= key_count {
+                problems.push(format!("安全键 {} 不存在", key + 1));
+            }
+        }
+        if let Some(key) = self.pause.key {
+            if key >= key_count {
+                problems.push(format!("暂停按键 {} 不存在", key + 1));
+            }
+        }
+        if let Some(key) = self.wake_key {
+            if key >= key_count {
+                problems.push(format!("唤醒按键 {} 不存在", key + 1));
+            }
+        }
+        if !(0.0..=1.0).contains(&self.drift.rate) {
+            problems.push("温漂补偿速率必须在 0 到 1 之间".to_string());
+        }
+        for (channel, axis) in self.axis_settings.iter().enumerate() {
+            if !(axis.min <= axis.center && axis.center <= axis.max) {
+                problems.push(format!("ADC {} 的中心值必须在行程范围内", channel + 1));
+            }
+        }
+        for mirror in &self.led_mirror.mapping {
+            if mirror.key >= self.protocol.key_count {
+                problems.push(format!("LED跟随引用了不存在的按键 {}", mirror.key + 1));
+            }
+            if mirror.led >= self.protocol.led_count {
+                problems.push(format!("LED跟随引用了不存在的 LED {}", mirror.led + 1));
+            }
+        }
+        if let Some(columns) = self.ghosting.columns {
+            if columns == 0 || columns > self.protocol.key_count {
+                problems.push(format!("鬼键检测的列数 {} 无效", columns));
+            }
+        }
+        if self.ghosting.threshold == 0 {
+            problems.push("鬼键提示次数必须大于 0".to_string());
+        }
+        if !(1..=256).contains(&self.histogram.bins) {
+            problems.push("直方图区间数量必须在 1 到 256 之间".to_string());
+        }
+        if let Some(channel) = self.bank_channel {
+            if channel >= self.protocol.adc_count {
+                problems.push(format!("分组旋钮 ADC {} 不存在", channel + 1));
+            }
+        }
+        for bank in &self.banks {
+            if let Some(led) = bank.led {
+                if led >= self.protocol.led_count {
+                    problems.push(format!("分组 {} 引用了不存在的 LED {}", bank.name, led + 1));
+                }
+            }
+        }
+        if let Some(key) = self.record_key {
+            if key >= key_count {
+                problems.push(format!("录制按键 {} 不存在", key + 1));
+            }
+        }
+        if let Some(led) = self.record_led {
+            if led >= self.protocol.led_count {
+                problems.push(format!("录制指示 LED {} 不存在", led + 1));
+            }
+        }
+        if self.led_brightness == 0 {
+            problems.push("LED 亮度不能为 0".to_string());
+        } else if self.led_brightness != 255 && !self.protocol.commands.led_dimming {
+            // 固件不支持调光时 LED 命令只发送 0/1，其他亮度不会生效
+            problems.push("协议未启用 led_dimming，LED 亮度只能为 255".to_string());
+        }
+        for watch in &self.watches {
+            if let Err(e) = WatchExpr::parse(&watch.expr) {
+                problems.push(format!("监视表达式 {}: {}", watch.name, e));
+            }
+        }
+        for schedule in &self.schedules {
+            if let Err(e) = CronExpr::parse(&schedule.cron) {
+                problems.push(format!("定时任务 {}: {}", schedule.name, e));
+            }
+            self.check_action(&schedule.action, &mut problems);
+            if let Some(condition) = &schedule.condition {
+                self.check_condition(condition, &mut problems);
+            }
+        }
+        for name in self.variables.values.keys() {
+            if !is_variable_name(name) {
+                problems.push(format!("变量名无效: {}", name));
+            }
+        }
+        if self.discord.enabled && (self.discord.app_id.is_empty() || !self.discord.app_id.chars().all(|c| c.is_ascii_digit())) {
+            problems.push(format!("Discord 应用 ID 无效: {}", self.discord.app_id));
+        }
+        for kind in self.action_limits.per_action.keys() {
+            if !ACTION_KINDS.contains(&kind.as_str()) {
+                problems.push(format!("冷却设置中的动作类型不存在: {}", kind));
+            }
+        }
+        for webhook in &self.webhooks {
+            if !webhook.url.starts_with("http://") {
+                problems.push(format!("Webhook {} 的地址无效（仅支持 http）: {}", webhook.name, webhook.url));
+            }
+            if webhook.attempts == 0 {
+                problems.push(format!("Webhook {} 的尝试次数至少为 1", webhook.name));
+            }
+            for trigger in &webhook.events {
+                if let WebhookTrigger::KeyPressed { key } | WebhookTrigger::KeyReleased { key } = trigger {
+                    if *key >= self.protocol.key_count {
+                        problems.push(format!("Webhook {} 引用了不存在的按键 {}", webhook.name, key + 1));
+                    }
+                }
+            }
+        }
+        problems
+    }
+    
+    // 旧配置保存的是 /dev/ttyUSBn 这类会随插拔顺序变化的名称，设备在线时换成 by-id 路径，
+    // 按串口记录的上报频率一并迁移；返回 true 表示配置已修改
+    pub fn migrate_port(&mut self) -> bool {
+        let old = self.serial_matrix.port.clone();
+        let stable = crate::serial::stable_port_name(&old);
+        if stable == old {
+            return false;
+        }
+        if let Some(hz) = self.report_rates.remove(&old) {
+            self.report_rates.entry(stable.clone()).or_insert(hz);
+        }
+        eprintln!("Migrating serial port {} -> {}", old, stable);
+        self.serial_matrix.port = stable;
+        true
+    }
+    
+    pub fn save(&self) {
+        // 保存配置到应用数据目录，使用安全的错误处理避免程序崩溃
+        let config_path = Self::get_config_path();
+        if let Ok(config_str) = serde_json::to_string_pretty(self) {
+            if let Err(e) = Self::write_atomic(&config_path, &config_str) {
+                // 仅记录错误，不导致程序崩溃
+                eprintln!("Failed to write config file {}: {}", config_path, e);
+            }
+        } else {
+            // 仅记录错误，不导致程序崩溃
+            eprintln!("Failed to serialize config");
+        }
+    }
+    
+    // 先写入临时文件并落盘，再替换原文件，断电时不会留下写了一半的配置；
+    // 原文件能正常解析时保留为 .bak
+    fn write_atomic(config_path: &str, content: &str) -> std::io::Result<()> {
+        let temp_path = format!("{}.tmp", config_path);
+        {
+            let mut file = fs::File::create(&temp_path)?;
+            file.write_all(content.as_bytes())?;
+            file.sync_all()?;
+        }
+        let current_valid = fs::read_to_string(config_path)
+            .is_ok_and(|current| Self::parse(&current).is_ok());
+        if current_valid {
+            if let Err(e) = fs::copy(config_path, format!("{}.bak", config_path)) {
+                eprintln!("Failed to back up config file: {}", e);
+            }
+        }
+        fs::rename(&temp_path, config_path)
+    }
+    
+    // 获取配置文件的正确路径
+    fn get_config_path() -> String {
+        Self::data_file_path("config.json")
+    }
+    
+    // 获取应用数据文件的路径（与配置文件同目录）
+    pub fn data_file_path(name: &str) -> String {
+        // 在Tauri应用中，我们需要考虑不同环境下的配置文件路径
+        // 对于开发环境，我们使用项目根目录
+        // 对于生产环境，我们使用应用所在目录
+        #[cfg(debug_assertions)]
+        {
+            // 开发环境：项目根目录
+            name.to_string()
+        }
+        #[cfg(not(debug_assertions))]
+        {
+            // 生产环境：应用所在目录
+            let exe_path = std::env::current_exe().unwrap_or_default();
+            let app_dir = exe_path.parent().unwrap_or_else(|| std::path::Path::new("."));
+            let path = app_dir.join(name);
+            path.to_str().unwrap_or(name).to_string()
+        }
+    }
+}
+
+impl Default for MatrixConfig {
+    fn default() -> Self {
+        Self {
+            protocol: ProtocolConfig::default(),
+            serial_matrix: SerialConfig {
+                port: crate::permissions::default_port().to_string(),
+                baud_rate: 9600,
+                data_bits: 8,
+                stop_bits: 1,
+                parity: "None".to_string(),
+                flow_control: FlowControl::None,
+                dtr: None,
+                rts: None,
+                read_timeout_ms: default_read_timeout(),
+                read_size: default_read_size(),
+                max_pending: default_max_pending(),
+            },
+            serial_screen: SerialScreenConfig {
+                enabled: false,
+                port: "COM2".to_string(),
+                baud_rate: 9600,
+                data_bits: 8,
+                stop_bits: 1,
+                parity: "None".to_string(),
+            },
+            // 自定义名称配置
+            labels: LabelsConfig::default(),
+            layout: LayoutConfig::default(),
+            axis_settings: default_axis_settings(),
+            drift: DriftConfig::default(),
+            histogram: HistogramConfig::default(),
+            range_learning: RangeLearningConfig::default(),
+            ghosting: GhostingConfig::default(),
+            led_mirror: LedMirrorConfig::default(),
+            pause: PauseConfig::default(),
+            safety: SafetyConfig::default(),
+            pwm: PwmConfig::default(),
+            commands: Vec::new(),
+            responses: Vec::new(),
+            game_state: GameStateConfig::default(),
+            bindings: Vec::new(),
+            device_bindings: BTreeMap::new(),
+            aggregate: AggregateConfig::default(),
+            bank_channel: None,
+            banks: Vec::new(),
+            report_rates: HashMap::new(),
+            wake_key: None,
+            record_key: None,
+            record_led: None,
+            macros: Vec::new(),
+            schedules: Vec::new(),
+            action_limits: ActionLimits::default(),
+            variables: VariablesConfig::default(),
+            webhooks: Vec::new(),
+            discord: DiscordConfig::default(),
+            watches: Vec::new(),
+            soak: SoakConfig::default(),
+            retry: RetryPolicy::default(),
+            simon: SimonConfig::default(),
+            snippets: Vec::new(),
+            adc_scaling: Vec::new(),
+            history: HistoryConfig::default(),
+            led_brightness: default_led_brightness(),
+            leds_enabled: true,
+            performance_mode: false,
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn protocol_with_field(offset: usize, length: usize, field_type: FieldType) -> ProtocolConfig {
+        ProtocolConfig {
+            fields: vec![FieldSpec {
+                name: "sensor".to_string(),
+                offset,
+                length,
+                field_type,
+                bits: BitLayout::default(),
+            }],
+            ..ProtocolConfig::default()
+        }
+    }
+
+    #[test]
+    fn accepts_fields_inside_the_payload() {
+        assert!(protocol_with_field(1, 2, FieldType::U16).validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_overflowing_field_offsets_and_lengths() {
+        assert!(protocol_with_field(usize::MAX, 1, FieldType::U8).validate().is_err());
+        assert!(protocol_with_field(1, usize::MAX, FieldType::U16).validate().is_err());
+        assert!(protocol_with_field(usize::MAX, usize::MAX, FieldType::I16).validate().is_err());
+    }
+
+    fn config_json(change: impl FnOnce(&mut Value)) -> String {
+        let mut value = serde_json::to_value(MatrixConfig::default()).unwrap();
+        change(&mut value);
+        value.to_string()
+    }
+
+    #[test]
+    fn loads_configs_with_problems_and_keeps_the_invalid_entries() {
+        let json = config_json(|value| value["wake_key"] = Value::from(999));
+        let (config, repairs) = MatrixConfig::parse(&json).unwrap();
+        assert!(repairs.is_empty());
+        assert_eq!(config.wake_key, Some(999));
+        assert!(!config.validate().is_empty());
+    }
+
+    #[test]
+    fn replaces_an_invalid_protocol_with_the_default() {
+        let json = config_json(|value| value["protocol"]["key_count"] = Value::from(usize::MAX));
+        let (config, repairs) = MatrixConfig::parse(&json).unwrap();
+        assert_eq!(repairs.len(), 1);
+        assert_eq!(config.protocol, ProtocolConfig::default());
+    }
+
+    #[test]
+    fn rejects_json_that_does_not_parse() {
+        assert!(MatrixConfig::parse("{ not json").is_err());
+    }
+
+    #[test]
+    fn rejects_overflowing_frame_lengths() {
+        let protocol = ProtocolConfig { adc_count: usize::MAX, ..ProtocolConfig::default() };
+        assert_eq!(protocol.frame_len(), usize::MAX);
+        assert!(protocol.validate().is_err());
+    }
+}
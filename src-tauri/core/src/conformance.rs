@@ -0,0 +1,132 @@
+// 协议一致性检查：用参考向量文件（输入字节流 + 期望解析结果）验证解析器，
+// 固件修改帧格式后可用同一份向量文件确认上位机解析一致
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use crate::matrix::DataParser;
+
+// 输入可写成十六进制字符串（"AA 47 00 ..."）或字节数组
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum VectorInput {
+    Hex(String),
+    Bytes(Vec<u8>),
+}
+
+impl VectorInput {
+    fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        match self {
+            VectorInput::Bytes(bytes) => Ok(bytes.clone()),
+            VectorInput::Hex(hex) => hex
+                .split_whitespace()
+                .map(|s| u8::from_str_radix(s, 16).map_err(|_| format!("无效的十六进制字节: {}", s)))
+                .collect(),
+        }
+    }
+}
+
+// 期望结果，只比较给出的字段
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ExpectedData {
+    pub valid: Option<bool>,
+    pub index: Option<u8>,
+    pub keys: Option<Vec<bool>>,
+    pub adc: Option<Vec<u8>>,
+    pub leds: Option<Vec<bool>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TestVector {
+    pub name: String,
+    pub input: VectorInput,
+    pub expected: ExpectedData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VectorFile {
+    pub vectors: Vec<TestVector>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VectorResult {
+    pub name: String,
+    pub passed: bool,
+    pub mismatches: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConformanceReport {
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub results: Vec<VectorResult>,
+}
+
+fn compare<T: PartialEq + std::fmt::Debug>(
+    mismatches: &mut Vec<String>,
+    field: &str,
+    expected: &Option<T>,
+    actual: T,
+) {
+    if let Some(expected) = expected {
+        if *expected != actual {
+            mismatches.push(format!("{}: 期望 {:?}，实际 {:?}", field, expected, actual));
+        }
+    }
+}
+
+fn check_vector(parser: &DataParser, vector: &TestVector) -> VectorResult {
+    let mut mismatches = Vec::new();
+    match vector.input.to_bytes() {
+        Ok(input) => {
+            let parsed = parser.parse_data(&input);
+            let expected = &vector.expected;
+            compare(&mut mismatches, "valid", &expected.valid, parsed.valid);
+            compare(&mut mismatches, "index", &expected.index, parsed.index);
+            compare(&mut mismatches, "keys", &expected.keys, parsed.keys);
+            compare(&mut mismatches, "adc", &expected.adc, parsed.adc);
+            compare(&mut mismatches, "leds", &expected.leds, parsed.leds);
+        }
+        Err(e) => mismatches.push(e),
+    }
+
+    VectorResult {
+        name: vector.name.clone(),
+        passed: mismatches.is_empty(),
+        mismatches,
+    }
+}
+
+// 读取向量文件并逐条用当前解析器检查
+pub fn run(parser: &DataParser, path: &Path) -> Result<ConformanceReport, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("读取向量文件失败: {}", e))?;
+    let file: VectorFile =
+        serde_json::from_str(&content).map_err(|e| format!("向量文件格式错误: {}", e))?;
+
+    let results: Vec<VectorResult> = file.vectors.iter().map(|v| check_vector(parser, v)).collect();
+    let passed = results.iter().filter(|r| r.passed).count();
+    Ok(ConformanceReport {
+        total: results.len(),
+        passed,
+        failed: results.len() - passed,
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MatrixConfig;
+
+    #[test]
+    fn default_protocol_passes_the_documented_vectors() {
+        let parser = DataParser::new(MatrixConfig::default());
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../docs/protocol_vectors.json");
+        let report = run(&parser, &path).unwrap();
+        let failures: Vec<_> = report.results.iter().filter(|r| !r.passed).collect();
+        assert_eq!(report.failed, 0, "{:?}", failures);
+        assert!(report.total > 0);
+    }
+}
@@ -0,0 +1,344 @@
+// 包分帧：COBS 和 SLIP 编码的数据包，以分隔字节结束。
+// 解码后的包内容与间隔分帧的帧相同（序号 按键位图 ADC LED位图 校验），
+// 校验和字段解析不变；发给设备的命令按同样的方式编码。
+// 另有定界分帧下的转义：帧内的 AA/BF/10 写作 10 加原字节异或 0x20
+
+use crate::config::Framing;
+
+const COBS_DELIMITER: u8 = 0x00;
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+const FRAME_HEADER: u8 = 0xAA;
+const FRAME_TAIL: u8 = 0xBF;
+const DLE: u8 = 0x10;
+const ESCAPE_XOR: u8 = 0x20;
+
+// COBS 编码，不含结尾的 0x00
+pub fn cobs_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(data.len() + data.len() / 254 + 2);
+    let mut code_index = 0;
+    encoded.push(0);
+    let mut code = 1u8;
+    for (i, &byte) in data.iter().enumerate() {
+        if byte == 0 {
+            encoded[code_index] = code;
+            code_index = encoded.len();
+            encoded.push(0);
+            code = 1;
+            continue;
+        }
+        encoded.push(byte);
+        code += 1;
+        // 满 254 个非零字节时结束本组；数据已结束时不再开始新的一组
+        if code == 0xFF && i + 1 < data.len() {
+            encoded[code_index] = code;
+            code_index = encoded.len();
+            encoded.push(0);
+            code = 1;
+        }
+    }
+    encoded[code_index] = code;
+    encoded
+}
+
+// COBS 解码，输入不含结尾的 0x00；编码无效时返回 None
+pub fn cobs_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        let code = data[i] as usize;
+        if code == 0 || i + code > data.len() {
+            return None;
+        }
+        decoded.extend_from_slice(&data[i + 1..i + code]);
+        i += code;
+        // 0xFF 组后面没有隐含的 0；最后一组后面也没有
+        if code < 0xFF && i < data.len() {
+            decoded.push(0);
+        }
+    }
+    Some(decoded)
+}
+
+// SLIP 编码，不含结尾的 END
+pub fn slip_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(data.len() + 2);
+    for &byte in data {
+        match byte {
+            SLIP_END => encoded.extend([SLIP_ESC, SLIP_ESC_END]),
+            SLIP_ESC => encoded.extend([SLIP_ESC, SLIP_ESC_ESC]),
+            _ => encoded.push(byte),
+        }
+    }
+    encoded
+}
+
+// SLIP 解码，输入不含 END；转义无效时返回 None
+pub fn slip_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(data.len());
+    let mut bytes = data.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte != SLIP_ESC {
+            decoded.push(byte);
+            continue;
+        }
+        match bytes.next() {
+            Some(&SLIP_ESC_END) => decoded.push(SLIP_END),
+            Some(&SLIP_ESC_ESC) => decoded.push(SLIP_ESC),
+            _ => return None,
+        }
+    }
+    Some(decoded)
+}
+
+// 按分帧方式编码发给设备的命令；定界和间隔分帧时原样发送
+pub fn encode_packet(framing: &Framing, data: &[u8]) -> Vec<u8> {
+    match framing {
+        Framing::Cobs => {
+            let mut packet = cobs_encode(data);
+            packet.push(COBS_DELIMITER);
+            packet
+        }
+        Framing::Slip => {
+            // 开头也发送 END，清除设备端可能残留的噪声
+            let mut packet = vec![SLIP_END];
+            packet.extend(slip_encode(data));
+            packet.push(SLIP_END);
+            packet
+        }
+        Framing::Delimited | Framing::Gap { .. } => data.to_vec(),
+    }
+}
+
+type PacketDecoder = fn(&[u8]) -> Option<Vec<u8>>;
+
+// 从字节流中切分并解码数据包，跨读取保留未结束的部分
+#[derive(Default)]
+pub struct PacketFramer {
+    pending: Vec<u8>,
+    overflowed: bool,  // 当前包已超长，丢弃到下一个分隔字节
+}
+
+impl PacketFramer {
+    // 加入本次读取的数据，返回已结束且解码成功的包；空包和无效包被丢弃，
+    // 一直没有分隔字节、累积超过 max_len 时丢弃整个包，直到下一个分隔字节
+    pub fn push(&mut self, framing: &Framing, data: &[u8], max_len: usize) -> Vec<Vec<u8>> {
+        let (delimiter, decode): (u8, PacketDecoder) = match framing {
+            Framing::Cobs => (COBS_DELIMITER, cobs_decode),
+            Framing::Slip => (SLIP_END, slip_decode),
+            Framing::Delimited | Framing::Gap { .. } => return Vec::new(),
+        };
+        let mut packets = Vec::new();
+        for &byte in data {
+            if byte != delimiter {
+                if self.pending.len() >= max_len {
+                    self.pending.clear();
+                    self.overflowed = true;
+                }
+                if !self.overflowed {
+                    self.pending.push(byte);
+                }
+                continue;
+            }
+            self.overflowed = false;
+            if !self.pending.is_empty() {
+                if let Some(packet) = decode(&self.pending).filter(|p| !p.is_empty()) {
+                    packets.push(packet);
+                }
+                self.pending.clear();
+            }
+        }
+        packets
+    }
+}
+
+fn needs_escape(byte: u8) -> bool {
+    matches!(byte, FRAME_HEADER | FRAME_TAIL | DLE)
+}
+
+// 转义发给设备的命令：保留第一个字节（命令头）和结尾的 BF，其余需要转义的字节加 10 前缀
+pub fn escape_command(command: &[u8]) -> Vec<u8> {
+    let Some((&first, rest)) = command.split_first() else {
+        return Vec::new();
+    };
+    let (body, tail) = match rest.split_last() {
+        Some((&FRAME_TAIL, body)) => (body, Some(FRAME_TAIL)),
+        _ => (rest, None),
+    };
+    let mut escaped = Vec::with_capacity(command.len() + 4);
+    escaped.push(first);
+    for &byte in body {
+        if needs_escape(byte) {
+            escaped.extend([DLE, byte ^ ESCAPE_XOR]);
+        } else {
+            escaped.push(byte);
+        }
+    }
+    escaped.extend(tail);
+    escaped
+}
+
+// 从转义的字节流中取出 AA...BF 帧并去掉转义，跨读取保留未结束的帧
+#[derive(Default)]
+pub struct EscapeFramer {
+    pending: Option<Vec<u8>>,  // 已收到帧头、尚未收到帧尾的帧内容（已去掉转义）
+    escaped: bool,  // 上一个字节是 10
+}
+
+impl EscapeFramer {
+    // 加入本次读取的数据，返回已结束的帧，每帧带上 AA 和 BF 首尾相接；转义无效和超过 max_len 的帧被丢弃
+    pub fn push(&mut self, data: &[u8], max_len: usize) -> Vec<u8> {
+        let mut frames = Vec::new();
+        for &byte in data {
+            if byte == FRAME_HEADER {
+                // 未转义的帧头总是开始新的一帧，之前未结束的帧丢弃
+                self.pending = Some(Vec::new());
+                self.escaped = false;
+                continue;
+            }
+            let Some(frame) = self.pending.as_mut() else {
+                continue;
+            };
+            if self.escaped {
+                self.escaped = false;
+                let original = byte ^ ESCAPE_XOR;
+                if !needs_escape(original) {
+                    self.pending = None;
+                    continue;
+                }
+                frame.push(original);
+            } else if byte == DLE {
+                self.escaped = true;
+            } else if byte == FRAME_TAIL {
+                frames.push(FRAME_HEADER);
+                frames.append(frame);
+                frames.push(FRAME_TAIL);
+                self.pending = None;
+            } else if frame.len() >= max_len {
+                self.pending = None;
+            } else {
+                frame.push(byte);
+            }
+        }
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn non_zero(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 255) as u8 + 1).collect()
+    }
+
+    #[test]
+    fn cobs_round_trips_around_group_boundary() {
+        for len in [0, 1, 253, 254, 255, 508, 509] {
+            let data = non_zero(len);
+            let encoded = cobs_encode(&data);
+            assert!(!encoded.contains(&0), "len {}", len);
+            assert_eq!(cobs_decode(&encoded), Some(data), "len {}", len);
+        }
+    }
+
+    #[test]
+    fn cobs_full_group_has_no_trailing_code() {
+        // 恰好 254 个非零字节为一个完整的组，不再追加空组
+        let encoded = cobs_encode(&non_zero(254));
+        assert_eq!(encoded.len(), 255);
+        assert_eq!(encoded[0], 0xFF);
+
+        // 第 255 个字节开始新的一组
+        let encoded = cobs_encode(&non_zero(255));
+        assert_eq!(encoded.len(), 257);
+        assert_eq!(encoded[255], 2);
+    }
+
+    #[test]
+    fn cobs_round_trips_zeros() {
+        for data in [vec![0], vec![0, 0], vec![1, 0, 2], vec![0, 1, 0]] {
+            assert_eq!(cobs_decode(&cobs_encode(&data)), Some(data));
+        }
+        let mut data = non_zero(254);
+        data.push(0);
+        data.extend(non_zero(3));
+        assert_eq!(cobs_decode(&cobs_encode(&data)), Some(data));
+    }
+
+    #[test]
+    fn cobs_rejects_invalid_codes() {
+        assert_eq!(cobs_decode(&[0x00, 0x01]), None);
+        assert_eq!(cobs_decode(&[0x05, 0x01, 0x02]), None);
+    }
+
+    #[test]
+    fn slip_escapes_end_and_esc() {
+        let data = [0x01, SLIP_END, 0x02, SLIP_ESC, 0x03];
+        let encoded = slip_encode(&data);
+        assert_eq!(encoded, [0x01, SLIP_ESC, SLIP_ESC_END, 0x02, SLIP_ESC, SLIP_ESC_ESC, 0x03]);
+        assert!(!encoded.contains(&SLIP_END));
+        assert_eq!(slip_decode(&encoded), Some(data.to_vec()));
+    }
+
+    #[test]
+    fn slip_rejects_invalid_escape() {
+        assert_eq!(slip_decode(&[0x01, SLIP_ESC, 0x02]), None);
+        assert_eq!(slip_decode(&[0x01, SLIP_ESC]), None);
+    }
+
+    #[test]
+    fn packet_framer_keeps_partial_packets_across_reads() {
+        let mut framer = PacketFramer::default();
+        let packet = encode_packet(&Framing::Slip, &[0x01, SLIP_END, 0x02]);
+        let (first, second) = packet.split_at(3);
+        assert!(framer.push(&Framing::Slip, first, 64).is_empty());
+        assert_eq!(framer.push(&Framing::Slip, second, 64), vec![vec![0x01, SLIP_END, 0x02]]);
+    }
+
+    #[test]
+    fn packet_framer_drops_invalid_and_overlong_packets() {
+        let mut framer = PacketFramer::default();
+        let packets = framer.push(&Framing::Slip, &[0x01, SLIP_ESC, 0x02, SLIP_END, 0x03, SLIP_END], 64);
+        assert_eq!(packets, vec![vec![0x03]]);
+
+        // 超过 max_len 的包整个丢弃，下一个分隔字节之后的包正常解出
+        let mut data = cobs_encode(&[0x01; 10]);
+        data.push(COBS_DELIMITER);
+        data.extend(cobs_encode(&[0x07, 0x08]));
+        data.push(COBS_DELIMITER);
+        assert_eq!(framer.push(&Framing::Cobs, &data, 4), vec![vec![0x07, 0x08]]);
+    }
+
+    #[test]
+    fn escape_command_keeps_header_and_tail() {
+        let command = [0xCC, FRAME_HEADER, 0x01, DLE, FRAME_TAIL, FRAME_TAIL];
+        assert_eq!(
+            escape_command(&command),
+            [0xCC, DLE, FRAME_HEADER ^ ESCAPE_XOR, 0x01, DLE, DLE ^ ESCAPE_XOR, DLE, FRAME_TAIL ^ ESCAPE_XOR, FRAME_TAIL]
+        );
+        assert!(escape_command(&[]).is_empty());
+    }
+
+    #[test]
+    fn escape_framer_joins_frames_split_across_reads() {
+        let mut framer = EscapeFramer::default();
+        let stream = [FRAME_HEADER, 0x01, DLE, FRAME_TAIL ^ ESCAPE_XOR, DLE, FRAME_HEADER ^ ESCAPE_XOR, FRAME_TAIL];
+        // 在转义前缀之后切开
+        assert!(framer.push(&stream[..3], 64).is_empty());
+        assert_eq!(framer.push(&stream[3..], 64), [FRAME_HEADER, 0x01, FRAME_TAIL, FRAME_HEADER, FRAME_TAIL]);
+    }
+
+    #[test]
+    fn escape_framer_drops_bad_frames() {
+        let mut framer = EscapeFramer::default();
+        // 转义后的字节不是 AA/BF/10
+        assert!(framer.push(&[FRAME_HEADER, 0x01, DLE, 0x41, 0x02, FRAME_TAIL], 64).is_empty());
+        // 超过 max_len 的帧
+        assert!(framer.push(&[FRAME_HEADER, 1, 2, 3, 4, 5, FRAME_TAIL], 4).is_empty());
+        // 新的帧头丢弃未结束的帧
+        assert_eq!(framer.push(&[FRAME_HEADER, 0x01, FRAME_HEADER, 0x02, FRAME_TAIL], 64), [FRAME_HEADER, 0x02, FRAME_TAIL]);
+    }
+}
@@ -0,0 +1,47 @@
+//! 矩阵摇杆的协议处理，不依赖 Tauri，可嵌入其他 Rust 工具：
+//!
+//! - [`serial`]：串口的打开、读写；[`transport`]：本地串口、TCP/RFC 2217 远程串口与模拟串口；
+//!   [`permissions`]：串口权限检查
+//! - [`matrix`]：帧解码（[`matrix::FrameDecoder`]）与带连接管理的解析器（[`matrix::DataParser`]）
+//! - [`command`]：发给设备的命令帧；[`response`]：设备应答的解码
+//! - [`config`]：协议、串口和上位机配置（[`config::MatrixConfig`]）
+//! - [`framing`]：COBS/SLIP 包的编解码与定界帧的转义
+//! - `bootloader`：固件下载（`bootloader` 功能，默认启用）
+//! - [`conformance`]：协议一致性测试向量
+//! - [`messages`]：带消息代码的错误类型 [`messages::AppError`]
+//! - [`scheduler`]、[`watch`]：配置中的定时任务与监视表达式
+//!
+//! 嵌入时不需要 Tauri 的事件层：由调用方循环调用 [`matrix::DataParser::read_and_parse`]，
+//! 其他任务通过 `subscribe_connection`（连接状态）、`subscribe_frames`（最新的有效帧）
+//! 和 `subscribe_key_edges`（每个按键变化）订阅解析结果。解析器放在锁中由多个任务共享时，
+//! 改用 `start_read`/`finish_read`，等待串口数据期间不持有锁：
+//!
+//! ```ignore
+//! let config = MatrixConfig::load();
+//! let mut parser = DataParser::new(config.clone());
+//! let mut edges = parser.subscribe_key_edges();
+//! tokio::spawn(async move {
+//!     while let Ok(edge) = edges.recv().await {
+//!         println!("key {} {}", edge.index, edge.pressed);
+//!     }
+//! });
+//! parser.connect(SerialManager::new(config.serial_matrix.clone()).await?).await;
+//! loop {
+//!     parser.read_and_parse().await?;
+//! }
+//! ```
+
+#[cfg(feature = "bootloader")]
+pub mod bootloader;
+pub mod command;
+pub mod config;
+pub mod conformance;
+pub mod framing;
+pub mod matrix;
+pub mod messages;
+pub mod permissions;
+pub mod response;
+pub mod scheduler;
+pub mod serial;
+pub mod transport;
+pub mod watch;
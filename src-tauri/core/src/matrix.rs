@@ -0,0 +1,959 @@
+use crate::serial::{PortReader, SerialManager};
+use crate::config::{ByteOrder, ChecksumType, FieldSpec, FieldType, Framing, MatrixConfig, ProtocolConfig};
+use crate::framing::{self, EscapeFramer, PacketFramer};
+use crate::messages::{self, AppError};
+use crate::response::{DecodedResponse, ResponseDecoder};
+use tokio::sync::{broadcast, watch, Mutex};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const MAX_FRAME_ERRORS: usize = 50;  // 保留最近的校验失败帧数量
+const MAX_READ_ERRORS: u8 = 5;  // 连续读取失败达到此次数视为连接断开
+const FRAME_POOL_SIZE: usize = 4;  // 循环使用的帧数量
+const WRONG_BAUD_WINDOW: usize = 512;  // 连续收到这么多字节仍没有完整帧时进行诊断
+const WRONG_BAUD_ENTROPY: f64 = 5.0;  // 字节熵（比特/字节）高于此值视为乱码
+const PRESENCE_CHECK_INTERVAL: Duration = Duration::from_secs(1);  // 没有数据时检查设备是否被拔出的间隔
+const KEY_EDGE_CAPACITY: usize = 256;  // 按键变化订阅者最多落后的事件数
+const COMMON_BAUD_RATES: [u32; 8] = [9600, 19200, 38400, 57600, 115200, 230400, 460800, 921600];
+
+// 按配置换算后的ADC物理量
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ScaledAdc {
+    pub channel: usize,
+    pub value: f64,
+    pub unit: String,
+}
+
+// 连接状态，供 DataParser::subscribe_connection 的订阅者使用
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum ConnectionState {
+    Disconnected,
+    Connected,
+}
+
+// 按键状态变化
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct KeyEdge {
+    pub index: usize,
+    pub pressed: bool,
+}
+
+// 校验失败的帧，供排查问题使用
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FrameError {
+    pub timestamp: u64,  // 毫秒时间戳
+    pub frame: String,  // 出错帧的十六进制内容
+    pub expected_checksum: u8,  // 帧中携带的校验值
+    pub computed_checksum: u8,  // 实际计算的校验值
+    pub context: String,  // 本次读取的完整缓冲区
+}
+
+// 已连接但只收到无法同步的数据，可能是波特率不一致；只用于 AA/BF 定界分帧
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BaudDiagnosis {
+    pub baud_rate: u32,  // 当前波特率
+    pub bytes_without_frame: usize,
+    pub sync_bytes_seen: usize,  // 其中 0xAA 帧头出现的次数
+    pub entropy: f64,  // 比特/字节
+    pub suggested_rates: Vec<u32>,  // 建议尝试的波特率，按与当前值的接近程度排序
+}
+
+// 统计自上一个完整帧以来收到的字节
+struct SyncMonitor {
+    bytes_without_frame: usize,
+    histogram: [usize; 256],
+    reported: bool,  // 每次失去同步只报告一次
+}
+
+impl SyncMonitor {
+    fn new() -> Self {
+        Self {
+            bytes_without_frame: 0,
+            histogram: [0; 256],
+            reported: false,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    fn entropy(&self) -> f64 {
+        let total = self.bytes_without_frame as f64;
+        self.histogram
+            .iter()
+            .filter(|&&n| n > 0)
+            .map(|&n| {
+                let p = n as f64 / total;
+                -p * p.log2()
+            })
+            .sum()
+    }
+
+    // 记录一次读取，满足条件时返回诊断
+    fn observe(&mut self, data: &[u8], has_frame: bool, baud_rate: u32) -> Option<BaudDiagnosis> {
+        if has_frame {
+            self.reset();
+            return None;
+        }
+        self.bytes_without_frame += data.len();
+        for &b in data {
+            self.histogram[b as usize] += 1;
+        }
+        if self.reported || self.bytes_without_frame < WRONG_BAUD_WINDOW {
+            return None;
+        }
+
+        // 没有帧头，或字节分布接近随机
+        let sync_bytes_seen = self.histogram[0xAA];
+        let entropy = self.entropy();
+        if sync_bytes_seen > 0 && entropy < WRONG_BAUD_ENTROPY {
+            return None;
+        }
+        self.reported = true;
+
+        let mut suggested_rates: Vec<u32> = COMMON_BAUD_RATES
+            .iter()
+            .copied()
+            .filter(|&rate| rate != baud_rate)
+            .collect();
+        suggested_rates.sort_by(|a, b| {
+            let distance = |rate: u32| (rate as f64 / baud_rate as f64).ln().abs();
+            distance(*a).total_cmp(&distance(*b))
+        });
+        Some(BaudDiagnosis {
+            baud_rate,
+            bytes_without_frame: self.bytes_without_frame,
+            sync_bytes_seen,
+            entropy,
+            suggested_rates,
+        })
+    }
+}
+
+// 间隔分帧：静默超过 idle 后，之前收到的字节组成一段，段首即帧首
+#[derive(Default)]
+struct GapFramer {
+    pending: Vec<u8>,
+    last_rx: Option<Instant>,
+}
+
+impl GapFramer {
+    // 加入本次读取的数据，返回已结束的一段（没有时为空）；一直没有静默、累积超过 max_len 时丢弃
+    fn push(&mut self, data: &[u8], idle: Duration, max_len: usize) -> Vec<u8> {
+        let silent = self.last_rx.is_some_and(|at| at.elapsed() >= idle);
+        let mut complete = Vec::new();
+        if silent && !self.pending.is_empty() {
+            complete = std::mem::take(&mut self.pending);
+        }
+        if !data.is_empty() {
+            if self.pending.len() + data.len() > max_len {
+                self.pending.clear();
+            }
+            self.pending.extend_from_slice(data);
+            self.last_rx = Some(Instant::now());
+        }
+        complete
+    }
+}
+
+// 一次读取的结果
+#[derive(Default)]
+pub struct ReadOutcome {
+    pub key_edges: Option<Vec<KeyEdge>>,  // 解析出新的有效帧时为相对上一帧的按键变化
+    pub frame_errors: Vec<FrameError>,
+    pub baud_diagnosis: Option<BaudDiagnosis>,
+    pub responses: Vec<DecodedResponse>,  // 本次数据中按配置布局解码出的设备应答
+}
+
+// 已取出读取端、尚未读取的一次读取，见 DataParser::start_read
+pub struct PendingRead {
+    reader: PortReader,
+    buffer: Vec<u8>,
+    generation: u64,
+}
+
+impl PendingRead {
+    pub async fn read(self) -> CompletedRead {
+        let (buffer, result) = self.reader.read(self.buffer).await;
+        CompletedRead {
+            buffer,
+            result,
+            generation: self.generation,
+        }
+    }
+}
+
+// 读取完成、等待 DataParser::finish_read 解析的数据
+pub struct CompletedRead {
+    buffer: Vec<u8>,
+    result: Result<usize, String>,
+    generation: u64,
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ParsedData {
+    pub index: u8,
+    pub keys: Vec<bool>,
+    pub adc: Vec<u8>,
+    pub leds: Vec<bool>,
+    pub raw_data: Vec<u8>,
+    pub valid: bool,
+    pub scaled_adc: Vec<ScaledAdc>,  // 仅包含配置了换算的通道
+    pub fields: HashMap<String, Value>,  // 协议中自定义字段的解码结果
+}
+
+impl ParsedData {
+    // 按协议的数量创建空数据
+    pub fn new(protocol: &ProtocolConfig) -> Self {
+        Self {
+            index: 0,
+            keys: vec![false; protocol.key_count],
+            adc: vec![0; protocol.adc_count],
+            leds: vec![false; protocol.led_count],
+            raw_data: Vec::new(),
+            valid: false,
+            scaled_adc: Vec::new(),
+            fields: HashMap::new(),
+        }
+    }
+}
+
+fn not_connected() -> AppError {
+    AppError::new(messages::SERIAL_NOT_CONNECTED, "串口未连接")
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// 帧解码器：根据协议配置计算各字段偏移，协议变化时整体替换
+#[derive(Debug, Clone)]
+pub struct FrameDecoder {
+    protocol: ProtocolConfig,
+    key_offset: usize,
+    adc_offset: usize,
+    led_offset: usize,
+    checksum_offset: usize,
+    frame_len: usize,
+}
+
+impl FrameDecoder {
+    pub fn new(protocol: &ProtocolConfig) -> Self {
+        let delimiter = protocol.framing.delimiter_len();
+        let key_offset = delimiter + 1;
+        let adc_offset = key_offset + protocol.key_count.div_ceil(8);
+        let led_offset = adc_offset + protocol.adc_count;
+        let checksum_offset = led_offset + protocol.led_count.div_ceil(8);
+        Self {
+            protocol: protocol.clone(),
+            key_offset,
+            adc_offset,
+            led_offset,
+            checksum_offset,
+            frame_len: checksum_offset + 1 + delimiter,
+        }
+    }
+    
+    pub fn protocol(&self) -> &ProtocolConfig {
+        &self.protocol
+    }
+    
+    // 数据中是否有帧头帧尾完整的帧（不论校验是否通过）；
+    // 间隔分帧和包分帧时没有帧头帧尾，只要长度够一帧即为 true，不能用于判断是否同步
+    pub fn has_frame(&self, data: &[u8]) -> bool {
+        self.frame_starts(data).next().is_some()
+    }
+    
+    // 所有帧起始位置（帧头帧尾匹配），从前往后
+    // 用 memchr 查找帧头，比逐字节比较快得多；间隔分帧和包分帧时数据由完整的帧首尾相接，按帧长切分
+    fn frame_starts<'a>(&'a self, data: &'a [u8]) -> impl DoubleEndedIterator<Item = usize> + 'a {
+        let gap = self.protocol.framing != Framing::Delimited;
+        let frame_len = self.frame_len;
+        let aligned = (0..if gap { data.len() / frame_len } else { 0 }).map(move |k| k * frame_len);
+        let last = frame_len - 1;
+        let candidates = if gap { &[][..] } else { &data[..data.len().saturating_sub(last)] };
+        let delimited = memchr::memchr_iter(0xAA, candidates).filter(move |&i| data[i + last] == 0xBF);
+        aligned.chain(delimited)
+    }
+    
+    // 返回（帧中携带的校验值，计算出的校验值）
+    fn checksums(&self, frame: &[u8]) -> (u8, u8) {
+        let covered = &frame[self.protocol.checksum_coverage.range(self.checksum_offset)];
+        let computed = match self.protocol.checksum {
+            ChecksumType::Xor => xor_checksum(covered),
+            ChecksumType::Sum => covered.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)),
+            ChecksumType::None => frame[self.checksum_offset],
+        };
+        (frame[self.checksum_offset], computed)
+    }
+    
+    // 把一帧解析到已有的 ParsedData 中，复用其中的缓冲区
+    fn decode_frame_into(&self, frame: &[u8], parsed: &mut ParsedData) {
+        parsed.index = frame[self.key_offset - 1];
+        
+        // 解析按键数据
+        parsed.keys.resize(self.protocol.key_count, false);
+        let key_bytes = &frame[self.key_offset..self.adc_offset];
+        for (i, key) in parsed.keys.iter_mut().enumerate() {
+            let (byte, bit) = self.protocol.key_bits.position(i, key_bytes.len());
+            *key = (key_bytes[byte] & (1 << bit)) != 0;
+        }
+        
+        // 解析ADC数据
+        parsed.adc.clear();
+        parsed.adc.extend_from_slice(&frame[self.adc_offset..self.led_offset]);
+        
+        // 解析LED状态
+        parsed.leds.resize(self.protocol.led_count, false);
+        let led_bytes = &frame[self.led_offset..self.checksum_offset];
+        for (i, led) in parsed.leds.iter_mut().enumerate() {
+            let (byte, bit) = self.protocol.led_bits.position(i, led_bytes.len());
+            *led = (led_bytes[byte] & (1 << bit)) != 0;
+        }
+        
+        // 解析自定义字段；没有配置时不分配内存
+        if !self.protocol.fields.is_empty() || !parsed.fields.is_empty() {
+            parsed.fields.clear();
+            for field in &self.protocol.fields {
+                parsed.fields.insert(field.name.clone(), decode_field(frame, field));
+            }
+        }
+    }
+    
+    pub fn decode(&self, data: &[u8]) -> ParsedData {
+        let mut parsed = ParsedData::new(&self.protocol);
+        self.decode_into(data, &mut parsed);
+        parsed
+    }
+    
+    // 解析到已有的 ParsedData 中；容量足够时不分配内存。scaled_adc 不在此处理
+    pub fn decode_into(&self, data: &[u8], parsed: &mut ParsedData) {
+        parsed.raw_data.clear();
+        parsed.raw_data.extend_from_slice(data);
+        
+        // 查找最新的有效帧（从后往前搜索），确保只处理最新的一帧
+        for i in self.frame_starts(data).rev() {
+            let frame = &data[i..i + self.frame_len];
+            let (expected, computed) = self.checksums(frame);
+            if expected == computed {
+                self.decode_frame_into(frame, parsed);
+                parsed.valid = true;
+                return;
+            }
+        }
+        
+        // 如果没有找到有效帧，使用最后一个帧（标记为无效）
+        parsed.valid = false;
+        match self.frame_starts(data).next_back() {
+            Some(i) => self.decode_frame_into(&data[i..i + self.frame_len], parsed),
+            None => {
+                parsed.index = 0;
+                parsed.keys.clear();
+                parsed.keys.resize(self.protocol.key_count, false);
+                parsed.adc.clear();
+                parsed.adc.resize(self.protocol.adc_count, 0);
+                parsed.leds.clear();
+                parsed.leds.resize(self.protocol.led_count, false);
+                parsed.fields.clear();
+            }
+        }
+    }
+    
+    // 找出缓冲区中帧头帧尾完整但校验失败的帧
+    pub fn find_checksum_errors(&self, data: &[u8]) -> Vec<FrameError> {
+        self.frame_starts(data)
+            .filter_map(|i| {
+                let frame = &data[i..i + self.frame_len];
+                let (expected, computed) = self.checksums(frame);
+                (expected != computed).then(|| FrameError {
+                    timestamp: now_ms(),
+                    frame: to_hex(frame),
+                    expected_checksum: expected,
+                    computed_checksum: computed,
+                    context: to_hex(data),
+                })
+            })
+            .collect()
+    }
+}
+
+// 解码一个自定义字段；单个值直接返回，多个值返回数组，超出帧的字段为 null
+fn decode_field(frame: &[u8], field: &FieldSpec) -> Value {
+    let Some(bytes) = field.byte_len().and_then(|len| field.offset.checked_add(len)).and_then(|end| frame.get(field.offset..end)) else {
+        return Value::Null;
+    };
+    let values: Vec<Value> = match field.field_type {
+        FieldType::Bitfield => (0..field.length)
+            .map(|i| {
+                let (byte, bit) = field.bits.position(i, bytes.len());
+                Value::from(bytes[byte] & (1 << bit) != 0)
+            })
+            .collect(),
+        FieldType::U8 => bytes.iter().map(|&b| Value::from(b)).collect(),
+        FieldType::U16 | FieldType::I16 => bytes
+            .chunks_exact(2)
+            .map(|pair| {
+                let raw = [pair[0], pair[1]];
+                let value = match field.bits.byte_order {
+                    ByteOrder::Little => u16::from_le_bytes(raw),
+                    ByteOrder::Big => u16::from_be_bytes(raw),
+                };
+                if field.field_type == FieldType::I16 {
+                    Value::from(value as i16)
+                } else {
+                    Value::from(value)
+                }
+            })
+            .collect(),
+    };
+    match <[Value; 1]>::try_from(values) {
+        Ok([value]) => value,
+        Err(values) => Value::Array(values),
+    }
+}
+
+// 按8字节一组异或后再折叠，编译器可以向量化
+fn xor_checksum(data: &[u8]) -> u8 {
+    let chunks = data.chunks_exact(8);
+    let tail = chunks.remainder().iter().fold(0u8, |acc, b| acc ^ b);
+    let wide = chunks.fold(0u64, |acc, chunk| {
+        acc ^ u64::from_le_bytes(chunk.try_into().unwrap_or_default())
+    });
+    wide.to_le_bytes().iter().fold(tail, |acc, b| acc ^ b)
+}
+
+pub struct DataParser {
+    serial: Arc<Mutex<Option<SerialManager>>>,
+    parsed_data: Arc<Mutex<Arc<ParsedData>>>,
+    pool: Vec<Arc<ParsedData>>,  // 已不再被引用的帧，循环使用以避免每帧分配
+    config: Arc<Mutex<MatrixConfig>>,
+    error_count: Arc<Mutex<u8>>, // 连续读取失败的次数
+    frame_errors: Arc<Mutex<VecDeque<FrameError>>>,  // 最近的校验失败帧
+    decoder: FrameDecoder,
+    sync_monitor: SyncMonitor,
+    responses: ResponseDecoder,
+    gap: GapFramer,
+    packets: PacketFramer,
+    escape: EscapeFramer,
+    read_buffer: Vec<u8>,
+    read_size: usize,  // 以下两项来自串口配置
+    max_pending: usize,
+    last_presence_check: Instant,
+    generation: u64,  // 每次连接和断开时递增，用于丢弃旧连接的读取结果
+    connection_tx: watch::Sender<ConnectionState>,
+    frame_tx: watch::Sender<Arc<ParsedData>>,  // 最近一次的有效帧
+    key_edge_tx: broadcast::Sender<KeyEdge>,
+}
+
+impl DataParser {
+    pub fn new(config: MatrixConfig) -> Self {
+        let (connection_tx, _) = watch::channel(ConnectionState::Disconnected);
+        let (frame_tx, _) = watch::channel(Arc::new(ParsedData::new(&config.protocol)));
+        let (key_edge_tx, _) = broadcast::channel(KEY_EDGE_CAPACITY);
+        Self {
+            connection_tx,
+            frame_tx,
+            key_edge_tx,
+            serial: Arc::new(Mutex::new(None)),
+            parsed_data: Arc::new(Mutex::new(Arc::new(ParsedData::new(&config.protocol)))),
+            pool: Vec::new(),
+            decoder: FrameDecoder::new(&config.protocol),
+            sync_monitor: SyncMonitor::new(),
+            responses: ResponseDecoder::new(&config.responses),
+            gap: GapFramer::default(),
+            packets: PacketFramer::default(),
+            escape: EscapeFramer::default(),
+            read_buffer: Vec::new(),
+            read_size: config.serial_matrix.read_size,
+            max_pending: config.serial_matrix.max_pending,
+            last_presence_check: Instant::now(),
+            generation: 0,
+            config: Arc::new(Mutex::new(config)),
+            error_count: Arc::new(Mutex::new(0)),
+            frame_errors: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+    
+    // 配置保存后同步给解析器；帧结构变化时替换解码器，无需重新连接。
+    // 解析器在读取之间才会被更新，替换总发生在两次读取（帧边界）之间
+    pub async fn update_config(&mut self, config: MatrixConfig) {
+        if config.protocol != *self.decoder.protocol() {
+            self.decoder = FrameDecoder::new(&config.protocol);
+            // 旧结构的数据已无法对应，按新结构重置，避免误报按键变化
+            *self.parsed_data.lock().await = Arc::new(ParsedData::new(&config.protocol));
+            self.pool.clear();
+            self.gap = GapFramer::default();
+            self.packets = PacketFramer::default();
+            self.escape = EscapeFramer::default();
+        }
+        self.responses = ResponseDecoder::new(&config.responses);
+        self.read_size = config.serial_matrix.read_size;
+        self.max_pending = config.serial_matrix.max_pending;
+        let mut guard = self.config.lock().await;
+        *guard = config;
+    }
+    
+    pub async fn connect(&mut self, serial: SerialManager) {
+        let mut guard = self.serial.lock().await;
+        *guard = Some(serial);
+        self.generation += 1;
+        self.sync_monitor.reset();
+        self.gap = GapFramer::default();
+        self.packets = PacketFramer::default();
+        self.escape = EscapeFramer::default();
+        // 连接时重置错误计数
+        let mut error_guard = self.error_count.lock().await;
+        *error_guard = 0;
+        self.connection_tx.send_replace(ConnectionState::Connected);
+    }
+    
+    pub async fn disconnect(&mut self) {
+        let mut guard = self.serial.lock().await;
+        if let Some(serial) = guard.as_mut() {
+            serial.close().await;
+        }
+        *guard = None;
+        self.generation += 1;
+        // 断开连接时重置错误计数
+        let mut error_guard = self.error_count.lock().await;
+        *error_guard = 0;
+        self.connection_tx.send_replace(ConnectionState::Disconnected);
+    }
+    
+    // 断开连接但不关闭串口，返回仍处于打开状态的句柄
+    pub async fn take_port(&mut self) -> Option<Box<dyn serialport::SerialPort>> {
+        let serial = self.serial.lock().await.take()?;
+        self.generation += 1;
+        *self.error_count.lock().await = 0;
+        self.connection_tx.send_replace(ConnectionState::Disconnected);
+        serial.take_port().await
+    }
+    
+    // 读取并解析一次数据
+    pub async fn read_and_parse(&mut self) -> Result<ReadOutcome, AppError> {
+        let read = self.start_read().await?.read().await;
+        self.finish_read(read).await
+    }
+    
+    // 取出读取端和读取缓冲区。读取可能阻塞到读取超时，解析器由多个任务共享时
+    // 应在释放解析器的锁后调用 PendingRead::read，再加锁把结果交给 finish_read
+    pub async fn start_read(&mut self) -> Result<PendingRead, AppError> {
+        let reader = self.serial.lock().await.as_ref().map(SerialManager::reader).ok_or_else(not_connected)?;
+        // 读取缓冲区在读取之间复用，出错提前返回时下次重新分配
+        let mut buffer = std::mem::take(&mut self.read_buffer);
+        buffer.resize(self.read_size, 0);
+        Ok(PendingRead {
+            reader,
+            buffer,
+            generation: self.generation,
+        })
+    }
+    
+    // 解析一次读取的结果；读取期间连接已断开或重连时丢弃结果
+    pub async fn finish_read(&mut self, read: CompletedRead) -> Result<ReadOutcome, AppError> {
+        let CompletedRead { buffer, result: read_result, generation } = read;
+        if generation != self.generation {
+            self.read_buffer = buffer;
+            return if self.is_connected().await {
+                Ok(ReadOutcome::default())
+            } else {
+                Err(not_connected())
+            };
+        }
+        // 没有数据时定期检查设备是否还在
+        let present = {
+            let guard = self.serial.lock().await;
+            let Some(serial) = guard.as_ref() else {
+                return Err(not_connected());
+            };
+            let idle = matches!(read_result, Ok(0));
+            if idle && self.last_presence_check.elapsed() >= PRESENCE_CHECK_INTERVAL {
+                self.last_presence_check = Instant::now();
+                serial.is_present()
+            } else {
+                true
+            }
+        };
+        if !present {
+            self.disconnect().await;
+            return Err(AppError::new(messages::SERIAL_CONNECTION_LOST, "串口设备已被移除")
+                .param("detail", "device removed"));
+        }
+        
+        let read_len = match read_result {
+            Ok(len) => {
+                // 成功读取数据，重置错误计数
+                let mut error_guard = self.error_count.lock().await;
+                *error_guard = 0;
+                len
+            },
+            Err(e) => {
+                // 读取失败，检查错误计数
+                let mut error_guard = self.error_count.lock().await;
+                *error_guard += 1;
+                if *error_guard < MAX_READ_ERRORS {
+                    return Err(e.into());
+                }
+                // 连续读取失败，认为设备已断开，关闭串口以便重新连接
+                drop(error_guard);
+                self.disconnect().await;
+                return Err(AppError::new(messages::SERIAL_CONNECTION_LOST, format!("串口连接已断开: {}", e))
+                    .param("detail", e));
+            }
+        };
+        
+        // 间隔分帧时等到静默后才处理已结束的一段；包分帧时只处理已结束的包，
+        // 长度与帧长一致的包首尾相接作为数据帧，所有包都用于应答解码
+        let mut outcome = ReadOutcome::default();
+        let chunk: Vec<u8>;
+        let framing = self.decoder.protocol().framing;
+        let data: &[u8] = match framing {
+            // 转义时去掉转义后的帧首尾相接，按普通定界帧解析
+            Framing::Delimited if self.decoder.protocol().escape => {
+                chunk = self.escape.push(&buffer[0..read_len], self.max_pending);
+                &chunk
+            }
+            Framing::Delimited => &buffer[0..read_len],
+            Framing::Gap { idle_ms } => {
+                chunk = self.gap.push(&buffer[0..read_len], Duration::from_millis(idle_ms), self.max_pending);
+                &chunk
+            }
+            Framing::Cobs | Framing::Slip => {
+                let packets = self.packets.push(&framing, &buffer[0..read_len], self.max_pending);
+                if !self.responses.is_empty() {
+                    for packet in &packets {
+                        outcome.responses.extend(self.responses.decode(packet));
+                    }
+                }
+                let frame_len = self.decoder.protocol().frame_len();
+                chunk = packets.into_iter().filter(|p| p.len() == frame_len).flatten().collect();
+                &chunk
+            }
+        };
+        
+        if !data.is_empty() {
+            // 波特率诊断依靠 AA/BF 判断是否同步，间隔分帧和包分帧时没有帧头帧尾，不做诊断
+            if framing == Framing::Delimited {
+                let baud_rate = self.config.lock().await.serial_matrix.baud_rate;
+                outcome.baud_diagnosis =
+                    self.sync_monitor.observe(data, self.decoder.has_frame(data), baud_rate);
+            }
+            outcome.frame_errors = self.decoder.find_checksum_errors(data);
+            if !self.responses.is_empty() && !framing.is_packet() {
+                outcome.responses = self.responses.decode(data);
+            }
+            if !outcome.frame_errors.is_empty() {
+                let mut errors = self.frame_errors.lock().await;
+                for error in &outcome.frame_errors {
+                    if errors.len() >= MAX_FRAME_ERRORS {
+                        errors.pop_front();
+                    }
+                    errors.push_back(error.clone());
+                }
+            }
+        }
+        
+        if !data.is_empty() {
+            // 只处理最新读取的数据，不累积
+            let mut next = self.take_pooled();
+            let mut data_guard = self.parsed_data.lock().await;
+            // 池中的帧都没有其他引用，make_mut 不会复制
+            let slot = Arc::make_mut(&mut next);
+            self.decoder.decode_into(data, slot);
+            
+            if slot.valid {
+                self.scale_adc(slot).await;
+                outcome.key_edges = Some(self.publish_key_edges(&data_guard.keys, &slot.keys));
+            } else {
+                // 保留上一帧的数据，只更新原始数据和有效标志
+                let raw = std::mem::take(&mut slot.raw_data);
+                slot.clone_from(&data_guard);
+                slot.raw_data.clear();
+                slot.raw_data.extend_from_slice(&raw);
+                slot.valid = false;
+            }
+            let previous = std::mem::replace(&mut *data_guard, next);
+            // 先替换订阅通道中的旧帧，旧帧不再被引用时才能回收
+            if data_guard.valid {
+                self.frame_tx.send_replace(Arc::clone(&data_guard));
+            }
+            drop(data_guard);
+            self.recycle(previous);
+        }
+        
+        self.read_buffer = buffer;
+        Ok(outcome)
+    }
+    
+    // 注入模拟输入：在最新数据上修改后作为新的有效帧，返回按键变化。
+    // 用于设备未连接时测试绑定，连接时下一帧真实数据会覆盖模拟的值
+    pub async fn inject(&mut self, change: impl FnOnce(&mut ParsedData)) -> Vec<KeyEdge> {
+        let protocol = self.decoder.protocol().clone();
+        let mut data_guard = self.parsed_data.lock().await;
+        let mut next = ParsedData::clone(&data_guard);
+        next.keys.resize(protocol.key_count, false);
+        next.adc.resize(protocol.adc_count, 0);
+        next.leds.resize(protocol.led_count, false);
+        change(&mut next);
+        next.raw_data.clear();
+        next.valid = true;
+        self.scale_adc(&mut next).await;
+        let edges = self.publish_key_edges(&data_guard.keys, &next.keys);
+        *data_guard = Arc::new(next);
+        self.frame_tx.send_replace(Arc::clone(&data_guard));
+        edges
+    }
+    
+    // 比较前后两帧的按键，把变化发给订阅者
+    fn publish_key_edges(&self, old: &[bool], new: &[bool]) -> Vec<KeyEdge> {
+        let edges = old.iter()
+            .zip(new.iter())
+            .enumerate()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(index, (_, &pressed))| KeyEdge { index, pressed })
+            .collect::<Vec<_>>();
+        for edge in &edges {
+            // 没有订阅者时发送失败，忽略
+            let _ = self.key_edge_tx.send(edge.clone());
+        }
+        edges
+    }
+    
+    // 连接状态的订阅，连接、断开和连接丢失时更新
+    pub fn subscribe_connection(&self) -> watch::Receiver<ConnectionState> {
+        self.connection_tx.subscribe()
+    }
+    
+    // 最近一次有效帧的订阅；订阅者只会看到最新值，处理慢时中间的帧被跳过
+    pub fn subscribe_frames(&self) -> watch::Receiver<Arc<ParsedData>> {
+        self.frame_tx.subscribe()
+    }
+    
+    // 按键变化的订阅，每个变化都会送达；落后超过 256 个事件时收到 Lagged 错误
+    pub fn subscribe_key_edges(&self) -> broadcast::Receiver<KeyEdge> {
+        self.key_edge_tx.subscribe()
+    }
+    
+    // 从池中取一个未被引用的帧，池中没有可用的帧时分配新帧
+    fn take_pooled(&mut self) -> Arc<ParsedData> {
+        while let Some(mut frame) = self.pool.pop() {
+            if Arc::get_mut(&mut frame).is_some() {
+                return frame;
+            }
+        }
+        Arc::new(ParsedData::new(self.decoder.protocol()))
+    }
+    
+    // 旧帧若已没有其他引用则放回池中；仍被事件订阅者持有的帧直接丢弃
+    fn recycle(&mut self, mut frame: Arc<ParsedData>) {
+        if self.pool.len() < FRAME_POOL_SIZE && Arc::get_mut(&mut frame).is_some() {
+            self.pool.push(frame);
+        }
+    }
+    
+    pub async fn get_frame_errors(&self) -> Vec<FrameError> {
+        let guard = self.frame_errors.lock().await;
+        guard.iter().cloned().collect()
+    }
+    
+    // 换算配置了单位的ADC通道，通道和单位不变时只更新数值，避免每帧复制单位字符串
+    async fn scale_adc(&self, parsed: &mut ParsedData) {
+        let config = self.config.lock().await;
+        let scalings = config.adc_scaling.iter().filter(|s| s.channel < parsed.adc.len());
+        let mut count = 0;
+        for s in scalings {
+            let value = s.apply(parsed.adc[s.channel]);
+            match parsed.scaled_adc.get_mut(count) {
+                Some(entry) if entry.channel == s.channel && entry.unit == s.unit => entry.value = value,
+                _ => {
+                    parsed.scaled_adc.truncate(count);
+                    parsed.scaled_adc.push(ScaledAdc {
+                        channel: s.channel,
+                        value,
+                        unit: s.unit.clone(),
+                    });
+                }
+            }
+            count += 1;
+        }
+        parsed.scaled_adc.truncate(count);
+    }
+    
+    // 当前使用的协议配置
+    pub fn protocol(&self) -> &ProtocolConfig {
+        self.decoder.protocol()
+    }
+    
+    pub fn parse_data(&self, data: &[u8]) -> ParsedData {
+        self.decoder.decode(data)
+    }
+    
+    pub async fn get_parsed_data(&self) -> Arc<ParsedData> {
+        let guard = self.parsed_data.lock().await;
+        Arc::clone(&guard)
+    }
+    
+    pub async fn get_raw_data(&self) -> Vec<u8> {
+        let guard = self.parsed_data.lock().await;
+        guard.raw_data.clone()
+    }
+    
+    pub async fn get_keys(&self) -> Vec<bool> {
+        let guard = self.parsed_data.lock().await;
+        guard.keys.clone()
+    }
+    
+    pub async fn get_adc(&self) -> Vec<u8> {
+        let guard = self.parsed_data.lock().await;
+        guard.adc.clone()
+    }
+    
+    pub async fn get_leds(&self) -> Vec<bool> {
+        let guard = self.parsed_data.lock().await;
+        guard.leds.clone()
+    }
+    
+    pub async fn is_connected(&self) -> bool {
+        self.serial.lock().await.is_some()
+    }
+    
+    pub async fn is_data_valid(&self) -> bool {
+        let guard = self.parsed_data.lock().await;
+        guard.valid
+    }
+    
+    // 发送 command 模块构造的命令帧，包分帧或转义时按协议编码后发送
+    pub async fn send_command(&self, command: &[u8]) -> Result<usize, AppError> {
+        let protocol = self.decoder.protocol();
+        if protocol.escape {
+            self.send_packet(framing::escape_command(command)).await
+        } else {
+            self.send_raw(command).await
+        }
+    }
+    
+    // 发送用户给出的字节（命令模板、校准命令），不做转义；包分帧时仍需编码为包，设备才能分隔
+    pub async fn send_raw(&self, data: &[u8]) -> Result<usize, AppError> {
+        self.send_packet(framing::encode_packet(&self.decoder.protocol().framing, data)).await
+    }
+    
+    async fn send_packet(&self, packet: Vec<u8>) -> Result<usize, AppError> {
+        let mut serial_guard = self.serial.lock().await;
+        if let Some(serial) = serial_guard.as_mut() {
+            Ok(serial.send(&packet).await?)
+        } else {
+            Err(not_connected())
+        }
+    }
+    
+    // 设置 DTR/RTS 控制线电平
+    pub async fn set_dtr(&self, level: bool) -> Result<(), AppError> {
+        match self.serial.lock().await.as_ref() {
+            Some(serial) => Ok(serial.set_dtr(level).await?),
+            None => Err(not_connected()),
+        }
+    }
+    
+    pub async fn set_rts(&self, level: bool) -> Result<(), AppError> {
+        match self.serial.lock().await.as_ref() {
+            Some(serial) => Ok(serial.set_rts(level).await?),
+            None => Err(not_connected()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BitOrder;
+
+    fn field(offset: usize, length: usize, field_type: FieldType, bit_order: BitOrder, byte_order: ByteOrder) -> FieldSpec {
+        FieldSpec {
+            name: "f".to_string(),
+            offset,
+            length,
+            field_type,
+            bits: crate::config::BitLayout { bit_order, byte_order },
+        }
+    }
+
+    fn bools(values: &[bool]) -> Value {
+        Value::Array(values.iter().map(|&v| Value::from(v)).collect())
+    }
+
+    #[test]
+    fn decodes_bitfields_in_each_layout() {
+        // 0x01 0x80：第一个字节最低位和第二个字节最高位为 1
+        let frame = [0xAA, 0x01, 0x80];
+        let mut expected = [false; 16];
+
+        expected[0] = true;
+        expected[15] = true;
+        let spec = field(1, 16, FieldType::Bitfield, BitOrder::LsbFirst, ByteOrder::Little);
+        assert_eq!(decode_field(&frame, &spec), bools(&expected));
+
+        expected = [false; 16];
+        expected[7] = true;
+        expected[8] = true;
+        let spec = field(1, 16, FieldType::Bitfield, BitOrder::MsbFirst, ByteOrder::Little);
+        assert_eq!(decode_field(&frame, &spec), bools(&expected));
+
+        expected = [false; 16];
+        expected[7] = true;
+        expected[8] = true;
+        let spec = field(1, 16, FieldType::Bitfield, BitOrder::LsbFirst, ByteOrder::Big);
+        assert_eq!(decode_field(&frame, &spec), bools(&expected));
+
+        expected = [false; 16];
+        expected[0] = true;
+        expected[15] = true;
+        let spec = field(1, 16, FieldType::Bitfield, BitOrder::MsbFirst, ByteOrder::Big);
+        assert_eq!(decode_field(&frame, &spec), bools(&expected));
+    }
+
+    #[test]
+    fn decodes_partial_bitfield_and_single_values() {
+        let frame = [0xAA, 0b0000_0101];
+        let spec = field(1, 3, FieldType::Bitfield, BitOrder::LsbFirst, ByteOrder::Little);
+        assert_eq!(decode_field(&frame, &spec), bools(&[true, false, true]));
+
+        // 单个值不包成数组
+        let spec = field(1, 1, FieldType::Bitfield, BitOrder::LsbFirst, ByteOrder::Little);
+        assert_eq!(decode_field(&frame, &spec), Value::from(true));
+        let spec = field(1, 1, FieldType::U8, BitOrder::LsbFirst, ByteOrder::Little);
+        assert_eq!(decode_field(&frame, &spec), Value::from(5));
+    }
+
+    #[test]
+    fn decodes_16_bit_values_in_both_byte_orders() {
+        let frame = [0xAA, 0x34, 0x12, 0xFF, 0xFF];
+        let spec = field(1, 1, FieldType::U16, BitOrder::LsbFirst, ByteOrder::Little);
+        assert_eq!(decode_field(&frame, &spec), Value::from(0x1234));
+        let spec = field(1, 1, FieldType::U16, BitOrder::LsbFirst, ByteOrder::Big);
+        assert_eq!(decode_field(&frame, &spec), Value::from(0x3412));
+        let spec = field(1, 2, FieldType::I16, BitOrder::LsbFirst, ByteOrder::Little);
+        assert_eq!(decode_field(&frame, &spec), Value::Array(vec![Value::from(0x1234), Value::from(-1)]));
+    }
+
+    #[test]
+    fn out_of_range_fields_are_null() {
+        let frame = [0xAA, 0x01, 0x02];
+        let spec = field(2, 2, FieldType::U8, BitOrder::LsbFirst, ByteOrder::Little);
+        assert_eq!(decode_field(&frame, &spec), Value::Null);
+        let spec = field(usize::MAX, 2, FieldType::U16, BitOrder::LsbFirst, ByteOrder::Little);
+        assert_eq!(decode_field(&frame, &spec), Value::Null);
+    }
+}
@@ -0,0 +1,60 @@
+// 面向用户的错误：带稳定的消息代码和参数，前端据此从 i18n 表中取对应语言的文本，
+// 日志中也输出代码；message 为中文原文，前端找不到代码时直接显示
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+
+pub const GENERIC: &str = "generic";
+pub const SERIAL_NOT_CONNECTED: &str = "serial.notConnected";
+pub const SERIAL_CONNECTION_LOST: &str = "serial.connectionLost";
+pub const SERIAL_PERMISSION_DENIED: &str = "serial.permissionDenied";
+pub const SERIAL_PORT_BUSY: &str = "serial.portBusy";
+pub const SERIAL_PORT_AMBIGUOUS: &str = "serial.portAmbiguous";
+pub const SERIAL_INVALID_SETTINGS: &str = "serial.invalidSettings";
+pub const SERIAL_DEVICE_NOT_FOUND: &str = "serial.deviceNotFound";
+pub const UNSUPPORTED_REPORT_RATE: &str = "command.unsupportedReportRate";
+pub const COMMAND_NOT_CONFIGURED: &str = "command.notConfigured";
+pub const CONFIG_INVALID: &str = "config.invalid";
+pub const CONFIG_PATH_NOT_FOUND: &str = "config.pathNotFound";
+pub const HISTORY_UNAVAILABLE: &str = "history.unavailable";
+pub const SOAK_RUNNING: &str = "soak.alreadyRunning";
+pub const SOAK_NOT_RUNNING: &str = "soak.notRunning";
+pub const SIMON_RUNNING: &str = "simon.alreadyRunning";
+pub const SIMON_NO_PAIRS: &str = "simon.noPairs";
+pub const FEATURE_DISABLED: &str = "feature.disabled";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppError {
+    pub code: String,
+    pub params: Map<String, Value>,
+    pub message: String,
+}
+
+impl AppError {
+    pub fn new(code: &str, message: impl Into<String>) -> Self {
+        Self {
+            code: code.to_string(),
+            params: Map::new(),
+            message: message.into(),
+        }
+    }
+
+    pub fn param(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.params.insert(name.to_string(), value.into());
+        self
+    }
+}
+
+// 尚未分配代码的错误统一为 generic，原文作为参数
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::new(GENERIC, message.clone()).param("message", message)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)
+    }
+}
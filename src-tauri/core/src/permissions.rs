@@ -0,0 +1,110 @@
+// 串口权限检查：Linux 上普通用户通常需要加入 dialout（Debian/Ubuntu）或 uucp（Arch）组
+// 才能打开串口，这里给出具体缺少的组和处理方法，而不是只返回 "Permission denied"
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortPermission {
+    pub port: String,
+    pub exists: bool,
+    pub accessible: bool,  // 当前用户可以读写
+    pub group: Option<String>,  // 设备文件所属的组
+    pub in_group: Option<bool>,  // 当前用户是否已在该组中
+    pub hint: Option<String>,  // 无法访问时的处理方法
+}
+
+// 各系统上串口设备的典型名称，用于默认配置
+pub fn default_port() -> &'static str {
+    if cfg!(windows) {
+        "COM1"
+    } else if cfg!(target_os = "macos") {
+        "/dev/cu.usbserial"
+    } else {
+        "/dev/ttyUSB0"
+    }
+}
+
+#[cfg(unix)]
+pub fn check_port_permissions(port: &str) -> PortPermission {
+    use std::ffi::CString;
+    use std::os::unix::fs::MetadataExt;
+
+    let mut result = PortPermission {
+        port: port.to_string(),
+        exists: false,
+        accessible: false,
+        group: None,
+        in_group: None,
+        hint: None,
+    };
+    let Ok(metadata) = std::fs::metadata(port) else {
+        return result;
+    };
+    result.exists = true;
+    result.group = group_name(metadata.gid());
+    result.in_group = current_groups().map(|groups| groups.contains(&metadata.gid()));
+    // 只检查读写权限，不打开设备：打开串口会拉高 DTR 使 Arduino 等板子复位，
+    // 没有 O_NONBLOCK 时还可能因等待载波信号而阻塞
+    result.accessible = CString::new(port)
+        .is_ok_and(|path| unsafe { libc::access(path.as_ptr(), libc::R_OK | libc::W_OK) } == 0);
+    if !result.accessible {
+        result.hint = Some(permission_hint(port, result.group.as_deref()));
+    }
+    result
+}
+
+// Windows 上没有按组授权，能否打开取决于是否被其他程序占用
+#[cfg(not(unix))]
+pub fn check_port_permissions(port: &str) -> PortPermission {
+    let exists = serialport::available_ports()
+        .unwrap_or_default()
+        .iter()
+        .any(|p| p.port_name.eq_ignore_ascii_case(port));
+    PortPermission {
+        port: port.to_string(),
+        exists,
+        accessible: exists,
+        group: None,
+        in_group: None,
+        hint: None,
+    }
+}
+
+pub fn permission_hint(port: &str, group: Option<&str>) -> String {
+    if cfg!(target_os = "macos") {
+        return format!("无权访问 {}，请检查设备文件权限或驱动是否安装", port);
+    }
+    let group = group.unwrap_or(default_group());
+    format!(
+        "当前用户无权访问 {port}。执行 sudo usermod -aG {group} $USER 后重新登录；\
+         或添加 udev 规则 /etc/udev/rules.d/99-joystick.rules：\
+         KERNEL==\"ttyUSB[0-9]*\", MODE=\"0660\", GROUP=\"{group}\"，然后执行 sudo udevadm control --reload-rules && sudo udevadm trigger"
+    )
+}
+
+// 发行版通常使用的串口组
+fn default_group() -> &'static str {
+    let uses_uucp = std::fs::read_to_string("/etc/group")
+        .map(|groups| groups.lines().any(|line| line.starts_with("uucp:")) && !groups.lines().any(|line| line.starts_with("dialout:")))
+        .unwrap_or(false);
+    if uses_uucp { "uucp" } else { "dialout" }
+}
+
+#[cfg(unix)]
+fn group_name(gid: u32) -> Option<String> {
+    let groups = std::fs::read_to_string("/etc/group").ok()?;
+    groups.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        let id: u32 = fields.nth(1)?.parse().ok()?;
+        (id == gid).then(|| name.to_string())
+    })
+}
+
+// 当前进程的附加组，Linux 上从 /proc 读取，其他系统未知
+#[cfg(unix)]
+fn current_groups() -> Option<Vec<u32>> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("Groups:"))?;
+    Some(line["Groups:".len()..].split_whitespace().filter_map(|g| g.parse().ok()).collect())
+}
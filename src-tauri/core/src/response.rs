@@ -0,0 +1,129 @@
+// 设备应答解码：按配置中的布局在收到的数据里查找帧头，
+// 把应答中的字段解码为具名数值，供界面和监视使用，不必再看原始十六进制
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use crate::config::{CommandCodes, ProtocolConfig, ResponseLayout};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DecodedResponse {
+    pub name: String,
+    pub fields: BTreeMap<String, f64>,
+    pub raw: Vec<u8>,
+}
+
+// 十六进制帧头，如 "AA 21" 或 "0xAA 0x21"
+pub fn parse_header(header: &str) -> Result<Vec<u8>, String> {
+    let bytes = header
+        .split_whitespace()
+        .map(|token| {
+            u8::from_str_radix(token.trim_start_matches("0x"), 16)
+                .map_err(|_| format!("无效的字节: {}", token))
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    if bytes.is_empty() {
+        return Err("帧头为空".to_string());
+    }
+    Ok(bytes)
+}
+
+// 字段格式占用的字节数，格式无效时为 None
+pub fn field_width(format: &str) -> Option<usize> {
+    match format {
+        "u8" | "i8" => Some(1),
+        "u16le" | "u16be" | "i16le" | "i16be" => Some(2),
+        "u32le" | "u32be" | "i32le" | "i32be" => Some(4),
+        _ => None,
+    }
+}
+
+fn read_field(data: &[u8], format: &str) -> Option<f64> {
+    let value = match format {
+        "u8" => *data.first()? as f64,
+        "i8" => *data.first()? as i8 as f64,
+        "u16le" => u16::from_le_bytes(data.get(..2)?.try_into().ok()?) as f64,
+        "u16be" => u16::from_be_bytes(data.get(..2)?.try_into().ok()?) as f64,
+        "i16le" => i16::from_le_bytes(data.get(..2)?.try_into().ok()?) as f64,
+        "i16be" => i16::from_be_bytes(data.get(..2)?.try_into().ok()?) as f64,
+        "u32le" => u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as f64,
+        "u32be" => u32::from_be_bytes(data.get(..4)?.try_into().ok()?) as f64,
+        "i32le" => i32::from_le_bytes(data.get(..4)?.try_into().ok()?) as f64,
+        "i32be" => i32::from_be_bytes(data.get(..4)?.try_into().ok()?) as f64,
+        _ => return None,
+    };
+    Some(value)
+}
+
+// 应答解码器：配置变化时整体替换，帧头无效的布局被忽略（validate 会报告）
+#[derive(Debug, Clone, Default)]
+pub struct ResponseDecoder {
+    layouts: Vec<(Vec<u8>, ResponseLayout)>,
+}
+
+impl ResponseDecoder {
+    pub fn new(layouts: &[ResponseLayout]) -> Self {
+        Self {
+            layouts: layouts
+                .iter()
+                .filter_map(|layout| Some((parse_header(&layout.header).ok()?, layout.clone())))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layouts.is_empty()
+    }
+
+    // 在一次读取的数据中查找所有完整的应答
+    pub fn decode(&self, data: &[u8]) -> Vec<DecodedResponse> {
+        let mut responses = Vec::new();
+        let mut pos = 0;
+        'scan: while pos < data.len() {
+            for (header, layout) in &self.layouts {
+                let Some(raw) = data.get(pos..pos + layout.length) else {
+                    continue;
+                };
+                if !raw.starts_with(header) {
+                    continue;
+                }
+                let fields = layout
+                    .fields
+                    .iter()
+                    .filter_map(|field| {
+                        let value = read_field(raw.get(field.offset..)?, &field.format)?;
+                        Some((field.name.clone(), value * field.scale + field.bias))
+                    })
+                    .collect();
+                responses.push(DecodedResponse {
+                    name: layout.name.clone(),
+                    fields,
+                    raw: raw.to_vec(),
+                });
+                pos += layout.length;
+                continue 'scan;
+            }
+            pos += 1;
+        }
+        responses
+    }
+}
+
+// 设备信息应答中的能力字段填入协议配置：key_count、adc_count、led_count 为数量，
+// led_dimming 非 0 表示支持调光；应答中没有的字段保持原值
+pub fn apply_capabilities(response: &DecodedResponse, protocol: &ProtocolConfig) -> ProtocolConfig {
+    let count = |name: &str, current: usize| {
+        response.fields.get(name)
+            .filter(|value| value.is_finite() && **value >= 0.0)
+            .map_or(current, |&value| value as usize)
+    };
+    ProtocolConfig {
+        key_count: count("key_count", protocol.key_count),
+        adc_count: count("adc_count", protocol.adc_count),
+        led_count: count("led_count", protocol.led_count),
+        commands: CommandCodes {
+            led_dimming: response.fields.get("led_dimming").map_or(protocol.commands.led_dimming, |&value| value != 0.0),
+            ..protocol.commands.clone()
+        },
+        ..protocol.clone()
+    }
+}
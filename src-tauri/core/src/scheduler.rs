@@ -0,0 +1,168 @@
+// 定时任务：按 cron 表达式（分 时 日 月 周）在指定时间执行动作，
+// 动作与绑定共用同一套执行逻辑
+
+use chrono::{DateTime, Datelike, Local, Timelike};
+use std::time::Duration;
+
+// 单个字段允许的取值，按位记录
+#[derive(Debug, Clone)]
+struct CronField {
+    allowed: u64,
+    any: bool,  // 字段为 *
+}
+
+impl CronField {
+    // 支持 *、数字、列表（1,5）、范围（1-5）和步长（*/15、1-30/5）
+    fn parse(text: &str, min: u32, max: u32) -> Result<Self, String> {
+        let mut allowed = 0u64;
+        for part in text.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step.parse().map_err(|_| format!("无效的步长: {}", part))?;
+                    if step == 0 {
+                        return Err(format!("无效的步长: {}", part));
+                    }
+                    (range, step)
+                }
+                None => (part, 1),
+            };
+            let (start, end) = if range == "*" {
+                (min, max)
+            } else if let Some((start, end)) = range.split_once('-') {
+                let start = start.parse().map_err(|_| format!("无效的取值: {}", part))?;
+                let end = end.parse().map_err(|_| format!("无效的取值: {}", part))?;
+                (start, end)
+            } else {
+                let value = range.parse().map_err(|_| format!("无效的取值: {}", part))?;
+                // 单个数字带步长时表示从该值开始到最大值
+                (value, if step > 1 { max } else { value })
+            };
+            if start < min || end > max || start > end {
+                return Err(format!("取值超出范围 {}-{}: {}", min, max, part));
+            }
+            for value in (start..=end).step_by(step as usize) {
+                allowed |= 1 << value;
+            }
+        }
+        Ok(Self {
+            allowed,
+            any: text == "*",
+        })
+    }
+
+    fn contains(&self, value: u32) -> bool {
+        self.allowed & (1 << value) != 0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CronExpr {
+    minute: CronField,
+    hour: CronField,
+    day: CronField,
+    month: CronField,
+    weekday: CronField,
+}
+
+impl CronExpr {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("cron 表达式需要5个字段（分 时 日 月 周）: {}", text));
+        };
+        let mut weekday = CronField::parse(weekday, 0, 7)?;
+        // 周日可写作 0 或 7
+        if weekday.contains(7) {
+            weekday.allowed |= 1;
+        }
+        Ok(Self {
+            minute: CronField::parse(minute, 0, 59)?,
+            hour: CronField::parse(hour, 0, 23)?,
+            day: CronField::parse(day, 1, 31)?,
+            month: CronField::parse(month, 1, 12)?,
+            weekday,
+        })
+    }
+
+    pub fn matches(&self, time: &DateTime<Local>) -> bool {
+        // 与标准 cron 一致：日和周都有限制时，满足其一即可
+        let day = self.day.contains(time.day());
+        let weekday = self.weekday.contains(time.weekday().num_days_from_sunday());
+        let day_matches = match (self.day.any, self.weekday.any) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        };
+        self.minute.contains(time.minute())
+            && self.hour.contains(time.hour())
+            && self.month.contains(time.month())
+            && day_matches
+    }
+}
+
+// 距离下一个整分钟的时间
+pub fn until_next_minute() -> Duration {
+    let now = Local::now();
+    let elapsed_ms = now.second() as u64 * 1000 + now.timestamp_subsec_millis() as u64;
+    Duration::from_millis(60_000 - elapsed_ms.min(59_999))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        // 2026-10-17 是周六
+        Local.with_ymd_and_hms(2026, 10, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_lists_ranges_and_steps() {
+        let cron = CronExpr::parse("*/15 9-17 * * *").unwrap();
+        assert!(cron.matches(&at(17, 9, 0)));
+        assert!(cron.matches(&at(17, 17, 45)));
+        assert!(!cron.matches(&at(17, 9, 10)));
+        assert!(!cron.matches(&at(17, 18, 0)));
+
+        let cron = CronExpr::parse("5,10 1-11/5 * * *").unwrap();
+        assert!(cron.matches(&at(17, 6, 10)));
+        assert!(!cron.matches(&at(17, 2, 5)));
+
+        // 单个数字带步长表示从该值到最大值
+        let cron = CronExpr::parse("50/5 * * * *").unwrap();
+        assert!(cron.matches(&at(17, 0, 55)));
+        assert!(!cron.matches(&at(17, 0, 45)));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for text in ["* * * *", "60 * * * *", "* 24 * * *", "* * 0 * *", "* * * 13 *", "* * * * 8", "*/0 * * * *", "5-1 * * * *", "a * * * *"] {
+            assert!(CronExpr::parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn sunday_is_zero_or_seven() {
+        for text in ["0 8 * * 0", "0 8 * * 7"] {
+            let cron = CronExpr::parse(text).unwrap();
+            assert!(cron.matches(&at(18, 8, 0)), "{}", text);
+            assert!(!cron.matches(&at(17, 8, 0)), "{}", text);
+        }
+    }
+
+    #[test]
+    fn day_and_weekday_match_either_when_both_restricted() {
+        // 每月 1 日或周六
+        let cron = CronExpr::parse("0 0 1 * 6").unwrap();
+        assert!(cron.matches(&at(17, 0, 0)));
+        assert!(cron.matches(&Local.with_ymd_and_hms(2026, 11, 1, 0, 0, 0).unwrap()));
+        assert!(!cron.matches(&at(18, 0, 0)));
+
+        // 只限制日时不看星期
+        let cron = CronExpr::parse("0 0 18 * *").unwrap();
+        assert!(cron.matches(&at(18, 0, 0)));
+        assert!(!cron.matches(&at(17, 0, 0)));
+    }
+}
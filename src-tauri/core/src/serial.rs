@@ -0,0 +1,354 @@
+use serialport::{DataBits, Parity, SerialPort, StopBits};
+use tokio::sync::Mutex;
+use std::sync::Arc;
+use std::vec::Vec;
+use crate::config::{FlowControl, SerialConfig};
+use crate::messages::{self, AppError};
+use crate::permissions;
+use crate::transport::{self, LocalPort, MockSerial, Transport};
+
+// 串口被其他程序占用时可用的处理方式：无法关闭对方持有的句柄，只能等待释放后重新打开
+pub const BUSY_STRATEGY: &str = "retry";
+
+// 串口及其 USB 设备信息，非 USB 串口的设备字段为空
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct PortInfo {
+    pub name: String,  // 可用于打开的名称，与 list_ports 一致
+    pub label: String,  // 显示名称，如 "STM32 Matrix (COM7)"
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub serial_number: Option<String>,
+}
+
+pub struct SerialManager {
+    port: Arc<Mutex<Option<Box<dyn Transport>>>>,  // 写入端
+    reader: PortReader,
+    name: String,
+    listed: bool,  // 打开时是否在系统的串口列表中；不在列表中的虚拟串口无法检查是否被拔出
+}
+
+impl SerialManager {
+    // 端口名为 tcp:// 或 rfc2217:// 地址时连接远程串口服务器，mock:// 时打开模拟串口，否则打开本地串口
+    pub async fn new(config: SerialConfig) -> Result<Self, AppError> {
+        let (data_bits, stop_bits, parity) = line_settings(&config)?;
+        let name = normalize_port_name(&config.port);
+        if transport::is_mock(&name) {
+            let port = MockSerial::open(&name, &config).map_err(|e| AppError::from(format!("无法打开 {}: {}", name, e)))?;
+            return Self::with_transport(Box::new(port), name, false);
+        }
+        if transport::is_network(&name) {
+            let port = transport::connect(&name, &config)
+                .map_err(|e| AppError::from(format!("无法连接 {}: {}", name, e)))?;
+            return Self::with_transport(port, name, false);
+        }
+        let mut port = serialport::new(&name, config.baud_rate)
+            .data_bits(data_bits)
+            .stop_bits(stop_bits)
+            .parity(parity)
+            .flow_control(match config.flow_control {
+                FlowControl::None => serialport::FlowControl::None,
+                FlowControl::Hardware => serialport::FlowControl::Hardware,
+                FlowControl::Software => serialport::FlowControl::Software,
+            })
+            .timeout(std::time::Duration::from_millis(config.read_timeout_ms))
+            .open()
+            .map_err(|e| open_error(&config.port, e))?;
+        // 有些板子用 DTR/RTS 控制复位和进入 bootloader，打开后立即设为配置的电平
+        if let Some(level) = config.dtr {
+            port.write_data_terminal_ready(level).map_err(|e| open_error(&config.port, e))?;
+        }
+        if let Some(level) = config.rts {
+            port.write_request_to_send(level).map_err(|e| open_error(&config.port, e))?;
+        }
+        
+        let listed = port_exists(&name);
+        Self::with_transport(Box::new(LocalPort(port)), name, listed)
+    }
+    
+    fn with_transport(port: Box<dyn Transport>, name: String, listed: bool) -> Result<Self, AppError> {
+        let reader = port
+            .try_clone()
+            .map_err(|e| AppError::from(format!("无法打开 {} 的读取句柄: {}", name, e)))?;
+        Ok(Self {
+            port: Arc::new(Mutex::new(Some(port))),
+            reader: PortReader {
+                port: Arc::new(std::sync::Mutex::new(Some(reader))),
+            },
+            name,
+            listed,
+        })
+    }
+    
+    // 设备是否仍然存在；有些驱动在设备拔出后读取只是超时而不报错，需要据此判断连接丢失
+    pub fn is_present(&self) -> bool {
+        !self.listed || port_exists(&self.name)
+    }
+    
+    pub async fn send(&self, data: &[u8]) -> Result<usize, String> {
+        let mut port = self.port.lock().await;
+        if let Some(port) = port.as_mut() {
+            port.write(data).map_err(|e| e.to_string())
+        } else {
+            Err("Serial port not connected".to_string())
+        }
+    }
+    
+    pub async fn set_dtr(&self, level: bool) -> Result<(), String> {
+        let mut port = self.port.lock().await;
+        if let Some(port) = port.as_mut() {
+            port.set_dtr(level).map_err(|e| e.to_string())
+        } else {
+            Err("Serial port not connected".to_string())
+        }
+    }
+    
+    pub async fn set_rts(&self, level: bool) -> Result<(), String> {
+        let mut port = self.port.lock().await;
+        if let Some(port) = port.as_mut() {
+            port.set_rts(level).map_err(|e| e.to_string())
+        } else {
+            Err("Serial port not connected".to_string())
+        }
+    }
+    
+    // 读取端可以复制到其他任务中使用，读取时不需要持有 SerialManager
+    pub fn reader(&self) -> PortReader {
+        self.reader.clone()
+    }
+    
+    // 把用户输入的串口名称转换为可打开的端口名：
+    // 去掉 \\.\ 前缀（COM10 以上的写法，打开时会自动加上），
+    // 设备管理器中的友好名称如 "USB-SERIAL CH340 (COM12)" 取括号中的端口，
+    // 也可以只输入设备描述的一部分，如 "CH340"；网络地址和模拟串口原样返回
+    pub fn resolve_port(name: &str) -> Result<String, AppError> {
+        let name = normalize_port_name(name);
+        if transport::is_virtual(&name) {
+            return Ok(name);
+        }
+        let ports = serialport::available_ports().unwrap_or_default();
+        if let Some(port) = ports.iter().find(|p| p.port_name.eq_ignore_ascii_case(&name)) {
+            return Ok(port.port_name.clone());
+        }
+        
+        let needle = name.to_lowercase();
+        let matches: Vec<&serialport::SerialPortInfo> = ports.iter()
+            .filter(|p| port_description(p).is_some_and(|d| d.to_lowercase().contains(&needle)))
+            .collect();
+        match matches.as_slice() {
+            [port] => Ok(port.port_name.clone()),
+            // 找不到设备时仍按原名称打开，由打开操作报告具体错误
+            [] => Ok(name),
+            _ => Err(AppError::new(messages::SERIAL_PORT_AMBIGUOUS, format!("{} 对应多个串口", name))
+                .param("name", name)
+                .param("ports", matches.iter().map(|p| p.port_name.clone()).collect::<Vec<_>>())),
+        }
+    }
+    
+    // Linux 上优先返回 /dev/serial/by-id 下的路径，重启后 ttyUSB 编号变化也不影响已保存的配置
+    pub fn list_ports() -> Vec<String> {
+        Self::list_port_info().into_iter().map(|port| port.name).collect()
+    }
+    
+    // 与 list_ports 相同，附带 USB 的 VID/PID、厂商、产品名和序列号，供界面显示和自动选择设备
+    pub fn list_port_info() -> Vec<PortInfo> {
+        let mut ports: Vec<PortInfo> = Vec::new();
+        for port in serialport::available_ports().unwrap_or_default() {
+            let name = stable_port_name(&port.port_name);
+            if ports.iter().any(|p| p.name == name) {
+                continue;
+            }
+            let label = match &port.port_type {
+                serialport::SerialPortType::UsbPort(usb) => {
+                    let product = usb.product.as_deref().or(usb.manufacturer.as_deref());
+                    product.map_or_else(|| port.port_name.clone(), |p| format!("{} ({})", p, port.port_name))
+                }
+                _ => port.port_name.clone(),
+            };
+            let mut info = PortInfo {
+                name,
+                label,
+                vid: None,
+                pid: None,
+                manufacturer: None,
+                product: None,
+                serial_number: None,
+            };
+            if let serialport::SerialPortType::UsbPort(usb) = port.port_type {
+                info.vid = Some(usb.vid);
+                info.pid = Some(usb.pid);
+                info.manufacturer = usb.manufacturer;
+                info.product = usb.product;
+                info.serial_number = usb.serial_number;
+            }
+            ports.push(info);
+        }
+        ports
+    }
+    
+    // 取出已打开的串口句柄交给其他模块（如固件下载）继续使用，避免关闭后重新打开时
+    // Windows 上句柄释放延迟导致打开失败；网络连接没有串口句柄，返回 None
+    pub async fn take_port(&self) -> Option<Box<dyn SerialPort>> {
+        self.reader.close().await;
+        self.port.lock().await.take()?.into_serial_port()
+    }
+    
+    pub async fn close(&self) {
+        self.reader.close().await;
+        let mut port = self.port.lock().await;
+        *port = None;
+    }
+}
+
+// 串口的读取端。串口读取会阻塞到有数据或超时（最长为配置的读取超时），
+// 在阻塞线程中进行，不占用异步运行时的工作线程，也不妨碍写入端发送命令
+#[derive(Clone)]
+pub struct PortReader {
+    port: Arc<std::sync::Mutex<Option<Box<dyn Transport>>>>,
+}
+
+impl PortReader {
+    // 读取到 buffer 中并交还缓冲区；超时只表示这段时间内没有数据，返回 0
+    pub async fn read(&self, mut buffer: Vec<u8>) -> (Vec<u8>, Result<usize, String>) {
+        let port = Arc::clone(&self.port);
+        let task = tokio::task::spawn_blocking(move || {
+            let result = match port.lock().unwrap().as_mut() {
+                Some(port) => match port.read(&mut buffer) {
+                    Err(e) if e.kind() == std::io::ErrorKind::TimedOut => Ok(0),
+                    result => result.map_err(|e| e.to_string()),
+                },
+                None => Err("Serial port not connected".to_string()),
+            };
+            (buffer, result)
+        });
+        match task.await {
+            Ok(result) => result,
+            Err(e) => (Vec::new(), Err(e.to_string())),
+        }
+    }
+    
+    // 等待正在进行的读取结束后关闭读取句柄，之后串口才真正释放
+    async fn close(&self) {
+        let port = Arc::clone(&self.port);
+        let _ = tokio::task::spawn_blocking(move || port.lock().unwrap().take()).await;
+    }
+}
+
+// 把配置中的数据位、停止位和校验方式转换为 serialport 的设置
+fn line_settings(config: &SerialConfig) -> Result<(DataBits, StopBits, Parity), AppError> {
+    let invalid = |detail: String| {
+        AppError::new(messages::SERIAL_INVALID_SETTINGS, format!("不支持的串口参数: {}", detail))
+            .param("detail", detail)
+    };
+    config.validate().map_err(invalid)?;
+    let data_bits = match config.data_bits {
+        5 => DataBits::Five,
+        6 => DataBits::Six,
+        7 => DataBits::Seven,
+        _ => DataBits::Eight,
+    };
+    let stop_bits = if config.stop_bits == 2 { StopBits::Two } else { StopBits::One };
+    let parity = match config.parity.to_ascii_lowercase().as_str() {
+        "odd" => Parity::Odd,
+        "even" => Parity::Even,
+        _ => Parity::None,
+    };
+    Ok((data_bits, stop_bits, parity))
+}
+
+// Unix 上检查设备文件，Windows 上检查系统的串口列表
+fn port_exists(name: &str) -> bool {
+    if cfg!(unix) {
+        std::path::Path::new(name).exists()
+    } else {
+        serialport::available_ports()
+            .unwrap_or_default()
+            .iter()
+            .any(|p| p.port_name.eq_ignore_ascii_case(name))
+    }
+}
+
+// 打开失败的原因：Unix 上权限不足时给出需要加入的组和处理方法；
+// 设备存在却无法打开时视为被其他程序占用
+fn open_error(port: &str, e: serialport::Error) -> AppError {
+    let denied = matches!(e.kind(), serialport::ErrorKind::Io(std::io::ErrorKind::PermissionDenied));
+    if cfg!(unix) && denied {
+        let permission = permissions::check_port_permissions(port);
+        let hint = permissions::permission_hint(port, permission.group.as_deref());
+        let mut error = AppError::new(messages::SERIAL_PERMISSION_DENIED, hint.clone())
+            .param("port", port)
+            .param("hint", hint);
+        if let Some(group) = permission.group {
+            error = error.param("group", group);
+        }
+        return error;
+    }
+    if e.kind() == serialport::ErrorKind::NoDevice && permissions::check_port_permissions(port).exists {
+        let hint = busy_hint(port);
+        return AppError::new(messages::SERIAL_PORT_BUSY, hint.clone())
+            .param("port", port)
+            .param("strategy", BUSY_STRATEGY)
+            .param("hint", hint);
+    }
+    e.to_string().into()
+}
+
+fn busy_hint(port: &str) -> String {
+    if cfg!(windows) {
+        format!("{} 已被其他程序占用，请关闭串口调试助手、Arduino IDE 串口监视器等程序后重试", port)
+    } else {
+        format!("{} 已被其他程序占用，可执行 fuser -v {} 查看占用的进程，关闭后重试", port, port)
+    }
+}
+
+// 去掉 Windows 设备路径前缀，并从 "名称 (COM12)" 形式中取出端口名
+pub fn normalize_port_name(name: &str) -> String {
+    let name = name.trim();
+    let name = name.strip_prefix(r"\\.\")
+        .or_else(|| name.strip_prefix(r"\\?\"))
+        .unwrap_or(name);
+    if let Some(inner) = name.strip_suffix(')').and_then(|rest| rest.rsplit_once('(')).map(|(_, inner)| inner) {
+        if inner.to_ascii_uppercase().starts_with("COM") {
+            return inner.to_string();
+        }
+    }
+    name.to_string()
+}
+
+// USB 串口的设备描述（产品名、厂商）
+fn port_description(port: &serialport::SerialPortInfo) -> Option<String> {
+    match &port.port_type {
+        serialport::SerialPortType::UsbPort(usb) => {
+            let parts: Vec<&str> = [usb.product.as_deref(), usb.manufacturer.as_deref()]
+                .into_iter()
+                .flatten()
+                .collect();
+            (!parts.is_empty()).then(|| parts.join(" "))
+        }
+        _ => None,
+    }
+}
+
+// 按设备序列号命名的稳定路径，找不到时返回原名称
+#[cfg(target_os = "linux")]
+pub fn stable_port_name(port: &str) -> String {
+    const BY_ID_DIR: &str = "/dev/serial/by-id";
+    let Ok(target) = std::fs::canonicalize(port) else {
+        return port.to_string();
+    };
+    let Ok(entries) = std::fs::read_dir(BY_ID_DIR) else {
+        return port.to_string();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| std::fs::canonicalize(path).is_ok_and(|p| p == target))
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|| port.to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn stable_port_name(port: &str) -> String {
+    port.to_string()
+}
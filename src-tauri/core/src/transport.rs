@@ -0,0 +1,598 @@
+// 串口的传输方式：本地串口，或通过网络连接远程串口服务器（如树莓派上的 ser2net）。
+// 端口名为 tcp://host:port 时按原始 TCP 透传；rfc2217://host:port 时使用 RFC 2217
+// （Telnet COM-PORT-OPTION），连接后按配置设置远端串口的波特率、数据位、校验、停止位和流控。
+// mock:// 为模拟串口，不需要硬件即可调试解析器和界面
+
+use serialport::SerialPort;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use crate::config::{FlowControl, SerialConfig};
+
+pub const TCP_SCHEME: &str = "tcp://";
+pub const RFC2217_SCHEME: &str = "rfc2217://";
+pub const MOCK_SCHEME: &str = "mock://";
+pub const MOCK_LOOPBACK: &str = "mock://loopback";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Telnet 命令
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const BINARY: u8 = 0;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+const COM_PORT_OPTION: u8 = 44;
+
+// COM-PORT-OPTION 子命令（客户端发往服务器）
+const SET_BAUDRATE: u8 = 1;
+const SET_DATASIZE: u8 = 2;
+const SET_PARITY: u8 = 3;
+const SET_STOPSIZE: u8 = 4;
+const SET_CONTROL: u8 = 5;
+const CONTROL_NO_FLOW: u8 = 1;
+const CONTROL_XON_XOFF: u8 = 2;
+const CONTROL_HARDWARE: u8 = 3;
+const CONTROL_DTR_ON: u8 = 8;
+const CONTROL_DTR_OFF: u8 = 9;
+const CONTROL_RTS_ON: u8 = 11;
+const CONTROL_RTS_OFF: u8 = 12;
+
+// 读取超时返回 ErrorKind::TimedOut，连接断开返回错误
+pub trait Transport: Send {
+    // 复制一个指向同一连接的句柄，读取和写入分别使用，读取等待期间仍可发送
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>>;
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize>;
+    fn write(&mut self, data: &[u8]) -> io::Result<usize>;
+    fn set_dtr(&mut self, level: bool) -> io::Result<()>;
+    fn set_rts(&mut self, level: bool) -> io::Result<()>;
+    // 本地串口交出句柄供固件下载使用；其他传输方式不支持
+    fn into_serial_port(self: Box<Self>) -> Option<Box<dyn SerialPort>> {
+        None
+    }
+}
+
+pub fn is_network(name: &str) -> bool {
+    name.starts_with(TCP_SCHEME) || name.starts_with(RFC2217_SCHEME)
+}
+
+pub fn is_mock(name: &str) -> bool {
+    name.starts_with(MOCK_SCHEME)
+}
+
+// 不在系统串口列表中、按名称直接打开的端口
+pub fn is_virtual(name: &str) -> bool {
+    is_network(name) || is_mock(name)
+}
+
+pub struct LocalPort(pub Box<dyn SerialPort>);
+
+impl Transport for LocalPort {
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(LocalPort(self.0.try_clone()?)))
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buffer)
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.0.write(data)
+    }
+
+    fn set_dtr(&mut self, level: bool) -> io::Result<()> {
+        self.0.write_data_terminal_ready(level).map_err(io::Error::from)
+    }
+
+    fn set_rts(&mut self, level: bool) -> io::Result<()> {
+        self.0.write_request_to_send(level).map_err(io::Error::from)
+    }
+
+    fn into_serial_port(self: Box<Self>) -> Option<Box<dyn SerialPort>> {
+        Some(self.0)
+    }
+}
+
+// 连接 tcp:// 或 rfc2217:// 地址
+pub fn connect(name: &str, config: &SerialConfig) -> io::Result<Box<dyn Transport>> {
+    let (address, rfc2217) = match name.strip_prefix(RFC2217_SCHEME) {
+        Some(address) => (address, true),
+        None => (name.strip_prefix(TCP_SCHEME).unwrap_or(name), false),
+    };
+    let addr = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("无法解析地址 {}", address)))?;
+    let stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+    stream.set_read_timeout(Some(Duration::from_millis(config.read_timeout_ms)))?;
+    stream.set_nodelay(true)?;
+    if !rfc2217 {
+        return Ok(Box::new(TcpTransport { stream }));
+    }
+    let mut transport = Rfc2217Transport {
+        stream,
+        telnet: TelnetState::Data,
+        replies: Vec::new(),
+    };
+    transport.negotiate(config)?;
+    Ok(Box::new(transport))
+}
+
+// 原始 TCP：服务器把串口数据原样转发，无法控制 DTR/RTS
+pub struct TcpTransport {
+    stream: TcpStream,
+}
+
+// 对方关闭连接时 read 返回 0，转换为错误以便按读取失败处理
+fn read_stream(stream: &mut TcpStream, buffer: &mut [u8]) -> io::Result<usize> {
+    match stream.read(buffer) {
+        Ok(0) if !buffer.is_empty() => Err(io::Error::new(io::ErrorKind::ConnectionAborted, "远程串口服务器关闭了连接")),
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => Err(io::Error::new(io::ErrorKind::TimedOut, e)),
+        result => result,
+    }
+}
+
+impl Transport for TcpTransport {
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(TcpTransport { stream: self.stream.try_clone()? }))
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        read_stream(&mut self.stream, buffer)
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.stream.write_all(data)?;
+        Ok(data.len())
+    }
+
+    fn set_dtr(&mut self, _level: bool) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "TCP 连接无法控制 DTR，请使用 rfc2217://"))
+    }
+
+    fn set_rts(&mut self, _level: bool) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "TCP 连接无法控制 RTS，请使用 rfc2217://"))
+    }
+}
+
+// 收到的 Telnet 流的解析状态，跨读取保留
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TelnetState {
+    Data,
+    Iac,  // 收到 IAC
+    Option(u8),  // 收到 IAC WILL/WONT/DO/DONT，等待选项
+    Subnegotiation,  // 在 SB ... SE 之间
+    SubnegotiationIac,
+}
+
+pub struct Rfc2217Transport {
+    stream: TcpStream,
+    telnet: TelnetState,
+    replies: Vec<u8>,  // 需要回复服务器的协商
+}
+
+impl Rfc2217Transport {
+    fn negotiate(&mut self, config: &SerialConfig) -> io::Result<()> {
+        let mut request = vec![
+            IAC, WILL, COM_PORT_OPTION,
+            IAC, WILL, BINARY,
+            IAC, DO, BINARY,
+            IAC, DO, SUPPRESS_GO_AHEAD,
+        ];
+        request.extend(subnegotiation(SET_BAUDRATE, &config.baud_rate.to_be_bytes()));
+        request.extend(subnegotiation(SET_DATASIZE, &[config.data_bits]));
+        let parity = match config.parity.to_ascii_lowercase().as_str() {
+            "odd" => 2,
+            "even" => 3,
+            _ => 1,
+        };
+        request.extend(subnegotiation(SET_PARITY, &[parity]));
+        request.extend(subnegotiation(SET_STOPSIZE, &[config.stop_bits]));
+        let flow = match config.flow_control {
+            FlowControl::None => CONTROL_NO_FLOW,
+            FlowControl::Software => CONTROL_XON_XOFF,
+            FlowControl::Hardware => CONTROL_HARDWARE,
+        };
+        request.extend(subnegotiation(SET_CONTROL, &[flow]));
+        if let Some(level) = config.dtr {
+            request.extend(subnegotiation(SET_CONTROL, &[if level { CONTROL_DTR_ON } else { CONTROL_DTR_OFF }]));
+        }
+        if let Some(level) = config.rts {
+            request.extend(subnegotiation(SET_CONTROL, &[if level { CONTROL_RTS_ON } else { CONTROL_RTS_OFF }]));
+        }
+        self.stream.write_all(&request)
+    }
+
+    // 去掉 Telnet 命令，把数据移到缓冲区开头，返回数据长度；
+    // 拒绝服务器提出的其他选项
+    fn strip_telnet(&mut self, buffer: &mut [u8], len: usize) -> usize {
+        let mut out = 0;
+        for i in 0..len {
+            let byte = buffer[i];
+            self.telnet = match (self.telnet, byte) {
+                (TelnetState::Data, IAC) => TelnetState::Iac,
+                (TelnetState::Data, _) => {
+                    buffer[out] = byte;
+                    out += 1;
+                    TelnetState::Data
+                }
+                (TelnetState::Iac, IAC) => {
+                    buffer[out] = IAC;
+                    out += 1;
+                    TelnetState::Data
+                }
+                (TelnetState::Iac, WILL | WONT | DO | DONT) => TelnetState::Option(byte),
+                (TelnetState::Iac, SB) => TelnetState::Subnegotiation,
+                (TelnetState::Iac, _) => TelnetState::Data,
+                (TelnetState::Option(command), option) => {
+                    self.reply(command, option);
+                    TelnetState::Data
+                }
+                // 服务器对设置的确认和线路状态通知不需要处理
+                (TelnetState::Subnegotiation, IAC) => TelnetState::SubnegotiationIac,
+                (TelnetState::Subnegotiation, _) => TelnetState::Subnegotiation,
+                (TelnetState::SubnegotiationIac, SE) => TelnetState::Data,
+                (TelnetState::SubnegotiationIac, _) => TelnetState::Subnegotiation,
+            };
+        }
+        out
+    }
+
+    fn reply(&mut self, command: u8, option: u8) {
+        let supported = matches!(option, BINARY | SUPPRESS_GO_AHEAD | COM_PORT_OPTION);
+        match command {
+            DO if !supported => self.replies.extend([IAC, WONT, option]),
+            WILL if !supported => self.replies.extend([IAC, DONT, option]),
+            _ => {}
+        }
+    }
+
+    fn set_control(&mut self, value: u8) -> io::Result<()> {
+        self.stream.write_all(&subnegotiation(SET_CONTROL, &[value]))
+    }
+}
+
+// IAC SB COM-PORT-OPTION 子命令 参数 IAC SE，参数中的 0xFF 需要转义
+fn subnegotiation(command: u8, value: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![IAC, SB, COM_PORT_OPTION, command];
+    bytes.extend(escape_iac(value));
+    bytes.extend([IAC, SE]);
+    bytes
+}
+
+fn escape_iac(data: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(data.len());
+    for &byte in data {
+        escaped.push(byte);
+        if byte == IAC {
+            escaped.push(IAC);
+        }
+    }
+    escaped
+}
+
+// 协商状态只用于读取，复制的句柄从头开始解析
+impl Transport for Rfc2217Transport {
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(Rfc2217Transport {
+            stream: self.stream.try_clone()?,
+            telnet: TelnetState::Data,
+            replies: Vec::new(),
+        }))
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let len = read_stream(&mut self.stream, buffer)?;
+        let len = self.strip_telnet(buffer, len);
+        if !self.replies.is_empty() {
+            let replies = std::mem::take(&mut self.replies);
+            self.stream.write_all(&replies)?;
+        }
+        Ok(len)
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.stream.write_all(&escape_iac(data))?;
+        Ok(data.len())
+    }
+
+    fn set_dtr(&mut self, level: bool) -> io::Result<()> {
+        self.set_control(if level { CONTROL_DTR_ON } else { CONTROL_DTR_OFF })
+    }
+
+    fn set_rts(&mut self, level: bool) -> io::Result<()> {
+        self.set_control(if level { CONTROL_RTS_ON } else { CONTROL_RTS_OFF })
+    }
+}
+
+// 模拟串口脚本的一步
+#[derive(Debug, Clone, PartialEq)]
+enum MockStep {
+    Data(Vec<u8>),  // 一次读取返回的数据，可以是半帧、多帧或错误的字节
+    Wait(u64),  // 静默的毫秒数
+    Loop,  // 回到脚本开头
+}
+
+// 模拟串口：mock://loopback 把写入的数据原样读回；
+// mock://<脚本文件路径> 按脚本回放数据，写入的数据被丢弃。脚本每行一步：
+//   AA 01 00 ... BF   十六进制字节，作为一次读取的结果返回（超出读取缓冲区的部分留到下次）
+//   "text"            ASCII 文本，用于文本协议
+//   wait 20           静默 20 毫秒
+//   loop              回到开头重复回放，之前至少要有一个 wait
+//   # 注释
+// 一帧拆成多行即模拟分段到达，故意写错校验或帧尾即模拟错误帧；脚本结束后只返回读取超时
+pub struct MockSerial {
+    steps: Vec<MockStep>,
+    position: usize,
+    offset: usize,  // 当前数据步已返回的字节数
+    resume_at: Option<Instant>,  // 正在执行的 wait 结束的时间
+    echo: Option<Arc<Mutex<VecDeque<u8>>>>,  // 回环模式下待读回的数据，复制的句柄共用
+    timeout: Duration,
+}
+
+impl MockSerial {
+    pub fn open(name: &str, config: &SerialConfig) -> io::Result<Self> {
+        let timeout = Duration::from_millis(config.read_timeout_ms);
+        if name.eq_ignore_ascii_case(MOCK_LOOPBACK) {
+            return Ok(Self::from_steps(Vec::new(), Some(Arc::default()), timeout));
+        }
+        let path = name.strip_prefix(MOCK_SCHEME).unwrap_or(name);
+        let script = std::fs::read_to_string(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("无法读取模拟串口脚本 {}: {}", path, e)))?;
+        let steps = parse_mock_script(&script).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self::from_steps(steps, None, timeout))
+    }
+
+    fn from_steps(steps: Vec<MockStep>, echo: Option<Arc<Mutex<VecDeque<u8>>>>, timeout: Duration) -> Self {
+        Self {
+            steps,
+            position: 0,
+            offset: 0,
+            resume_at: None,
+            echo,
+            timeout,
+        }
+    }
+
+    // 没有数据可读时与真实串口一样等待读取超时
+    fn idle(&self, until: Option<Instant>) -> io::Result<usize> {
+        let wait = until.map_or(self.timeout, |until| until.saturating_duration_since(Instant::now()).min(self.timeout));
+        std::thread::sleep(wait);
+        Err(io::Error::new(io::ErrorKind::TimedOut, "模拟串口没有数据"))
+    }
+}
+
+fn parse_mock_script(script: &str) -> Result<Vec<MockStep>, String> {
+    let mut steps = Vec::new();
+    for (number, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |detail: String| format!("第 {} 行: {}", number + 1, detail);
+        if line.eq_ignore_ascii_case("loop") {
+            if !steps.iter().any(|step| matches!(step, MockStep::Wait(ms) if *ms > 0)) {
+                return Err(error("loop 之前至少需要一个大于 0 的 wait".to_string()));
+            }
+            steps.push(MockStep::Loop);
+        } else if let Some(ms) = line.strip_prefix("wait ") {
+            let ms = ms.trim().parse().map_err(|_| error(format!("无效的等待时间: {}", ms.trim())))?;
+            steps.push(MockStep::Wait(ms));
+        } else if let Some(text) = line.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+            steps.push(MockStep::Data(text.as_bytes().to_vec()));
+        } else {
+            let bytes = line
+                .split_whitespace()
+                .map(|token| {
+                    u8::from_str_radix(token.trim_start_matches("0x"), 16).map_err(|_| error(format!("无效的字节: {}", token)))
+                })
+                .collect::<Result<Vec<u8>, String>>()?;
+            steps.push(MockStep::Data(bytes));
+        }
+    }
+    Ok(steps)
+}
+
+impl Transport for MockSerial {
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        let mut clone = Self::from_steps(self.steps.clone(), self.echo.clone(), self.timeout);
+        clone.position = self.position;
+        clone.offset = self.offset;
+        Ok(Box::new(clone))
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if let Some(echo) = &self.echo {
+            let mut echo = echo.lock().unwrap();
+            if echo.is_empty() {
+                drop(echo);
+                return self.idle(None);
+            }
+            let len = buffer.len().min(echo.len());
+            for (slot, byte) in buffer.iter_mut().zip(echo.drain(..len)) {
+                *slot = byte;
+            }
+            return Ok(len);
+        }
+        loop {
+            let Some(step) = self.steps.get(self.position) else {
+                return self.idle(None);
+            };
+            match step {
+                MockStep::Data(bytes) => {
+                    let len = buffer.len().min(bytes.len() - self.offset);
+                    buffer[..len].copy_from_slice(&bytes[self.offset..self.offset + len]);
+                    self.offset += len;
+                    if self.offset == bytes.len() {
+                        self.position += 1;
+                        self.offset = 0;
+                    }
+                    if len > 0 {
+                        return Ok(len);
+                    }
+                }
+                MockStep::Wait(ms) => {
+                    let until = *self.resume_at.get_or_insert_with(|| Instant::now() + Duration::from_millis(*ms));
+                    if Instant::now() < until {
+                        return self.idle(Some(until));
+                    }
+                    self.resume_at = None;
+                    self.position += 1;
+                }
+                MockStep::Loop => self.position = 0,
+            }
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if let Some(echo) = &self.echo {
+            echo.lock().unwrap().extend(data);
+        }
+        Ok(data.len())
+    }
+
+    fn set_dtr(&mut self, _level: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_rts(&mut self, _level: bool) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use crate::config::MatrixConfig;
+    use crate::matrix::DataParser;
+    use crate::serial::SerialManager;
+
+    const VALID_FRAME: &str = "AA 47 00 00 03 80 80 80 80 00 00 00 80 00 00 00 00 00 00 00 00 00 6E BF";
+
+    #[test]
+    fn parses_mock_script_steps() {
+        let script = "# 注释\n\nAA 01\n0x02 BF\n\"OK\"\nwait 20\nloop\n";
+        let steps = parse_mock_script(script).unwrap();
+        assert_eq!(steps, [
+            MockStep::Data(vec![0xAA, 0x01]),
+            MockStep::Data(vec![0x02, 0xBF]),
+            MockStep::Data(b"OK".to_vec()),
+            MockStep::Wait(20),
+            MockStep::Loop,
+        ]);
+    }
+
+    #[test]
+    fn rejects_bad_mock_scripts() {
+        let error = parse_mock_script("AA 01\nAA XY\n").unwrap_err();
+        assert!(error.contains("第 2 行") && error.contains("XY"), "{}", error);
+        assert!(parse_mock_script("wait abc").is_err());
+        // 没有等待的循环会一直占用读取
+        assert!(parse_mock_script("AA\nloop").is_err());
+        assert!(parse_mock_script("AA\nwait 0\nloop").is_err());
+    }
+
+    #[test]
+    fn mock_reads_split_steps_across_small_buffers() {
+        let steps = parse_mock_script("01 02 03\n04").unwrap();
+        let mut mock = MockSerial::from_steps(steps, None, Duration::from_millis(1));
+        let mut buffer = [0u8; 2];
+        assert_eq!(mock.read(&mut buffer).unwrap(), 2);
+        assert_eq!(buffer, [0x01, 0x02]);
+        assert_eq!(mock.read(&mut buffer).unwrap(), 1);
+        assert_eq!(buffer[0], 0x03);
+        assert_eq!(mock.read(&mut buffer).unwrap(), 1);
+        assert_eq!(buffer[0], 0x04);
+        assert_eq!(mock.read(&mut buffer).unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn mock_script_frames_go_through_the_parser() {
+        // 错误的字节、校验错误的帧、分两次到达的帧，最后是一个完整的有效帧
+        let bad_checksum = VALID_FRAME.replace("6E BF", "6F BF");
+        let split = "AA 01 01 00 00 00 00 00 00 00 00 00\n00 00 00 00 00 00 00 00 00 00 AA BF";
+        let script = format!("12 34\n{}\n{}\n{}\n", bad_checksum, split, VALID_FRAME);
+        let path = std::env::temp_dir().join(format!("mock_script_{}.txt", std::process::id()));
+        std::fs::write(&path, script).unwrap();
+
+        let config = MatrixConfig::default();
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let (data, frame_errors) = runtime.block_on(async {
+            let serial = SerialManager::new(SerialConfig {
+                port: format!("{}{}", MOCK_SCHEME, path.display()),
+                read_timeout_ms: 10,
+                ..config.serial_matrix.clone()
+            }).await.unwrap();
+            let mut parser = DataParser::new(config);
+            parser.connect(serial).await;
+            let mut frame_errors = 0;
+            for _ in 0..10 {
+                let pending = parser.start_read().await.unwrap();
+                let read = pending.read().await;
+                let outcome = parser.finish_read(read).await.unwrap();
+                frame_errors += outcome.frame_errors.len();
+                if outcome.key_edges.is_some() {
+                    break;
+                }
+            }
+            (parser.get_parsed_data().await, frame_errors)
+        });
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(frame_errors, 1);
+        assert!(data.valid);
+        assert_eq!(data.index, 0x47);
+        assert!(data.keys[16] && data.keys[17]);
+    }
+
+    fn rfc2217_pair() -> (Rfc2217Transport, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let transport = Rfc2217Transport {
+            stream,
+            telnet: TelnetState::Data,
+            replies: Vec::new(),
+        };
+        (transport, server)
+    }
+
+    #[test]
+    fn escapes_iac_in_outgoing_data() {
+        assert_eq!(escape_iac(&[0x01, IAC, 0x02]), [0x01, IAC, IAC, 0x02]);
+        assert_eq!(subnegotiation(SET_BAUDRATE, &[IAC]), [IAC, SB, COM_PORT_OPTION, SET_BAUDRATE, IAC, IAC, IAC, SE]);
+    }
+
+    #[test]
+    fn strips_telnet_commands_from_incoming_data() {
+        let (mut transport, _server) = rfc2217_pair();
+        let mut buffer = [
+            0x01, IAC, IAC, 0x02,
+            IAC, SB, COM_PORT_OPTION, 101, IAC, IAC, IAC, SE,
+            IAC, DO, 24,
+            IAC, WILL, BINARY,
+            0x03,
+        ];
+        let len = buffer.len();
+        let len = transport.strip_telnet(&mut buffer, len);
+        assert_eq!(buffer[..len], [0x01, IAC, 0x02, 0x03]);
+        // 只拒绝不支持的选项
+        assert_eq!(transport.replies, [IAC, WONT, 24]);
+    }
+
+    #[test]
+    fn keeps_telnet_state_across_reads() {
+        let (mut transport, _server) = rfc2217_pair();
+        let mut first = [0x01, IAC];
+        assert_eq!(transport.strip_telnet(&mut first, 2), 1);
+        let mut second = [IAC, 0x02];
+        let len = transport.strip_telnet(&mut second, 2);
+        assert_eq!(second[..len], [IAC, 0x02]);
+    }
+}
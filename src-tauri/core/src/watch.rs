@@ -0,0 +1,362 @@
+// 监视表达式：对每帧数据求值，输出具名的布尔信号，便于排查接线问题
+// 语法示例：keys[3] && adc[1] > 128、!leds[0] || index == 0x10
+// 支持 keys[i] / adc[i] / leds[i] / index / valid、变量 $name（不存在时为 0）、
+// 数字（十进制或 0x 十六进制）、比较运算、+ -、! && || 和括号；布尔值按 0/1 参与运算
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use crate::config::WatchConfig;
+use crate::matrix::ParsedData;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Variable(String),
+    Op(&'static str),
+}
+
+const OPERATORS: [&str; 15] = [
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "+", "-", "(", ")", "[", "]",
+];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let c = rest.chars().next().unwrap_or_default();
+        let len = if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '.')
+                .unwrap_or(rest.len());
+            let literal = &rest[..len];
+            let value = match literal.strip_prefix("0x").or_else(|| literal.strip_prefix("0X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).map(|v| v as f64).ok(),
+                None => literal.parse().ok(),
+            };
+            tokens.push(Token::Number(value.ok_or(format!("无效的数字: {}", literal))?));
+            len
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..len].to_string()));
+            len
+        } else if let Some(name) = rest.strip_prefix('$') {
+            let len = name
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(name.len());
+            if len == 0 {
+                return Err("$ 后缺少变量名".to_string());
+            }
+            tokens.push(Token::Variable(name[..len].to_string()));
+            len + 1
+        } else {
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or(format!("无法识别的字符: {}", c))?;
+            tokens.push(Token::Op(op));
+            op.len()
+        };
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Keys,
+    Adc,
+    Leds,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Number(f64),
+    Index,
+    Valid,
+    Element(Field, usize),
+    Variable(String),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+// 递归下降解析，优先级从低到高：|| && 比较 + - 一元 基本项
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        if self.peek_op() == Some(op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn binary(&mut self, ops: &[&'static str], next: fn(&mut Self) -> Result<Expr, String>) -> Result<Expr, String> {
+        let mut left = next(self)?;
+        while let Some(op) = self.peek_op().filter(|op| ops.contains(op)) {
+            self.pos += 1;
+            let right = next(self)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        self.binary(&["||"], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        self.binary(&["&&"], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        self.binary(&["==", "!=", "<=", ">=", "<", ">"], Self::sum)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        self.binary(&["+", "-"], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self.tokens.get(self.pos).cloned().ok_or("表达式不完整")?;
+        self.pos += 1;
+        match token {
+            Token::Number(value) => Ok(Expr::Number(value)),
+            Token::Variable(name) => Ok(Expr::Variable(name)),
+            Token::Op("(") => {
+                let expr = self.or()?;
+                if !self.eat(")") {
+                    return Err("缺少 )".to_string());
+                }
+                Ok(expr)
+            }
+            Token::Ident(name) => {
+                let field = match name.as_str() {
+                    "index" => return Ok(Expr::Index),
+                    "valid" => return Ok(Expr::Valid),
+                    "keys" => Field::Keys,
+                    "adc" => Field::Adc,
+                    "leds" => Field::Leds,
+                    _ => return Err(format!("未知的名称: {}", name)),
+                };
+                let index = match (self.eat("["), self.tokens.get(self.pos).cloned()) {
+                    (true, Some(Token::Number(n))) if n >= 0.0 && n.fract() == 0.0 => n as usize,
+                    _ => return Err(format!("{} 需要下标，如 {}[0]", name, name)),
+                };
+                self.pos += 1;
+                if !self.eat("]") {
+                    return Err("缺少 ]".to_string());
+                }
+                Ok(Expr::Element(field, index))
+            }
+            Token::Op(op) => Err(format!("意外的符号: {}", op)),
+        }
+    }
+}
+
+fn flag(value: bool) -> f64 {
+    if value { 1.0 } else { 0.0 }
+}
+
+impl Expr {
+    fn eval(&self, data: &ParsedData, vars: &BTreeMap<String, i64>) -> f64 {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::Index => data.index as f64,
+            Expr::Valid => flag(data.valid),
+            // 下标超出协议数量时按 0 处理
+            Expr::Element(Field::Keys, i) => flag(data.keys.get(*i).copied().unwrap_or(false)),
+            Expr::Element(Field::Adc, i) => data.adc.get(*i).copied().unwrap_or(0) as f64,
+            Expr::Element(Field::Leds, i) => flag(data.leds.get(*i).copied().unwrap_or(false)),
+            Expr::Variable(name) => vars.get(name).copied().unwrap_or(0) as f64,
+            Expr::Not(inner) => flag(inner.eval(data, vars) == 0.0),
+            Expr::Neg(inner) => -inner.eval(data, vars),
+            Expr::Binary(op, left, right) => {
+                let left = left.eval(data, vars);
+                // 逻辑运算短路
+                match *op {
+                    "&&" => return flag(left != 0.0 && right.eval(data, vars) != 0.0),
+                    "||" => return flag(left != 0.0 || right.eval(data, vars) != 0.0),
+                    _ => {}
+                }
+                let right = right.eval(data, vars);
+                match *op {
+                    "==" => flag(left == right),
+                    "!=" => flag(left != right),
+                    "<=" => flag(left <= right),
+                    ">=" => flag(left >= right),
+                    "<" => flag(left < right),
+                    ">" => flag(left > right),
+                    "+" => left + right,
+                    _ => left - right,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WatchExpr {
+    expr: Expr,
+}
+
+impl WatchExpr {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            pos: 0,
+        };
+        let expr = parser.or()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(format!("表达式末尾有多余内容: {}", text));
+        }
+        Ok(Self { expr })
+    }
+
+    pub fn eval(&self, data: &ParsedData, vars: &BTreeMap<String, i64>) -> bool {
+        self.expr.eval(data, vars) != 0.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WatchSignal {
+    pub name: String,
+    pub value: bool,
+}
+
+// 当前配置的全部监视表达式，解析失败的表达式会被跳过
+pub struct WatchSet {
+    watches: Vec<(String, WatchExpr)>,
+    last: Vec<WatchSignal>,
+}
+
+impl WatchSet {
+    pub fn new(watches: &[WatchConfig]) -> Self {
+        let watches = watches
+            .iter()
+            .filter_map(|w| match WatchExpr::parse(&w.expr) {
+                Ok(expr) => Some((w.name.clone(), expr)),
+                Err(e) => {
+                    eprintln!("Invalid watch {}: {}", w.name, e);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            watches,
+            last: Vec::new(),
+        }
+    }
+
+    // 求值所有表达式，有信号变化时返回全部信号的当前值
+    pub fn evaluate(&mut self, data: &ParsedData, vars: &BTreeMap<String, i64>) -> Option<Vec<WatchSignal>> {
+        let signals: Vec<WatchSignal> = self
+            .watches
+            .iter()
+            .map(|(name, expr)| WatchSignal {
+                name: name.clone(),
+                value: expr.eval(data, vars),
+            })
+            .collect();
+        if signals == self.last {
+            return None;
+        }
+        self.last = signals.clone();
+        Some(signals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProtocolConfig;
+
+    fn frame() -> ParsedData {
+        let mut data = ParsedData::new(&ProtocolConfig::default());
+        data.index = 0x10;
+        data.valid = true;
+        data.keys[3] = true;
+        data.adc[1] = 200;
+        data.leds[0] = true;
+        data
+    }
+
+    fn eval(text: &str) -> bool {
+        let vars = BTreeMap::from([("count".to_string(), 3)]);
+        WatchExpr::parse(text).unwrap().eval(&frame(), &vars)
+    }
+
+    #[test]
+    fn evaluates_fields_and_operators() {
+        assert!(eval("keys[3] && adc[1] > 128"));
+        assert!(!eval("keys[2] || !leds[0]"));
+        assert!(eval("index == 0x10 && valid"));
+        assert!(eval("adc[1] - 100 == 100"));
+        assert!(eval("-adc[1] < 0"));
+        assert!(eval("keys[3] + keys[2] == 1"));
+    }
+
+    #[test]
+    fn respects_precedence_and_parentheses() {
+        // && 优先于 ||
+        assert!(eval("keys[3] || keys[2] && keys[1]"));
+        assert!(!eval("(keys[3] || keys[2]) && keys[1]"));
+        assert!(eval("!(adc[1] < 100)"));
+    }
+
+    #[test]
+    fn missing_values_are_zero() {
+        assert!(eval("$count == 3"));
+        assert!(eval("$missing == 0"));
+        // 下标超出协议数量
+        assert!(eval("adc[99] == 0 && !keys[99]"));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for text in ["", "keys", "keys[1", "adc[1.5]", "foo", "keys[1] &&", "(1", "1 )", "$", "1 # 2", "0xZZ"] {
+            assert!(WatchExpr::parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn watch_set_reports_only_changes() {
+        let watches = [
+            WatchConfig { name: "pressed".to_string(), expr: "keys[3]".to_string() },
+            WatchConfig { name: "broken".to_string(), expr: "keys[".to_string() },
+        ];
+        let mut set = WatchSet::new(&watches);
+        let vars = BTreeMap::new();
+        let mut data = frame();
+        let signals = set.evaluate(&data, &vars).unwrap();
+        assert_eq!(signals, vec![WatchSignal { name: "pressed".to_string(), value: true }]);
+        assert_eq!(set.evaluate(&data, &vars), None);
+        data.keys[3] = false;
+        assert!(!set.evaluate(&data, &vars).unwrap()[0].value);
+    }
+}
@@ -0,0 +1,63 @@
+// 动作执行日志：记录每个执行的动作（来源、动作及参数、耗时、结果），只保留最近的记录；
+// 失败时发布事件。输出线程和引擎都会写入，使用同步锁
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use crate::config::ActionConfig;
+use crate::events::{AppEvent, EventBus};
+
+const MAX_RECORDS: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActionRecord {
+    pub timestamp: u64,  // 毫秒时间戳
+    pub source: String,  // 触发的绑定，如 "key 3"、"schedule 下班"
+    pub action: ActionConfig,  // 动作类型和参数
+    pub duration_us: u64,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct ActionLog {
+    records: Arc<Mutex<VecDeque<ActionRecord>>>,
+    bus: EventBus,
+}
+
+impl ActionLog {
+    pub fn new(bus: &EventBus) -> Self {
+        Self {
+            records: Arc::new(Mutex::new(VecDeque::new())),
+            bus: bus.clone(),
+        }
+    }
+
+    pub fn record(&self, source: &str, action: &ActionConfig, started: Instant, result: Result<(), String>) {
+        let record = ActionRecord {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            source: source.to_string(),
+            action: action.clone(),
+            duration_us: started.elapsed().as_micros() as u64,
+            success: result.is_ok(),
+            error: result.err(),
+        };
+        if !record.success {
+            self.bus.publish(AppEvent::ActionFailed(record.clone()));
+        }
+        let mut records = self.records.lock().unwrap();
+        if records.len() >= MAX_RECORDS {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    // 最近的记录，新的在前
+    pub fn recent(&self, limit: usize) -> Vec<ActionRecord> {
+        self.records.lock().unwrap().iter().rev().take(limit).cloned().collect()
+    }
+}
@@ -0,0 +1,62 @@
+// 多设备聚合：记录参与聚合的设备最近的有效帧，与主设备的数据按配置顺序拼接为一个逻辑设备的帧。
+// 尚未连接或已断开的设备按全部松开、ADC 为 0 填充
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::config::AggregateConfig;
+use crate::matrix::ParsedData;
+
+#[derive(Default)]
+pub struct Aggregator {
+    config: AggregateConfig,
+    latest: HashMap<String, Arc<ParsedData>>,
+}
+
+impl Aggregator {
+    pub fn new(config: &AggregateConfig) -> Self {
+        Self {
+            config: config.clone(),
+            latest: HashMap::new(),
+        }
+    }
+
+    pub fn update_config(&mut self, config: &AggregateConfig) {
+        self.config = config.clone();
+        self.latest.retain(|id, _| config.devices.contains(id));
+    }
+
+    // 记录设备的新帧，返回 false 表示该设备不参与聚合
+    pub fn update(&mut self, id: &str, data: &Arc<ParsedData>) -> bool {
+        if !self.config.enabled || !self.config.devices.iter().any(|device| device == id) {
+            return false;
+        }
+        self.latest.insert(id.to_string(), Arc::clone(data));
+        true
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.latest.remove(id);
+    }
+
+    // 主设备的数据之后依次拼接各设备的按键和 ADC；未启用聚合时为 None。
+    // LED、原始数据和自定义字段取自主设备
+    pub fn merge(&self, primary: &ParsedData) -> Option<ParsedData> {
+        if !self.config.enabled {
+            return None;
+        }
+        let mut merged = primary.clone();
+        for id in &self.config.devices {
+            match self.latest.get(id) {
+                Some(data) => {
+                    merged.keys.extend_from_slice(&data.keys);
+                    merged.adc.extend_from_slice(&data.adc);
+                }
+                None => {
+                    merged.keys.extend(std::iter::repeat_n(false, primary.keys.len()));
+                    merged.adc.extend(std::iter::repeat_n(0, primary.adc.len()));
+                }
+            }
+        }
+        Some(merged)
+    }
+}
@@ -0,0 +1,79 @@
+// 命令行参数：快捷方式和脚本可以直接以指定的串口、配置方案启动并连接，
+// 例如 --port COM5 --baud 115200 --profile Racing --connect --minimized；
+// 指定串口或波特率时立即连接，连接成功后才保存到配置。
+// 第二个实例启动时的参数按同样方式处理，serialjoy:// 链接中的操作需前端确认。
+// 不带 -- 的参数视为要打开的文件（如双击固件时传入的路径），相对路径按启动目录解析
+
+use std::path::Path;
+use crate::deeplink::LinkRequest;
+use crate::profile::PROFILE_EXTENSION;
+
+const FIRMWARE_EXTENSIONS: [&str; 2] = ["bin", "hex"];
+
+#[derive(Debug, Clone, Default)]
+pub struct LaunchArgs {
+    pub port: Option<String>,
+    pub baud: Option<u32>,
+    pub profile: Option<String>,  // 配置方案名称
+    pub connect: bool,  // 启动后立即连接
+    pub minimized: bool,  // 启动时只显示托盘图标
+    pub firmware: Option<String>,  // 要下载的固件文件，需前端确认
+    pub profile_file: Option<String>,  // 要导入的配置方案文件，需前端确认
+    pub link: Option<LinkRequest>,  // 链接请求的切换方案或连接，需前端确认
+}
+
+impl LaunchArgs {
+    pub fn from_link(link: &str) -> Result<Self, String> {
+        let mut args = Self::default();
+        crate::deeplink::apply(link, &mut args)?;
+        Ok(args)
+    }
+
+    // 解析参数（不含程序名），支持 "--port COM5" 和 "--port=COM5" 两种写法，无法识别的参数忽略；
+    // cwd 为启动该进程时的工作目录
+    pub fn parse<I: IntoIterator<Item = String>>(args: I, cwd: &Path) -> Self {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if crate::deeplink::is_link(&arg) {
+                if let Err(e) = crate::deeplink::apply(&arg, &mut parsed) {
+                    eprintln!("Ignoring link: {}", e);
+                }
+                continue;
+            }
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+            let mut value = || inline.clone().or_else(|| args.next());
+            match flag.as_str() {
+                "--port" => parsed.port = value(),
+                "--baud" => match value().and_then(|v| v.parse().ok()) {
+                    Some(baud) => parsed.baud = Some(baud),
+                    None => eprintln!("Ignoring invalid --baud value"),
+                },
+                "--profile" => parsed.profile = value(),
+                "--connect" => parsed.connect = true,
+                "--minimized" => parsed.minimized = true,
+                _ if !arg.starts_with('-') => parsed.open_file(&cwd.join(&arg)),
+                _ => eprintln!("Ignoring unknown argument: {}", arg),
+            }
+        }
+        parsed
+    }
+
+    // 按扩展名决定如何处理打开的文件
+    pub fn open_file(&mut self, path: &Path) {
+        let extension = path.extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .unwrap_or_default();
+        if FIRMWARE_EXTENSIONS.contains(&extension.as_str()) {
+            self.firmware = Some(path.to_string_lossy().into_owned());
+        } else if extension == PROFILE_EXTENSION {
+            self.profile_file = Some(path.to_string_lossy().into_owned());
+        } else {
+            eprintln!("Ignoring unsupported file: {}", path.display());
+        }
+    }
+}
@@ -0,0 +1,144 @@
+// 连接记录：每次连接的起止时间、断开原因和期间的错误次数写入本地文件，
+// 不依赖 history 功能，用于把现场偶发的断线与时间、机器对应起来
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::config::MatrixConfig;
+use crate::events::{AppEvent, DisconnectReason, ReconnectState};
+
+const LOG_FILE: &str = "connections.jsonl";
+const MAX_RECORDS: usize = 500;  // 文件中保留的最近记录数
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionRecord {
+    pub port: String,
+    pub machine: String,  // 计算机名
+    pub connected_at: u64,  // 毫秒时间戳
+    pub disconnected_at: Option<u64>,  // 仍在连接时为 None
+    pub duration_ms: u64,
+    pub reason: Option<DisconnectReason>,  // 原因未知（如应用退出）时为 None
+    pub frame_errors: u64,
+    pub reconnect_attempts: u32,  // 本次连接建立前的自动重连次数
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn machine_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_default()
+}
+
+pub struct ConnectionLog {
+    records: VecDeque<ConnectionRecord>,
+    current: Option<ConnectionRecord>,
+    reconnect_attempts: u32,
+    machine: String,
+}
+
+impl ConnectionLog {
+    // 读取已有记录；文件不存在或某行损坏时跳过
+    pub fn load() -> Self {
+        let mut records: VecDeque<ConnectionRecord> = fs::read_to_string(MatrixConfig::data_file_path(LOG_FILE))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        while records.len() > MAX_RECORDS {
+            records.pop_front();
+        }
+        Self {
+            records,
+            current: None,
+            reconnect_attempts: 0,
+            machine: machine_name(),
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::Connection { connected: true, port, .. } => {
+                // 没有收到断开事件就再次连接时，上一条按原因未知结束
+                self.finish(None);
+                self.current = Some(ConnectionRecord {
+                    port: port.clone(),
+                    machine: self.machine.clone(),
+                    connected_at: now_ms(),
+                    disconnected_at: None,
+                    duration_ms: 0,
+                    reason: None,
+                    frame_errors: 0,
+                    reconnect_attempts: std::mem::take(&mut self.reconnect_attempts),
+                });
+            }
+            AppEvent::Connection { connected: false, reason, .. } => self.finish(*reason),
+            AppEvent::FrameError(_) => {
+                if let Some(current) = self.current.as_mut() {
+                    current.frame_errors += 1;
+                }
+            }
+            AppEvent::Reconnect(ReconnectState::Waiting { .. }) => self.reconnect_attempts += 1,
+            AppEvent::Reconnect(ReconnectState::GaveUp { .. }) => self.reconnect_attempts = 0,
+            _ => {}
+        }
+    }
+
+    fn finish(&mut self, reason: Option<DisconnectReason>) {
+        let Some(mut record) = self.current.take() else {
+            return;
+        };
+        let now = now_ms();
+        record.disconnected_at = Some(now);
+        record.duration_ms = now.saturating_sub(record.connected_at);
+        record.reason = reason;
+        if let Err(e) = self.append(&record) {
+            eprintln!("Failed to write connection log: {}", e);
+        }
+        self.records.push_back(record);
+        if self.records.len() > MAX_RECORDS {
+            self.records.pop_front();
+            if let Err(e) = self.rewrite() {
+                eprintln!("Failed to write connection log: {}", e);
+            }
+        }
+    }
+
+    fn append(&self, record: &ConnectionRecord) -> std::io::Result<()> {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(MatrixConfig::data_file_path(LOG_FILE))?;
+        writeln!(file, "{}", serde_json::to_string(record)?)
+    }
+
+    // 超过保留数量后整体重写，丢弃最早的记录
+    fn rewrite(&self) -> std::io::Result<()> {
+        let mut content = String::new();
+        for record in &self.records {
+            content.push_str(&serde_json::to_string(record)?);
+            content.push('\n');
+        }
+        fs::write(MatrixConfig::data_file_path(LOG_FILE), content)
+    }
+
+    // 最近的记录，新的在前；正在进行的连接排在最前
+    pub fn recent(&self, limit: usize) -> Vec<ConnectionRecord> {
+        let current = self.current.clone().map(|mut record| {
+            record.duration_ms = now_ms().saturating_sub(record.connected_at);
+            record
+        });
+        current
+            .into_iter()
+            .chain(self.records.iter().rev().cloned())
+            .take(limit)
+            .collect()
+    }
+}
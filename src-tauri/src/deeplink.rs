@@ -0,0 +1,86 @@
+// serialjoy:// 链接，与命令行参数走同一套处理：
+//   serialjoy://profile/Racing                  切换配置方案
+//   serialjoy://connect?port=COM5&baud=115200   修改串口设置并连接
+//   serialjoy://flash?file=C:/fw/v2.bin         打开固件，由前端确认后下载
+// 链接可能来自任意网页，所有操作都要由前端确认后执行；连接链接只接受本机串口
+
+use serde::Serialize;
+use tauri::Url;
+use crate::cli::LaunchArgs;
+use crate::transport;
+
+pub const SCHEME: &str = "serialjoy";
+
+// 等待前端确认的链接操作，确认前不修改配置
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LinkRequest {
+    Profile { name: String },
+    Connect { port: Option<String>, baud: Option<u32> },
+}
+
+pub fn is_link(arg: &str) -> bool {
+    arg.len() > SCHEME.len() && arg[..SCHEME.len()].eq_ignore_ascii_case(SCHEME) && arg[SCHEME.len()..].starts_with(':')
+}
+
+// 把链接的内容合并到启动参数中
+pub fn apply(link: &str, args: &mut LaunchArgs) -> Result<(), String> {
+    let url = Url::parse(link).map_err(|e| format!("无效的链接 {}: {}", link, e))?;
+    let query = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    match url.host_str().unwrap_or_default() {
+        "profile" => {
+            let name = url.path().trim_start_matches('/');
+            if name.is_empty() {
+                return Err(format!("链接缺少配置方案名称: {}", link));
+            }
+            args.link = Some(LinkRequest::Profile { name: decode(name) });
+        }
+        "connect" => {
+            let port = query("port");
+            if let Some(port) = &port {
+                // 网络地址和模拟串口的脚本文件不允许由链接指定
+                if transport::is_virtual(&port.to_ascii_lowercase()) {
+                    return Err(format!("链接只能连接本机串口: {}", port));
+                }
+            }
+            let baud = match query("baud") {
+                Some(baud) => Some(baud.parse().map_err(|_| format!("无效的波特率: {}", baud))?),
+                None => None,
+            };
+            args.link = Some(LinkRequest::Connect { port, baud });
+        }
+        "flash" => {
+            args.firmware = Some(query("file").ok_or_else(|| format!("链接缺少固件文件: {}", link))?);
+        }
+        other => return Err(format!("不支持的链接类型: {}", other)),
+    }
+    Ok(())
+}
+
+// 解码路径中的 %XX
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,262 @@
+// 主设备之外同时连接的矩阵设备：与主设备使用相同的协议，各自在后台读取并发布数据帧，
+// 按配置中该设备 id 的绑定产生输出；绑定触发的 LED、PWM 和设备命令发往该设备
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::{watch, Mutex};
+use tauri::Manager;
+use crate::command::report_rate_command;
+use crate::config::{MatrixConfig, SerialConfig};
+use crate::events::{AppEvent, DisconnectReason, EventBus};
+use crate::matrix::{ConnectionState, DataParser, ParsedData};
+use crate::messages::{self as msg, AppError};
+use crate::output::OutputEngine;
+use crate::serial::SerialManager;
+use crate::AppState;
+
+const READ_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceInfo {
+    pub id: String,
+    pub port: String,
+    pub baud_rate: u32,
+    pub connected: bool,
+}
+
+#[derive(Clone)]
+struct MatrixDevice {
+    port: String,
+    baud_rate: u32,
+    parser: Arc<Mutex<DataParser>>,
+    output: Arc<Mutex<OutputEngine>>,
+}
+
+// 按 id 找到的设备，主设备的 id 为 None；锁定解析器后再锁定输出引擎
+#[derive(Clone)]
+pub struct DeviceHandle {
+    pub id: Option<String>,
+    pub port: String,
+    pub parser: Arc<Mutex<DataParser>>,
+    pub output: Arc<Mutex<OutputEngine>>,
+}
+
+// 设备表只在取出或替换设备时短暂加锁，不跨 await 持有。读取任务持有设备的解析器时
+// 会处理绑定产生的请求（如暂停时遍历所有设备），因此锁定任何设备的解析器之前都要先释放设备表
+#[derive(Default)]
+pub struct DeviceSet {
+    devices: std::sync::Mutex<HashMap<String, MatrixDevice>>,
+}
+
+// 设备使用主设备的协议和串口参数，只替换端口和波特率
+fn device_config(config: &MatrixConfig, port: &str, baud_rate: u32) -> MatrixConfig {
+    MatrixConfig {
+        serial_matrix: SerialConfig {
+            port: port.to_string(),
+            baud_rate,
+            ..config.serial_matrix.clone()
+        },
+        ..config.clone()
+    }
+}
+
+fn not_found(id: &str) -> AppError {
+    AppError::new(msg::SERIAL_DEVICE_NOT_FOUND, format!("设备 {} 不存在", id)).param("id", id)
+}
+
+impl DeviceSet {
+    fn get(&self, id: &str) -> Result<MatrixDevice, AppError> {
+        self.devices.lock().unwrap().get(id).cloned().ok_or_else(|| not_found(id))
+    }
+
+    fn snapshot(&self) -> Vec<(String, MatrixDevice)> {
+        self.devices.lock().unwrap().iter().map(|(id, device)| (id.clone(), device.clone())).collect()
+    }
+
+    // 打开设备的串口并开始读取，恢复该串口上次设置的上报频率；同一 id 已连接时先断开旧的连接
+    pub async fn connect(
+        &self,
+        app: &tauri::AppHandle,
+        id: String,
+        port: String,
+        baud_rate: u32,
+        config: &MatrixConfig,
+        output: OutputEngine,
+    ) -> Result<(), AppError> {
+        let bus = app.state::<AppState>().bus.clone();
+        self.disconnect(&id, &bus).await.ok();
+        let device_config = device_config(config, &port, baud_rate);
+        let serial = SerialManager::new(SerialConfig {
+            port: SerialManager::resolve_port(&port)?,
+            ..device_config.serial_matrix.clone()
+        }).await?;
+
+        let mut parser = DataParser::new(device_config);
+        parser.connect(serial).await;
+        let report_rate = config.report_rates.get(&port)
+            .filter(|_| config.protocol.commands.report_interval.is_some());
+        if let Some(&hz) = report_rate {
+            let result = match report_rate_command(hz, parser.protocol()) {
+                Ok(command) => parser.send_command(&command).await.map(|_| ()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                eprintln!("Failed to apply report rate {} Hz to device {}: {}", hz, id, e);
+            }
+        }
+        let connection = parser.subscribe_connection();
+        let parser = Arc::new(Mutex::new(parser));
+        let output = Arc::new(Mutex::new(output));
+        spawn_reader(app.clone(), id.clone(), port.clone(), Arc::downgrade(&parser), Arc::clone(&output), connection);
+        bus.publish(AppEvent::DeviceConnection { device: id.clone(), connected: true, port: port.clone(), reason: None });
+        self.devices.lock().unwrap().insert(id, MatrixDevice { port, baud_rate, parser, output });
+        Ok(())
+    }
+
+    pub fn handle(&self, id: &str) -> Result<DeviceHandle, AppError> {
+        let device = self.get(id)?;
+        Ok(DeviceHandle {
+            id: Some(id.to_string()),
+            port: device.port,
+            parser: device.parser,
+            output: device.output,
+        })
+    }
+
+    // 暂停和演练模式对所有设备生效
+    pub async fn set_paused(&self, paused: bool) {
+        for (_, device) in self.snapshot() {
+            device.output.lock().await.set_paused(paused);
+        }
+    }
+
+    pub async fn set_rehearsal(&self, enabled: bool) {
+        for (_, device) in self.snapshot() {
+            device.output.lock().await.set_rehearsal(enabled);
+        }
+    }
+
+    // 断开并移除设备，后台读取任务随之结束
+    pub async fn disconnect(&self, id: &str, bus: &EventBus) -> Result<(), AppError> {
+        let device = self.devices.lock().unwrap().remove(id).ok_or_else(|| not_found(id))?;
+        device.parser.lock().await.disconnect().await;
+        bus.publish(AppEvent::DeviceConnection {
+            device: id.to_string(),
+            connected: false,
+            port: device.port,
+            reason: Some(DisconnectReason::User),
+        });
+        Ok(())
+    }
+
+    pub async fn parsed_data(&self, id: &str) -> Result<Arc<ParsedData>, AppError> {
+        let device = self.get(id)?;
+        let data = device.parser.lock().await.get_parsed_data().await;
+        Ok(data)
+    }
+
+    pub async fn is_connected(&self, id: &str) -> Result<bool, AppError> {
+        let device = self.get(id)?;
+        let connected = device.parser.lock().await.is_connected().await;
+        Ok(connected)
+    }
+
+    pub async fn list(&self) -> Vec<DeviceInfo> {
+        let mut devices = Vec::new();
+        for (id, device) in self.snapshot() {
+            devices.push(DeviceInfo {
+                id,
+                port: device.port.clone(),
+                baud_rate: device.baud_rate,
+                connected: device.parser.lock().await.is_connected().await,
+            });
+        }
+        devices.sort_by(|a, b| a.id.cmp(&b.id));
+        devices
+    }
+
+    // 配置保存后同步给各设备的解析器，保留各自的端口和波特率
+    pub async fn update_config(&self, config: &MatrixConfig) {
+        for (_, device) in self.snapshot() {
+            let device_config = device_config(config, &device.port, device.baud_rate);
+            device.parser.lock().await.update_config(device_config).await;
+            device.output.lock().await.update_config(config);
+        }
+    }
+}
+
+// 连接期间持续读取，新的有效帧经过该设备的绑定处理后以设备 id 发布；
+// 设备被移除后解析器释放，任务结束。连接丢失时不自动重连，需要重新连接
+fn spawn_reader(
+    app: tauri::AppHandle,
+    id: String,
+    port: String,
+    parser: Weak<Mutex<DataParser>>,
+    output: Arc<Mutex<OutputEngine>>,
+    mut connection: watch::Receiver<ConnectionState>,
+) {
+    tauri::async_runtime::spawn(async move {
+        let bus = app.state::<AppState>().bus.clone();
+        loop {
+            if *connection.borrow_and_update() == ConnectionState::Disconnected {
+                if connection.changed().await.is_err() {
+                    return;
+                }
+                continue;
+            }
+            let Some(parser) = parser.upgrade() else {
+                return;
+            };
+            let pending = parser.lock().await.start_read().await;
+            let result = match pending {
+                Ok(pending) => {
+                    let read = pending.read().await;
+                    let mut parser = parser.lock().await;
+                    match parser.finish_read(read).await {
+                        Ok(outcome) if outcome.key_edges.is_some() => {
+                            let data = parser.get_parsed_data().await;
+                            // 参与聚合的设备只记录数据，释放解析器后按主设备的绑定处理
+                            let aggregated = app.state::<AppState>().aggregator.lock().unwrap().update(&id, &data);
+                            if !aggregated {
+                                crate::process_device_frame(&app, &id, &output, &parser, &data).await;
+                            }
+                            Ok(Some((data, aggregated)))
+                        }
+                        Ok(_) => Ok(None),
+                        Err(e) => Err(e),
+                    }
+                }
+                Err(e) => Err(e),
+            };
+            drop(parser);
+            match result {
+                Ok(Some((data, aggregated))) => {
+                    bus.publish(AppEvent::DeviceFrame { device: id.clone(), data });
+                    if aggregated {
+                        crate::process_aggregate_frame(&app).await;
+                    }
+                    tokio::task::yield_now().await;
+                }
+                Ok(None) => tokio::task::yield_now().await,
+                Err(e) if e.code == msg::SERIAL_CONNECTION_LOST => {
+                    eprintln!("Device {} lost: {}", id, e);
+                    app.state::<AppState>().aggregator.lock().unwrap().remove(&id);
+                    bus.publish(AppEvent::DeviceConnection {
+                        device: id.clone(),
+                        connected: false,
+                        port: port.clone(),
+                        reason: Some(DisconnectReason::Lost),
+                    });
+                }
+                Err(e) => {
+                    if e.code != msg::SERIAL_NOT_CONNECTED {
+                        eprintln!("Failed to read device {}: {}", id, e);
+                    }
+                    tokio::time::sleep(READ_ERROR_DELAY).await;
+                }
+            }
+        }
+    });
+}
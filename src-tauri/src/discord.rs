@@ -0,0 +1,195 @@
+// Discord 状态：通过 Discord 客户端的本地 IPC（Windows 命名管道 / Unix 套接字）设置 Rich Presence，
+// 显示设备连接状态和当前分组。Discord 未运行时静默跳过，下次状态变化时再尝试连接
+
+use serde_json::{json, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use crate::config::DiscordConfig;
+use crate::events::AppEvent;
+
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+const IPC_SLOTS: u32 = 10;  // Discord 依次尝试 discord-ipc-0 ~ 9
+const MAX_RESPONSE: usize = 64 * 1024;
+
+trait IpcStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> IpcStream for T {}
+
+#[cfg(windows)]
+async fn open_ipc(slot: u32) -> std::io::Result<Box<dyn IpcStream>> {
+    let pipe = tokio::net::windows::named_pipe::ClientOptions::new()
+        .open(format!(r"\\.\pipe\discord-ipc-{}", slot))?;
+    Ok(Box::new(pipe))
+}
+
+#[cfg(unix)]
+async fn open_ipc(slot: u32) -> std::io::Result<Box<dyn IpcStream>> {
+    let dir = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+        .unwrap_or_else(|| "/tmp".to_string());
+    let socket = tokio::net::UnixStream::connect(format!("{}/discord-ipc-{}", dir, slot)).await?;
+    Ok(Box::new(socket))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// 要显示的状态，变化时才发送
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Presence {
+    port: Option<String>,  // 已连接的串口
+    since: u64,  // 连接时间（秒）
+    bank: Option<String>,
+}
+
+impl Presence {
+    fn activity(&self) -> Value {
+        let details = match &self.port {
+            Some(port) => format!("Connected on {}", port),
+            None => "Disconnected".to_string(),
+        };
+        let mut activity = json!({ "details": details });
+        if let Some(bank) = &self.bank {
+            activity["state"] = json!(format!("Bank: {}", bank));
+        }
+        if self.port.is_some() {
+            activity["timestamps"] = json!({ "start": self.since });
+        }
+        activity
+    }
+}
+
+pub struct DiscordPresence {
+    config: DiscordConfig,
+    stream: Option<Box<dyn IpcStream>>,
+    presence: Presence,
+    sent: Option<Presence>,  // 最近一次成功发送的状态
+    nonce: u64,
+}
+
+impl DiscordPresence {
+    pub fn new(config: &DiscordConfig) -> Self {
+        Self {
+            config: config.clone(),
+            stream: None,
+            presence: Presence::default(),
+            sent: None,
+            nonce: 0,
+        }
+    }
+
+    // 应用 ID 变化或关闭时断开，之后按新配置重新连接
+    pub async fn update_config(&mut self, config: &DiscordConfig) {
+        if *config == self.config {
+            return;
+        }
+        if !config.enabled && self.stream.is_some() {
+            // 关闭时清除显示的状态
+            let _ = self.set_activity(Value::Null).await;
+        }
+        self.config = config.clone();
+        self.stream = None;
+        self.sent = None;
+        self.sync().await;
+    }
+
+    pub async fn handle_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::Connection { connected: true, port, .. } => {
+                self.presence.port = Some(port.clone());
+                self.presence.since = now_secs();
+            }
+            AppEvent::Connection { connected: false, .. } => {
+                self.presence.port = None;
+                self.presence.bank = None;
+            }
+            AppEvent::BankChanged { name, .. } => self.presence.bank = Some(name.clone()),
+            _ => return,
+        }
+        self.sync().await;
+    }
+
+    // 状态与上次发送的不同时发送；失败时断开，下次重新连接
+    async fn sync(&mut self) {
+        if !self.config.enabled || self.sent.as_ref() == Some(&self.presence) {
+            return;
+        }
+        let activity = self.presence.activity();
+        match self.set_activity(activity).await {
+            Ok(()) => self.sent = Some(self.presence.clone()),
+            Err(e) => {
+                eprintln!("Failed to update Discord presence: {}", e);
+                self.stream = None;
+            }
+        }
+    }
+
+    async fn set_activity(&mut self, activity: Value) -> std::io::Result<()> {
+        self.nonce += 1;
+        let payload = json!({
+            "cmd": "SET_ACTIVITY",
+            "args": { "pid": std::process::id(), "activity": activity },
+            "nonce": self.nonce.to_string(),
+        });
+        let stream = self.connect().await?;
+        write_frame(stream, OP_FRAME, &payload).await?;
+        read_frame(stream).await?;
+        Ok(())
+    }
+
+    async fn connect(&mut self) -> std::io::Result<&mut Box<dyn IpcStream>> {
+        if self.stream.is_none() {
+            let mut last_error = std::io::Error::new(std::io::ErrorKind::NotFound, "Discord is not running");
+            for slot in 0..IPC_SLOTS {
+                match open_ipc(slot).await {
+                    Ok(mut stream) => {
+                        let handshake = json!({ "v": 1, "client_id": self.config.app_id });
+                        write_frame(&mut stream, OP_HANDSHAKE, &handshake).await?;
+                        read_frame(&mut stream).await?;
+                        self.stream = Some(stream);
+                        break;
+                    }
+                    Err(e) => last_error = e,
+                }
+            }
+            if self.stream.is_none() {
+                return Err(last_error);
+            }
+        }
+        self.stream.as_mut().ok_or_else(|| std::io::Error::other("Discord is not connected"))
+    }
+}
+
+// 帧格式：操作码 u32 LE + 长度 u32 LE + JSON
+async fn write_frame(stream: &mut Box<dyn IpcStream>, op: u32, payload: &Value) -> std::io::Result<()> {
+    let body = payload.to_string().into_bytes();
+    let mut frame = Vec::with_capacity(8 + body.len());
+    frame.extend(op.to_le_bytes());
+    frame.extend((body.len() as u32).to_le_bytes());
+    frame.extend(body);
+    stream.write_all(&frame).await?;
+    stream.flush().await
+}
+
+async fn read_frame(stream: &mut Box<dyn IpcStream>) -> std::io::Result<Value> {
+    let mut header = [0u8; 8];
+    stream.read_exact(&mut header).await?;
+    let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    if len > MAX_RESPONSE {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Discord response too large"));
+    }
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body).await?;
+    let response: Value = serde_json::from_slice(&body)?;
+    // 错误响应（如应用 ID 无效）带有 evt: ERROR
+    if response["evt"] == "ERROR" {
+        let message = response["data"]["message"].as_str().unwrap_or("unknown error").to_string();
+        return Err(std::io::Error::other(message));
+    }
+    Ok(response)
+}
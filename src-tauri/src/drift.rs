@@ -0,0 +1,81 @@
+// 霍尔传感器温漂补偿：按键全部松开、轴读数落在（已补偿的）死区内时视为空闲，
+// 空闲期间让零点偏移缓慢跟随读数，偏移量限制在 max_offset 以内
+
+use serde::Serialize;
+use crate::config::{AxisSettings, DriftConfig};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AxisCompensation {
+    pub channel: usize,
+    pub center: u8,  // 配置的中心值
+    pub offset: f32,  // 当前的零点偏移
+    pub effective_center: u8,  // 补偿后的中心值
+}
+
+pub struct DriftCompensator {
+    config: DriftConfig,
+    offsets: Vec<f32>,
+}
+
+impl DriftCompensator {
+    pub fn new(config: &DriftConfig, channels: usize) -> Self {
+        Self {
+            config: config.clone(),
+            offsets: vec![0.0; channels],
+        }
+    }
+
+    // 配置变化时保留已学到的偏移，只按新的上限截断
+    pub fn update_config(&mut self, config: &DriftConfig, channels: usize) {
+        self.config = config.clone();
+        self.offsets.resize(channels, 0.0);
+        let limit = config.max_offset as f32;
+        for offset in &mut self.offsets {
+            *offset = offset.clamp(-limit, limit);
+        }
+    }
+
+    // 用一帧数据更新偏移；any_key_pressed 为 true 时不学习
+    pub fn observe(&mut self, adc: &[u8], axes: &[AxisSettings], any_key_pressed: bool) {
+        if !self.config.enabled || any_key_pressed {
+            return;
+        }
+        let limit = self.config.max_offset as f32;
+        for (channel, &value) in adc.iter().enumerate() {
+            let Some(offset) = self.offsets.get_mut(channel) else {
+                break;
+            };
+            let axis = axes.get(channel).cloned().unwrap_or_default();
+            let error = value as f32 - (axis.center as f32 + *offset);
+            // 超出死区说明轴正在被操作，不是漂移
+            if error.abs() > axis.deadzone as f32 {
+                continue;
+            }
+            *offset = (*offset + error * self.config.rate).clamp(-limit, limit);
+        }
+    }
+
+    // 应用补偿后的轴参数
+    pub fn compensate(&self, channel: usize, axis: &AxisSettings) -> AxisSettings {
+        let mut axis = axis.clone();
+        if self.config.enabled {
+            let offset = self.offsets.get(channel).copied().unwrap_or(0.0);
+            axis.center = (axis.center as f32 + offset).round().clamp(0.0, 255.0) as u8;
+        }
+        axis
+    }
+
+    pub fn compensation(&self, axes: &[AxisSettings]) -> Vec<AxisCompensation> {
+        self.offsets.iter().enumerate()
+            .map(|(channel, &offset)| {
+                let axis = axes.get(channel).cloned().unwrap_or_default();
+                AxisCompensation {
+                    channel,
+                    center: axis.center,
+                    offset,
+                    effective_center: self.compensate(channel, &axis).center,
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,348 @@
+// 内部事件总线：各子系统的输出统一发布到一个广播通道，
+// 前端事件转发、日志、历史记录等都作为订阅者接入，新功能无需单独铺设通路
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+use tokio::sync::broadcast::{self, error::RecvError, Receiver, Sender};
+#[cfg(feature = "flash")]
+use crate::bootloader::{BootloaderEvent, LogLevel};
+use crate::action_log::ActionRecord;
+use crate::config::{ActionConfig, HostState};
+use crate::deeplink::LinkRequest;
+use crate::ghosting::GhostGroup;
+use crate::health::StartupReport;
+use crate::profile::ProfilePreview;
+use crate::range_learning::RangeProposal;
+use crate::response::DecodedResponse;
+use crate::simon::SimonEvent;
+use crate::soak::SoakReport;
+use crate::matrix::{BaudDiagnosis, FrameError, KeyEdge, ParsedData};
+use crate::watch::WatchSignal;
+
+const BUS_CAPACITY: usize = 256;
+const DATA_EMIT_INTERVAL: Duration = Duration::from_millis(16);  // 数据类事件发给前端的最小间隔
+const HIDDEN_POLL_INTERVAL: Duration = Duration::from_millis(100);  // 窗口隐藏时检查是否恢复显示的间隔
+
+// 自动重连的进度
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ReconnectState {
+    Waiting { port: String, attempt: u32, max_attempts: u32, next_retry_ms: u64 },
+    Reconnected { port: String, attempt: u32 },
+    GaveUp { port: String, attempts: u32 },
+}
+
+// 连接断开的原因
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisconnectReason {
+    User,  // 用户断开
+    Lost,  // 连续读取失败
+    ForceReconnect,  // 强制重连前断开
+    Flash,  // 串口交给固件下载
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum AppEvent {
+    Frame(Arc<ParsedData>),  // 解析出新的有效帧，订阅者共享同一份数据
+    DeviceFrame { device: String, data: Arc<ParsedData> },  // 额外连接的设备解析出新的有效帧
+    AggregateFrame(Arc<ParsedData>),  // 启用多设备聚合时，合并后的逻辑设备的帧
+    KeyEdge(KeyEdge),  // 按键按下/松开
+    FrameError(FrameError),  // 帧校验失败
+    WrongBaud(BaudDiagnosis),  // 只收到无法同步的数据
+    DeviceResponse(DecodedResponse),  // 按配置布局解码出的设备应答
+    GameState(BTreeMap<String, serde_json::Value>),  // 游戏状态中发生变化的字段
+    WatchSignals(Vec<WatchSignal>),  // 监视表达式的值发生变化
+    Connection { connected: bool, port: String, reason: Option<DisconnectReason> },  // 连接时 reason 为 None
+    DeviceConnection { device: String, connected: bool, port: String, reason: Option<DisconnectReason> },  // 额外设备的连接变化
+    Reconnect(ReconnectState),
+    PortAdded { port: String },  // 系统中出现了新的串口
+    PortRemoved { port: String },  // 串口从系统中消失
+    BankChanged { index: usize, name: String },  // 旋钮切换了绑定分组
+    RangeProposal(RangeProposal),  // 学到的行程与配置相差较大，等待用户确认
+    Ghosting(GhostGroup),  // 某个按键组合多次出现疑似鬼键
+    PauseChanged { paused: bool },  // 输出暂停或恢复
+    Rehearsal(ActionConfig),  // 演练模式下本应执行的动作
+    ActionFailed(ActionRecord),  // 动作执行失败
+    VariableChanged { name: String, value: i64 },  // 变量的值发生变化
+    HostState(HostState),  // 主机状态（供电、网络、麦克风）发生变化
+    ConfigChanged { pointer: String },  // 配置被修改，pointer 为修改的部分，空字符串表示整个配置
+    #[cfg(feature = "flash")]
+    Bootloader(BootloaderEvent),
+    StartupReport(StartupReport),
+    SoakReport(SoakReport),  // 老化测试结束
+    Simon(SimonEvent),  // Simon 质检模式的进度
+    FlashRequested { file: String },  // 通过链接或文件打开了固件，等待前端确认
+    ProfileOpened(ProfilePreview),  // 打开了配置方案文件，等待前端确认导入
+    LinkRequested(LinkRequest),  // 链接请求切换方案或连接，等待前端确认
+}
+
+impl AppEvent {
+    // 转发给前端时使用的事件名
+    fn tauri_name(&self) -> &'static str {
+        match self {
+            AppEvent::Frame(_) => "matrix-data",
+            AppEvent::DeviceFrame { .. } => "device-data",
+            AppEvent::AggregateFrame(_) => "aggregate-data",
+            AppEvent::KeyEdge(_) => "key-edge",
+            AppEvent::FrameError(_) => "frame-error",
+            AppEvent::WrongBaud(_) => "likely-wrong-baud",
+            AppEvent::DeviceResponse(_) => "device-response",
+            AppEvent::GameState(_) => "game-state",
+            AppEvent::WatchSignals(_) => "watch-signals",
+            AppEvent::Connection { .. } => "connection-changed",
+            AppEvent::DeviceConnection { .. } => "device-connection-changed",
+            AppEvent::Reconnect(_) => "reconnect-state",
+            AppEvent::PortAdded { .. } => "port-added",
+            AppEvent::PortRemoved { .. } => "port-removed",
+            AppEvent::BankChanged { .. } => "bank-changed",
+            AppEvent::RangeProposal(_) => "range-proposal",
+            AppEvent::Ghosting(_) => "ghosting-detected",
+            AppEvent::PauseChanged { .. } => "pause-changed",
+            AppEvent::Rehearsal(_) => "rehearsal-action",
+            AppEvent::ActionFailed(_) => "action-failed",
+            AppEvent::VariableChanged { .. } => "variable-changed",
+            AppEvent::HostState(_) => "host-state",
+            AppEvent::ConfigChanged { .. } => "config-changed",
+            #[cfg(feature = "flash")]
+            AppEvent::Bootloader(BootloaderEvent::Log { .. }) => "bootloader-log",
+            #[cfg(feature = "flash")]
+            AppEvent::Bootloader(BootloaderEvent::Progress { .. }) => "bootloader-progress",
+            #[cfg(feature = "flash")]
+            AppEvent::Bootloader(BootloaderEvent::Version(_)) => "bootloader-version",
+            AppEvent::StartupReport(_) => "startup-report",
+            AppEvent::SoakReport(_) => "soak-report",
+            AppEvent::Simon(_) => "simon",
+            AppEvent::FlashRequested { .. } => "flash-requested",
+            AppEvent::ProfileOpened(_) => "profile-opened",
+            AppEvent::LinkRequested(_) => "link-requested",
+        }
+    }
+
+    // 数据类事件只需要最新值，前端处理不过来时可以丢弃旧值；其余为状态变化，必须送达
+    fn is_data(&self) -> bool {
+        match self {
+            AppEvent::Frame(_) | AppEvent::DeviceFrame { .. } | AppEvent::AggregateFrame(_) => true,
+            #[cfg(feature = "flash")]
+            AppEvent::Bootloader(BootloaderEvent::Progress { .. }) => true,
+            _ => false,
+        }
+    }
+
+    // 数据类事件在待发送表中的键，每个设备的数据各自保留最新一个
+    fn data_key(&self) -> String {
+        match self {
+            AppEvent::DeviceFrame { device, .. } => format!("{}:{}", self.tauri_name(), device),
+            _ => self.tauri_name().to_string(),
+        }
+    }
+}
+
+// 发往前端的事件计数
+#[derive(Default)]
+pub struct EmitStats {
+    data_emitted: AtomicU64,
+    data_dropped: AtomicU64,  // 被更新的值覆盖而未发送的数据类事件
+    state_emitted: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmitStatsReport {
+    pub data_emitted: u64,
+    pub data_dropped: u64,
+    pub state_emitted: u64,
+}
+
+impl EmitStats {
+    pub fn report(&self) -> EmitStatsReport {
+        EmitStatsReport {
+            data_emitted: self.data_emitted.load(Ordering::Relaxed),
+            data_dropped: self.data_dropped.load(Ordering::Relaxed),
+            state_emitted: self.state_emitted.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EventBus {
+    tx: Sender<AppEvent>,
+    stats: Arc<EmitStats>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(BUS_CAPACITY);
+        Self { tx, stats: Arc::new(EmitStats::default()) }
+    }
+
+    // 没有订阅者时发送会失败，直接忽略
+    pub fn publish(&self, event: AppEvent) {
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> Receiver<AppEvent> {
+        self.tx.subscribe()
+    }
+
+    pub fn emit_stats(&self) -> EmitStatsReport {
+        self.stats.report()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 接收下一个事件，订阅者处理过慢时跳过丢失的部分；总线关闭时返回 None
+pub async fn next_event(rx: &mut Receiver<AppEvent>) -> Option<AppEvent> {
+    loop {
+        match rx.recv().await {
+            Ok(event) => return Some(event),
+            Err(RecvError::Lagged(n)) => eprintln!("Event subscriber lagged, skipped {} events", n),
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+// 把总线上的事件转发给前端，负载与事件类型内部的数据一致。
+// 状态变化事件立即发送；数据类事件每种只保留最新一个，由单独的任务限速发送，
+// 窗口隐藏时暂停发送，避免前端忙碌时事件无限堆积
+pub fn spawn_tauri_forwarder(app: AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    let stats = bus.stats.clone();
+    let pending: Arc<std::sync::Mutex<HashMap<String, AppEvent>>> = Default::default();
+    let notify = Arc::new(Notify::new());
+
+    {
+        let app = app.clone();
+        let stats = stats.clone();
+        let pending = pending.clone();
+        let notify = notify.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(event) = next_event(&mut rx).await {
+                if event.is_data() {
+                    let replaced = pending.lock().unwrap().insert(event.data_key(), event);
+                    if replaced.is_some() {
+                        stats.data_dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    notify.notify_one();
+                } else {
+                    forward_to_tauri(&app, event);
+                    stats.state_emitted.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+    }
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            notify.notified().await;
+            while !window_visible(&app) {
+                tokio::time::sleep(HIDDEN_POLL_INTERVAL).await;
+            }
+            let events: Vec<AppEvent> = pending.lock().unwrap().drain().map(|(_, event)| event).collect();
+            for event in events {
+                forward_to_tauri(&app, event);
+                stats.data_emitted.fetch_add(1, Ordering::Relaxed);
+            }
+            tokio::time::sleep(DATA_EMIT_INTERVAL).await;
+        }
+    });
+}
+
+fn window_visible(app: &AppHandle) -> bool {
+    app.get_webview_window("main")
+        .and_then(|window| window.is_visible().ok())
+        .unwrap_or(true)
+}
+
+fn forward_to_tauri(app: &AppHandle, event: AppEvent) {
+    let name = event.tauri_name();
+    let result = match event {
+        AppEvent::Frame(data) | AppEvent::AggregateFrame(data) => app.emit(name, data),
+        AppEvent::DeviceFrame { device, data } => {
+            app.emit(name, serde_json::json!({ "device": device, "data": data }))
+        }
+        AppEvent::KeyEdge(edge) => app.emit(name, edge),
+        AppEvent::FrameError(error) => app.emit(name, error),
+        AppEvent::WrongBaud(diagnosis) => app.emit(name, diagnosis),
+        AppEvent::DeviceResponse(response) => app.emit(name, response),
+        AppEvent::GameState(values) => app.emit(name, values),
+        AppEvent::WatchSignals(signals) => app.emit(name, signals),
+        AppEvent::Connection { connected, port, reason } => {
+            app.emit(name, serde_json::json!({ "connected": connected, "port": port, "reason": reason }))
+        }
+        AppEvent::DeviceConnection { device, connected, port, reason } => app.emit(
+            name,
+            serde_json::json!({ "device": device, "connected": connected, "port": port, "reason": reason }),
+        ),
+        AppEvent::Reconnect(state) => app.emit(name, state),
+        AppEvent::PortAdded { port } | AppEvent::PortRemoved { port } => {
+            app.emit(name, serde_json::json!({ "port": port }))
+        }
+        AppEvent::ConfigChanged { pointer } => app.emit(name, serde_json::json!({ "pointer": pointer })),
+        AppEvent::BankChanged { index, name: bank } => {
+            app.emit(name, serde_json::json!({ "index": index, "name": bank }))
+        }
+        AppEvent::RangeProposal(proposal) => app.emit(name, proposal),
+        AppEvent::Ghosting(group) => app.emit(name, group),
+        AppEvent::PauseChanged { paused } => app.emit(name, serde_json::json!({ "paused": paused })),
+        AppEvent::Rehearsal(action) => app.emit(name, action),
+        AppEvent::ActionFailed(record) => app.emit(name, record),
+        AppEvent::HostState(host) => app.emit(name, host),
+        AppEvent::VariableChanged { name: variable, value } => {
+            app.emit(name, serde_json::json!({ "name": variable, "value": value }))
+        }
+        #[cfg(feature = "flash")]
+        AppEvent::Bootloader(event) => app.emit(name, event),
+        AppEvent::StartupReport(report) => app.emit(name, report),
+        AppEvent::SoakReport(report) => app.emit(name, report),
+        AppEvent::Simon(event) => app.emit(name, event),
+        AppEvent::FlashRequested { file } => app.emit(name, serde_json::json!({ "file": file })),
+        AppEvent::ProfileOpened(preview) => app.emit(name, preview),
+        AppEvent::LinkRequested(request) => app.emit(name, request),
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to emit {}: {}", name, e);
+    }
+}
+
+// 把连接变化、重连、波特率诊断和下载错误写入日志
+pub fn spawn_logger(bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = next_event(&mut rx).await {
+            match event {
+                AppEvent::Connection { connected, port, .. } => {
+                    let status = if connected { "connected" } else { "disconnected" };
+                    println!("Serial port {} {}", port, status);
+                }
+                AppEvent::Reconnect(ReconnectState::Waiting { port, attempt, max_attempts, next_retry_ms }) => {
+                    println!("Reconnecting {} in {} ms (attempt {} of {})", port, next_retry_ms, attempt, max_attempts);
+                }
+                AppEvent::Reconnect(ReconnectState::GaveUp { port, attempts }) => {
+                    eprintln!("Gave up reconnecting {} after {} attempts", port, attempts);
+                }
+                AppEvent::WrongBaud(diagnosis) => {
+                    eprintln!(
+                        "No frames at {} baud after {} bytes, try {:?}",
+                        diagnosis.baud_rate, diagnosis.bytes_without_frame, diagnosis.suggested_rates
+                    );
+                }
+                #[cfg(feature = "flash")]
+                AppEvent::Bootloader(BootloaderEvent::Log { level: LogLevel::Error, message, .. }) => {
+                    eprintln!("Bootloader error: {}", message);
+                }
+                _ => {}
+            }
+        }
+    });
+}
@@ -0,0 +1,165 @@
+// 游戏状态：轮询 HTTP 接口或接收 UDP 数据报，按配置的 JSON Pointer 取出字段，
+// 保存最新值并据此点亮LED；来源的增删改在下一次轮询时生效
+
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use crate::config::{GameStateConfig, GameStateLed, GameStateSource, GameStateTransport};
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_DATAGRAM: usize = 65536;
+
+// JSON 值是否为真：true、非零数字、非空且不为 "0"/"false" 的字符串
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty() && s != "0" && !s.eq_ignore_ascii_case("false"),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+#[derive(Default)]
+pub struct GameState {
+    values: BTreeMap<String, Value>,  // "来源.字段" -> 最新值
+    leds: HashMap<usize, bool>,  // 最近一次请求的LED状态
+}
+
+impl GameState {
+    // 从来源返回的文档中取出字段，返回值发生变化的字段
+    pub fn update(&mut self, source: &GameStateSource, document: &Value) -> BTreeMap<String, Value> {
+        let mut changed = BTreeMap::new();
+        for field in &source.fields {
+            let value = document.pointer(&field.pointer).cloned().unwrap_or(Value::Null);
+            let key = format!("{}.{}", source.name, field.name);
+            if self.values.get(&key) != Some(&value) {
+                self.values.insert(key.clone(), value.clone());
+                changed.insert(key, value);
+            }
+        }
+        changed
+    }
+
+    // 配置中已删除的来源不再保留旧值
+    pub fn retain_sources(&mut self, config: &GameStateConfig) {
+        self.values.retain(|key, _| {
+            key.split_once('.')
+                .is_some_and(|(source, _)| config.sources.iter().any(|s| s.name == source))
+        });
+    }
+
+    pub fn values(&self) -> BTreeMap<String, Value> {
+        self.values.clone()
+    }
+
+    // 状态需要改变的LED
+    pub fn led_changes(&mut self, mappings: &[GameStateLed]) -> Vec<(usize, bool)> {
+        let mut changes = Vec::new();
+        for mapping in mappings {
+            let on = self.values.get(&mapping.value).is_some_and(is_truthy);
+            if self.leds.insert(mapping.led, on) != Some(on) {
+                changes.push((mapping.led, on));
+            }
+        }
+        changes
+    }
+
+    // 重新连接后设备的LED状态未知，下次全部重新发送
+    pub fn reset_leds(&mut self) {
+        self.leds.clear();
+    }
+}
+
+// 轮询器：记录各 HTTP 来源上次轮询的时间，保持 UDP 来源的监听
+pub struct GameStatePoller {
+    client: reqwest::Client,
+    last_poll: HashMap<String, Instant>,
+    sockets: HashMap<String, UdpSocket>,  // 监听地址 -> 套接字
+    failed_binds: HashSet<String>,  // 监听失败的地址，配置修改后才重试
+}
+
+impl GameStatePoller {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(HTTP_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            last_poll: HashMap::new(),
+            sockets: HashMap::new(),
+            failed_binds: HashSet::new(),
+        }
+    }
+
+    // 读取到期的来源，返回 (来源, 文档)；单个来源失败只记录日志
+    pub async fn poll(&mut self, config: &GameStateConfig) -> Vec<(GameStateSource, Value)> {
+        let configured = |bind: &String| {
+            config.sources.iter().any(|s| matches!(&s.transport, GameStateTransport::Udp { bind: b } if b == bind))
+        };
+        self.sockets.retain(|bind, _| configured(bind));
+        self.failed_binds.retain(configured);
+        let now = Instant::now();
+        let mut documents = Vec::new();
+        for source in &config.sources {
+            match &source.transport {
+                GameStateTransport::Http { url } => {
+                    let interval = Duration::from_millis(source.interval_ms);
+                    if self.last_poll.get(&source.name).is_some_and(|last| now.duration_since(*last) < interval) {
+                        continue;
+                    }
+                    self.last_poll.insert(source.name.clone(), now);
+                    match self.fetch(url).await {
+                        Ok(document) => documents.push((source.clone(), document)),
+                        Err(e) => eprintln!("Failed to poll game state {}: {}", source.name, e),
+                    }
+                }
+                GameStateTransport::Udp { bind } => {
+                    for document in self.receive(bind).await {
+                        documents.push((source.clone(), document));
+                    }
+                }
+            }
+        }
+        documents
+    }
+
+    async fn fetch(&self, url: &str) -> Result<Value, String> {
+        let response = self.client.get(url).send().await.map_err(|e| e.to_string())?;
+        let response = response.error_for_status().map_err(|e| e.to_string())?;
+        response.json().await.map_err(|e| e.to_string())
+    }
+
+    // 取出已收到的全部数据报，不等待
+    async fn receive(&mut self, bind: &str) -> Vec<Value> {
+        if self.failed_binds.contains(bind) {
+            return Vec::new();
+        }
+        if !self.sockets.contains_key(bind) {
+            match UdpSocket::bind(bind).await {
+                Ok(socket) => {
+                    self.sockets.insert(bind.to_string(), socket);
+                }
+                Err(e) => {
+                    eprintln!("Failed to listen for game state on {}: {}", bind, e);
+                    self.failed_binds.insert(bind.to_string());
+                    return Vec::new();
+                }
+            }
+        }
+        let Some(socket) = self.sockets.get(bind) else {
+            return Vec::new();
+        };
+        let mut documents = Vec::new();
+        let mut buffer = vec![0u8; MAX_DATAGRAM];
+        while let Ok((len, _)) = socket.try_recv_from(&mut buffer) {
+            match serde_json::from_slice(&buffer[..len]) {
+                Ok(document) => documents.push(document),
+                Err(e) => eprintln!("Invalid game state datagram on {}: {}", bind, e),
+            }
+        }
+        documents
+    }
+}
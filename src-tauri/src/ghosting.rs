@@ -0,0 +1,106 @@
+// 按键矩阵鬼键检测：没有二极管的矩阵中，同时按下矩形三个角上的按键时，
+// 第四个角也会被读成按下。矩形的四个角在同一帧内有两个以上同时变为按下时，
+// 多半是第三个按键带出了鬼键；同一组合出现达到阈值次数后提示用户
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::config::GhostingConfig;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GhostGroup {
+    pub keys: [usize; 4],  // 矩形四个角的按键编号，从小到大
+    pub rows: [usize; 2],
+    pub columns: [usize; 2],
+    pub occurrences: u32,
+    pub last_seen: u64,  // 毫秒时间戳
+}
+
+pub struct GhostDetector {
+    config: GhostingConfig,
+    previous: Vec<bool>,
+    groups: HashMap<[usize; 4], GhostGroup>,
+}
+
+impl GhostDetector {
+    pub fn new(config: &GhostingConfig) -> Self {
+        Self {
+            config: config.clone(),
+            previous: Vec::new(),
+            groups: HashMap::new(),
+        }
+    }
+
+    // 列数变化后原有的行列对应关系失效，清空统计
+    pub fn update_config(&mut self, config: &GhostingConfig) {
+        if config.columns != self.config.columns {
+            self.groups.clear();
+        }
+        self.config = config.clone();
+    }
+
+    // 处理一帧按键状态，返回本帧达到提示阈值的组合
+    pub fn observe(&mut self, keys: &[bool]) -> Vec<GhostGroup> {
+        let previous = std::mem::replace(&mut self.previous, keys.to_vec());
+        let Some(columns) = self.config.columns.filter(|&c| c > 0) else {
+            return Vec::new();
+        };
+        let pressed: Vec<usize> = keys.iter().enumerate().filter(|(_, &p)| p).map(|(i, _)| i).collect();
+        if pressed.len() < 4 {
+            return Vec::new();
+        }
+        let is_new = |key: usize| !previous.get(key).copied().unwrap_or(false);
+        if !pressed.iter().any(|&key| is_new(key)) {
+            return Vec::new();
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let mut reached = Vec::new();
+        for (i, &a) in pressed.iter().enumerate() {
+            for &b in &pressed[i + 1..] {
+                let (row_a, col_a) = (a / columns, a % columns);
+                let (row_b, col_b) = (b / columns, b % columns);
+                // 以对角线上的两个按键确定矩形，左上角在前，避免同一矩形重复计数
+                if row_a >= row_b || col_a >= col_b {
+                    continue;
+                }
+                let (c, d) = (row_a * columns + col_b, row_b * columns + col_a);
+                if !keys.get(c).copied().unwrap_or(false) || !keys.get(d).copied().unwrap_or(false) {
+                    continue;
+                }
+                let mut corners = [a, b, c, d];
+                corners.sort_unstable();
+                if corners.iter().filter(|&&key| is_new(key)).count() < 2 {
+                    continue;
+                }
+                let group = self.groups.entry(corners).or_insert_with(|| GhostGroup {
+                    keys: corners,
+                    rows: [row_a, row_b],
+                    columns: [col_a, col_b],
+                    occurrences: 0,
+                    last_seen: 0,
+                });
+                group.occurrences += 1;
+                group.last_seen = timestamp;
+                if group.occurrences == self.config.threshold {
+                    reached.push(group.clone());
+                }
+            }
+        }
+        reached
+    }
+
+    // 按出现次数从多到少排列
+    pub fn report(&self) -> Vec<GhostGroup> {
+        let mut groups: Vec<GhostGroup> = self.groups.values().cloned().collect();
+        groups.sort_by(|a, b| b.occurrences.cmp(&a.occurrences).then(a.keys.cmp(&b.keys)));
+        groups
+    }
+
+    pub fn reset(&mut self) {
+        self.groups.clear();
+    }
+}
@@ -0,0 +1,158 @@
+// 启动健康检查：汇总配置、上次运行状态和设备情况，供前端第一时间提示用户
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use crate::config::{ActionConfig, BindingConfig, ConfigLoadStatus, MatrixConfig};
+use crate::output::parse_key_combo;
+use crate::serial::SerialManager;
+
+// 运行标记文件：正常退出时删除，启动时仍存在说明上次异常退出
+const SESSION_MARKER: &str = "session.lock";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueLevel {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthIssue {
+    pub level: IssueLevel,
+    pub code: String,  // 稳定的问题代码，前端据此显示对应的提示
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupReport {
+    pub config_valid: bool,
+    pub config_status: ConfigLoadStatus,
+    pub previous_session_crashed: bool,
+    pub port: String,
+    pub device_found: bool,
+    pub issues: Vec<HealthIssue>,
+}
+
+impl StartupReport {
+    pub fn collect(config: &MatrixConfig, config_status: &ConfigLoadStatus) -> Self {
+        let mut issues = Vec::new();
+        let mut issue = |level: IssueLevel, code: &str, message: String| {
+            issues.push(HealthIssue {
+                level,
+                code: code.to_string(),
+                message,
+            });
+        };
+
+        // 配置文件
+        let mut config_valid = true;
+        match config_status {
+            ConfigLoadStatus::RecoveredFromBackup { error, corrupted_copy } => {
+                issue(
+                    IssueLevel::Warning,
+                    "config_recovered_from_backup",
+                    format!("配置文件已损坏（{}），已从备份恢复，损坏的文件保存为 {}", error, corrupted_copy),
+                );
+            }
+            ConfigLoadStatus::Defaulted { error, corrupted_copy } => {
+                config_valid = false;
+                issue(
+                    IssueLevel::Error,
+                    "config_parse_failed",
+                    format!("配置文件解析失败，已使用默认配置（{}），原文件保存为 {}", error, corrupted_copy),
+                );
+            }
+            ConfigLoadStatus::Repaired { repairs } => {
+                for repair in repairs {
+                    issue(IssueLevel::Warning, "config_repaired", repair.clone());
+                }
+            }
+            ConfigLoadStatus::Loaded | ConfigLoadStatus::Missing => {}
+        }
+        for problem in config.validate() {
+            config_valid = false;
+            issue(IssueLevel::Warning, "config_invalid", problem);
+        }
+
+        // 绑定冲突与无效按键
+        let mut key_bindings: HashMap<usize, usize> = HashMap::new();
+        for binding in &config.bindings {
+            let combos: Vec<&String> = match binding {
+                BindingConfig::KeyPress { key, action, .. } => {
+                    *key_bindings.entry(*key).or_default() += 1;
+                    match action {
+                        ActionConfig::KeyCombo { keys } => vec![keys],
+                        _ => Vec::new(),
+                    }
+                }
+                BindingConfig::AxisRepeat { positive_key, negative_key, .. } => {
+                    positive_key.iter().chain(negative_key.iter()).collect()
+                }
+                BindingConfig::DualStage { .. } | BindingConfig::Encoder { .. } => Vec::new(),
+                BindingConfig::Flick { action, .. } | BindingConfig::Spin { action, .. } => match action {
+                    ActionConfig::KeyCombo { keys } => vec![keys],
+                    _ => Vec::new(),
+                },
+            };
+            for combo in combos {
+                if let Err(e) = parse_key_combo(combo) {
+                    issue(IssueLevel::Warning, "binding_invalid_key", e);
+                }
+            }
+        }
+        let mut conflicts: Vec<_> = key_bindings.into_iter().filter(|(_, n)| *n > 1).collect();
+        conflicts.sort();
+        for (key, count) in conflicts {
+            issue(
+                IssueLevel::Warning,
+                "binding_conflict",
+                format!("按键 {} 绑定了 {} 个动作", key + 1, count),
+            );
+        }
+        if let Some(key) = config.wake_key {
+            if config.bindings.iter().any(|b| matches!(b, BindingConfig::KeyPress { key: k, .. } if *k == key)) {
+                issue(
+                    IssueLevel::Warning,
+                    "wake_key_conflict",
+                    format!("唤醒按键 {} 同时绑定了其它动作", key + 1),
+                );
+            }
+        }
+
+        // 上次运行是否异常退出
+        let marker = MatrixConfig::data_file_path(SESSION_MARKER);
+        let previous_session_crashed = fs::metadata(&marker).is_ok();
+        if previous_session_crashed {
+            issue(IssueLevel::Warning, "previous_crash", "上次运行未正常退出".to_string());
+        }
+
+        // 上次使用的串口是否存在
+        let port = config.serial_matrix.port.clone();
+        let device_found = SerialManager::list_ports().contains(&port);
+        if !device_found {
+            issue(IssueLevel::Warning, "device_not_found", format!("未找到串口 {}", port));
+        }
+
+        Self {
+            config_valid,
+            config_status: config_status.clone(),
+            previous_session_crashed,
+            port,
+            device_found,
+            issues,
+        }
+    }
+}
+
+// 启动时创建运行标记
+pub fn mark_session_start() {
+    if let Err(e) = fs::write(MatrixConfig::data_file_path(SESSION_MARKER), b"") {
+        eprintln!("Failed to create session marker: {}", e);
+    }
+}
+
+// 正常退出时删除运行标记
+pub fn mark_session_end() {
+    let _ = fs::remove_file(MatrixConfig::data_file_path(SESSION_MARKER));
+}
@@ -0,0 +1,87 @@
+// ADC直方图：连接期间持续统计每个通道各读数出现的次数，
+// 用于检查踏板等是否用满了量程、是否在两端削顶，无需导出数据
+
+use serde::Serialize;
+use crate::matrix::ParsedData;
+
+const ADC_VALUES: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AdcHistogram {
+    pub channel: usize,
+    pub samples: u64,
+    pub bins: Vec<HistogramBin>,
+    pub min: Option<u8>,
+    pub max: Option<u8>,
+    pub p1: Option<u8>,
+    pub p99: Option<u8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistogramBin {
+    pub start: u8,  // 本区间包含的最小读数
+    pub end: u8,  // 本区间包含的最大读数
+    pub count: u64,
+}
+
+// 按读数逐个计数，区间数量在查询时再合并，修改配置不需要重新统计
+#[derive(Default)]
+pub struct AdcHistograms {
+    counts: Vec<[u64; ADC_VALUES]>,
+}
+
+impl AdcHistograms {
+    pub fn record(&mut self, data: &ParsedData) {
+        if !data.valid {
+            return;
+        }
+        if self.counts.len() < data.adc.len() {
+            self.counts.resize(data.adc.len(), [0; ADC_VALUES]);
+        }
+        for (counts, &value) in self.counts.iter_mut().zip(&data.adc) {
+            counts[value as usize] += 1;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.counts.clear();
+    }
+
+    pub fn report(&self, channel: usize, bins: usize) -> AdcHistogram {
+        let empty = [0; ADC_VALUES];
+        let counts = self.counts.get(channel).unwrap_or(&empty);
+        let samples: u64 = counts.iter().sum();
+        let width = ADC_VALUES.div_ceil(bins.clamp(1, ADC_VALUES));
+        let bins = counts
+            .chunks(width)
+            .enumerate()
+            .map(|(i, chunk)| HistogramBin {
+                start: (i * width) as u8,
+                end: (i * width + chunk.len() - 1) as u8,
+                count: chunk.iter().sum(),
+            })
+            .collect();
+        AdcHistogram {
+            channel,
+            samples,
+            bins,
+            min: counts.iter().position(|&c| c > 0).map(|v| v as u8),
+            max: counts.iter().rposition(|&c| c > 0).map(|v| v as u8),
+            p1: percentile(counts, samples, 0.01),
+            p99: percentile(counts, samples, 0.99),
+        }
+    }
+}
+
+// 累计次数达到 fraction 时的读数
+fn percentile(counts: &[u64; ADC_VALUES], samples: u64, fraction: f64) -> Option<u8> {
+    if samples == 0 {
+        return None;
+    }
+    let target = ((samples as f64 * fraction).ceil() as u64).max(1);
+    let mut seen = 0;
+    counts.iter().position(|&c| {
+        seen += c;
+        seen >= target
+    }).map(|v| v as u8)
+}
@@ -0,0 +1,148 @@
+// 历史记录：把按键、连接和ADC采样写入本地SQLite数据库，重启后仍可查询
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use crate::config::{HistoryConfig, MatrixConfig};
+use crate::events::AppEvent;
+use crate::matrix::ParsedData;
+
+const DB_FILE: &str = "history.db";
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);  // 清理过期记录的间隔
+const DAY_MS: i64 = 24 * 3600 * 1000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryRecord {
+    pub timestamp: i64,  // 毫秒时间戳
+    pub kind: String,  // key / connection / adc
+    pub detail: Value,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+pub struct HistoryStore {
+    conn: Connection,
+    config: HistoryConfig,
+    last_adc_sample: Option<Instant>,
+    last_prune: Instant,
+}
+
+impl HistoryStore {
+    pub fn open(config: &HistoryConfig) -> Result<Self, String> {
+        let conn = Connection::open(MatrixConfig::data_file_path(DB_FILE))
+            .map_err(|e| e.to_string())?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                detail TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_events_kind_time ON events (kind, timestamp);",
+        )
+        .map_err(|e| e.to_string())?;
+
+        let mut store = Self {
+            conn,
+            config: config.clone(),
+            last_adc_sample: None,
+            last_prune: Instant::now(),
+        };
+        store.prune();
+        Ok(store)
+    }
+
+    pub fn update_config(&mut self, config: &HistoryConfig) {
+        self.config = config.clone();
+    }
+
+    fn insert(&self, kind: &str, detail: Value) {
+        if !self.config.enabled {
+            return;
+        }
+        let result = self.conn.execute(
+            "INSERT INTO events (timestamp, kind, detail) VALUES (?1, ?2, ?3)",
+            params![now_ms(), kind, detail.to_string()],
+        );
+        if let Err(e) = result {
+            eprintln!("Failed to write history: {}", e);
+        }
+    }
+
+    // 作为事件总线的订阅者，记录按键变化、连接变化和ADC采样
+    pub fn handle_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::KeyEdge(edge) => {
+                self.insert("key", json!({ "index": edge.index, "pressed": edge.pressed }));
+            }
+            AppEvent::Connection { connected, port, .. } => {
+                let event = if *connected { "connected" } else { "disconnected" };
+                self.insert("connection", json!({ "event": event, "port": port }));
+            }
+            AppEvent::Frame(data) => self.sample_adc(data),
+            _ => {}
+        }
+
+        if self.last_prune.elapsed() >= PRUNE_INTERVAL {
+            self.prune();
+        }
+    }
+
+    // 按间隔采样ADC
+    fn sample_adc(&mut self, data: &ParsedData) {
+        let interval = Duration::from_millis(self.config.adc_sample_interval_ms);
+        if self.last_adc_sample.is_none_or(|t| t.elapsed() >= interval) {
+            self.last_adc_sample = Some(Instant::now());
+            self.insert("adc", json!({ "values": data.adc }));
+        }
+    }
+
+    // 按保留策略删除过期记录
+    fn prune(&mut self) {
+        self.last_prune = Instant::now();
+        let now = now_ms();
+        let policies = [
+            ("key", self.config.key_retention_days),
+            ("connection", self.config.connection_retention_days),
+            ("adc", self.config.adc_retention_days),
+        ];
+        for (kind, days) in policies {
+            let result = self.conn.execute(
+                "DELETE FROM events WHERE kind = ?1 AND timestamp < ?2",
+                params![kind, now - days as i64 * DAY_MS],
+            );
+            if let Err(e) = result {
+                eprintln!("Failed to prune history: {}", e);
+            }
+        }
+    }
+
+    // 查询指定类型在时间范围内的记录（毫秒时间戳，闭区间）
+    pub fn query(&self, kind: &str, from: i64, to: i64) -> Result<Vec<HistoryRecord>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT timestamp, kind, detail FROM events
+                 WHERE kind = ?1 AND timestamp BETWEEN ?2 AND ?3
+                 ORDER BY timestamp",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![kind, from, to], |row| {
+                let detail: String = row.get(2)?;
+                Ok(HistoryRecord {
+                    timestamp: row.get(0)?,
+                    kind: row.get(1)?,
+                    detail: serde_json::from_str(&detail).unwrap_or(Value::Null),
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+    }
+}
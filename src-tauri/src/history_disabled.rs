@@ -0,0 +1,29 @@
+// 未启用 history 功能时的替代实现：数据库始终打开失败，查询返回 HISTORY_UNAVAILABLE
+
+use serde::Serialize;
+use serde_json::Value;
+use crate::config::HistoryConfig;
+use crate::events::AppEvent;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryRecord {
+    pub timestamp: i64,
+    pub kind: String,
+    pub detail: Value,
+}
+
+pub struct HistoryStore;
+
+impl HistoryStore {
+    pub fn open(_config: &HistoryConfig) -> Result<Self, String> {
+        Err("编译时未启用 history 功能".to_string())
+    }
+
+    pub fn update_config(&mut self, _config: &HistoryConfig) {}
+
+    pub fn handle_event(&mut self, _event: &AppEvent) {}
+
+    pub fn query(&self, _kind: &str, _from: i64, _to: i64) -> Result<Vec<HistoryRecord>, String> {
+        Ok(Vec::new())
+    }
+}
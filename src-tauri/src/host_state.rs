@@ -0,0 +1,197 @@
+// 主机状态采样：交流电/电池供电、网络连通、麦克风是否正在使用，供序列和定时任务的条件判断。
+// 采样会读文件或调用系统接口，应在阻塞线程中进行；无法获取的项为 None
+
+use std::net::UdpSocket;
+use crate::config::HostState;
+
+pub fn sample() -> HostState {
+    HostState {
+        on_battery: platform::on_battery(),
+        network_online: Some(network_online()),
+        microphone_in_use: platform::microphone_in_use(),
+    }
+}
+
+// 有默认路由即视为联网：UDP 的 connect 只选择路由，不发送数据
+fn network_online() -> bool {
+    UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| socket.connect("8.8.8.8:53"))
+        .is_ok()
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows_sys::Win32::Foundation::{ERROR_SUCCESS, FILETIME};
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+    use windows_sys::Win32::System::Registry::{
+        RegCloseKey, RegEnumKeyExW, RegGetValueW, RegOpenKeyExW, HKEY, HKEY_CURRENT_USER, KEY_READ, RRF_RT_REG_QWORD,
+    };
+
+    const MICROPHONE_CONSENT: &str =
+        r"Software\Microsoft\Windows\CurrentVersion\CapabilityAccessManager\ConsentStore\microphone";
+
+    fn wide(text: &str) -> Vec<u16> {
+        text.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    pub fn on_battery() -> Option<bool> {
+        let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+        if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+            return None;
+        }
+        match status.ACLineStatus {
+            0 => Some(true),
+            1 => Some(false),
+            _ => None,
+        }
+    }
+
+    // 正在使用麦克风的应用在隐私设置记录中的 LastUsedTimeStop 为 0；
+    // 打包应用直接列在 microphone 下，普通程序列在 microphone\NonPackaged 下
+    pub fn microphone_in_use() -> Option<bool> {
+        let root = open_key(HKEY_CURRENT_USER, MICROPHONE_CONSENT)?;
+        let mut in_use = any_in_use(root);
+        if let Some(non_packaged) = open_key(root, "NonPackaged") {
+            in_use |= any_in_use(non_packaged);
+            unsafe { RegCloseKey(non_packaged) };
+        }
+        unsafe { RegCloseKey(root) };
+        Some(in_use)
+    }
+
+    fn open_key(parent: HKEY, path: &str) -> Option<HKEY> {
+        let mut key: HKEY = std::ptr::null_mut();
+        let path = wide(path);
+        let result = unsafe { RegOpenKeyExW(parent, path.as_ptr(), 0, KEY_READ, &mut key) };
+        (result == ERROR_SUCCESS).then_some(key)
+    }
+
+    fn any_in_use(key: HKEY) -> bool {
+        let value = wide("LastUsedTimeStop");
+        let mut index = 0;
+        loop {
+            let mut name = [0u16; 512];
+            let mut len = name.len() as u32;
+            let mut written: FILETIME = unsafe { std::mem::zeroed() };
+            let result = unsafe {
+                RegEnumKeyExW(
+                    key,
+                    index,
+                    name.as_mut_ptr(),
+                    &mut len,
+                    std::ptr::null(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    &mut written,
+                )
+            };
+            if result != ERROR_SUCCESS {
+                return false;
+            }
+            index += 1;
+            let mut stop: u64 = u64::MAX;
+            let mut size = std::mem::size_of::<u64>() as u32;
+            let result = unsafe {
+                RegGetValueW(
+                    key,
+                    name.as_ptr(),
+                    value.as_ptr(),
+                    RRF_RT_REG_QWORD,
+                    std::ptr::null_mut(),
+                    &mut stop as *mut u64 as *mut _,
+                    &mut size,
+                )
+            };
+            if result == ERROR_SUCCESS && stop == 0 {
+                return true;
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+
+    // 有交流电源（Mains）时以其 online 为准；只有电池时视为电池供电；台式机两者都没有
+    pub fn on_battery() -> Option<bool> {
+        let mut mains = None;
+        let mut battery = false;
+        for entry in fs::read_dir("/sys/class/power_supply").ok()?.flatten() {
+            let path = entry.path();
+            let kind = fs::read_to_string(path.join("type")).unwrap_or_default();
+            match kind.trim() {
+                "Mains" => {
+                    let online = fs::read_to_string(path.join("online")).unwrap_or_default().trim() == "1";
+                    mains = Some(mains.unwrap_or(false) || online);
+                }
+                "Battery" => battery = true,
+                _ => {}
+            }
+        }
+        match mains {
+            Some(online) => Some(!online),
+            None if battery => Some(true),
+            None => None,
+        }
+    }
+
+    // ALSA 录音子设备（pcm*c）处于 RUNNING 状态即有程序正在录音
+    pub fn microphone_in_use() -> Option<bool> {
+        let cards = fs::read_dir("/proc/asound").ok()?;
+        for card in cards.flatten().filter(|c| c.file_name().to_string_lossy().starts_with("card")) {
+            let Ok(devices) = fs::read_dir(card.path()) else {
+                continue;
+            };
+            for device in devices.flatten() {
+                let name = device.file_name().to_string_lossy().to_string();
+                if !(name.starts_with("pcm") && name.ends_with('c')) {
+                    continue;
+                }
+                let Ok(subdevices) = fs::read_dir(device.path()) else {
+                    continue;
+                };
+                for subdevice in subdevices.flatten() {
+                    let status = fs::read_to_string(subdevice.path().join("status")).unwrap_or_default();
+                    if status.contains("state: RUNNING") {
+                        return Some(true);
+                    }
+                }
+            }
+        }
+        Some(false)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::process::Command;
+
+    pub fn on_battery() -> Option<bool> {
+        let output = Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+        let text = String::from_utf8_lossy(&output.stdout);
+        if text.contains("'Battery Power'") {
+            Some(true)
+        } else if text.contains("'AC Power'") {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    // 无法在不申请权限的情况下查询麦克风状态
+    pub fn microphone_in_use() -> Option<bool> {
+        None
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+mod platform {
+    pub fn on_battery() -> Option<bool> {
+        None
+    }
+
+    pub fn microphone_in_use() -> Option<bool> {
+        None
+    }
+}
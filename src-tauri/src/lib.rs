@@ -1,85 +1,956 @@
-mod config;
-mod serial;
-mod matrix;
+mod action_log;
+mod aggregate;
+mod cli;
+mod connection_log;
+mod deeplink;
+mod devices;
+mod discord;
+mod drift;
+mod events;
+mod game_state;
+mod ghosting;
+mod health;
+mod histogram;
+mod host_state;
+#[cfg_attr(not(feature = "history"), path = "history_disabled.rs")]
+mod history;
+mod simon;
+mod snapshot;
+mod soak;
 mod tray;
+mod variables;
+mod output;
+mod performance;
+mod profile;
+mod pwm;
+mod range_learning;
+mod webhooks;
+mod window_actions;
 
+// 协议处理位于 serial-joystick-core，按原有的模块路径引入
+use serial_joystick_core::{command, config, conformance, matrix, messages, permissions, response, scheduler, serial, transport, watch};
+#[cfg(feature = "flash")]
+use serial_joystick_core::bootloader;
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tauri::Manager;
+use tauri_plugin_deep_link::DeepLinkExt;
 use tokio::sync::Mutex;
-use crate::config::{MatrixConfig, SerialConfig};
-use crate::matrix::{DataParser, ParsedData};
-use crate::serial::SerialManager;
+#[cfg(feature = "flash")]
+use crate::bootloader::{BootloaderClient, FlashPlan};
+use crate::command::{info_command, led_command, pwm_command, report_rate_command};
+use crate::action_log::{ActionLog, ActionRecord};
+use crate::config::{is_variable_name, AxisSettings, GiveUpBehavior, HostState, LayoutConfig, MatrixConfig, SequenceStep, SerialConfig};
+use crate::conformance::ConformanceReport;
+use crate::connection_log::{ConnectionLog, ConnectionRecord};
+use crate::devices::{DeviceHandle, DeviceInfo, DeviceSet};
+use crate::game_state::{GameState, GameStatePoller};
+use crate::events::{AppEvent, DisconnectReason, EmitStatsReport, EventBus, ReconnectState};
+use crate::ghosting::{GhostDetector, GhostGroup};
+use crate::health::StartupReport;
+use crate::histogram::{AdcHistogram, AdcHistograms};
+use crate::history::{HistoryRecord, HistoryStore};
+use crate::matrix::{ConnectionState, DataParser, FrameError, KeyEdge, ParsedData};
+use crate::messages::{self as msg, AppError};
+use crate::cli::LaunchArgs;
+use crate::discord::DiscordPresence;
+use crate::drift::AxisCompensation;
+use crate::output::{ActionStats, AppRequest, OutputEngine};
+use crate::performance::{PerformanceMode, PerformanceStatus};
+use crate::permissions::PortPermission;
+use crate::aggregate::Aggregator;
+use crate::deeplink::LinkRequest;
+use crate::profile::ProfilePreview;
+use crate::pwm::PwmLimiter;
+use crate::range_learning::{RangeLearner, RangeProposal};
+use crate::response::DecodedResponse;
+use crate::scheduler::CronExpr;
+use crate::serial::{PortInfo, SerialManager};
+use crate::simon::{Picker, SimonEvent, SimonSummary};
+use crate::snapshot::DeviceSnapshot;
+use crate::soak::{SoakReport, SoakTest};
+use crate::variables::Variables;
+use crate::watch::WatchSet;
+use crate::webhooks::WebhookThrottle;
 
 // 应用状态
 struct AppState {
-    parser: Mutex<DataParser>,
+    parser: Arc<Mutex<DataParser>>,  // 主设备
+    devices: DeviceSet,  // 同时连接的其他设备，按 id 区分，各有自己的解析器和输出引擎
+    aggregator: std::sync::Mutex<Aggregator>,  // 参与聚合的设备最近的帧，不跨 await 持有
     config: Mutex<MatrixConfig>,
+    output: Arc<Mutex<OutputEngine>>,  // 主设备的输出引擎
+    history: Mutex<Option<HistoryStore>>,  // 数据库打开失败时为 None
+    watches: Mutex<WatchSet>,
+    soak: Mutex<Option<SoakTest>>,  // 正在进行的老化测试
+    adc_histograms: Mutex<AdcHistograms>,
+    range_learner: Mutex<RangeLearner>,
+    ghost_detector: Mutex<GhostDetector>,
+    simon_generation: AtomicU64,  // 每次开始/停止 Simon 模式时递增
+    simon_running: AtomicBool,  // 进行中时暂停绑定输出，避免测试按键触发动作
+    reconnect_generation: AtomicU64,  // 每次连接/断开/开始重连时递增，用于取消过期的重连任务
+    sequence_generation: AtomicU64,  // 取消动作序列时递增，正在执行的序列在下一步之前停止
+    led_targets: Mutex<HashMap<usize, bool>>,  // 最近一次请求的LED状态，总开关打开时据此恢复
+    pwm: Mutex<PwmLimiter>,
+    connection_log: Mutex<ConnectionLog>,
+    game_state: Mutex<GameState>,
+    host_state: Mutex<HostState>,
+    performance: Mutex<PerformanceMode>,
+    responses: Mutex<HashMap<String, DecodedResponse>>,  // 每种设备应答最近一次的解码结果
+    action_log: ActionLog,
+    variables: Variables,
+    webhooks_skipped: AtomicU64,  // 冷却期间丢弃的 Webhook 数量
+    startup_report: StartupReport,
+    flash_request: Mutex<Option<String>>,  // 等待前端确认下载的固件
+    profile_request: Mutex<Option<ProfilePreview>>,  // 等待前端确认导入的配置方案
+    link_request: Mutex<Option<LinkRequest>>,  // 等待前端确认的链接操作
+    bus: EventBus,
+}
+
+#[tauri::command]
+async fn list_serial_ports() -> Result<Vec<PortInfo>, AppError> {
+    Ok(SerialManager::list_port_info())
 }
 
+// 把友好名称（如 "USB-SERIAL CH340 (COM12)"）或 \\.\COM12 转换为端口名
 #[tauri::command]
-async fn list_serial_ports() -> Result<Vec<String>, String> {
-    Ok(SerialManager::list_ports())
+fn resolve_serial_port(name: String) -> Result<String, AppError> {
+    SerialManager::resolve_port(&name)
 }
 
+// 连接前检查当前用户能否访问串口（Linux 上常因不在 dialout/uucp 组而失败）
+#[tauri::command]
+fn check_port_permissions(port: String) -> PortPermission {
+    crate::permissions::check_port_permissions(&port)
+}
+
+// 打开配置中的串口，并恢复该设备的设置
+async fn open_matrix(
+    state: &AppState,
+    parser: &mut DataParser,
+    config: &MatrixConfig,
+) -> Result<(), AppError> {
+    let serial = SerialManager::new(SerialConfig {
+        port: SerialManager::resolve_port(&config.serial_matrix.port)?,
+        ..config.serial_matrix.clone()
+    }).await?;
+    
+    parser.connect(serial).await;
+    state.pwm.lock().await.reset();
+    state.game_state.lock().await.reset_leds();
+    state.bus.publish(AppEvent::Connection {
+        connected: true,
+        port: config.serial_matrix.port.clone(),
+        reason: None,
+    });
+    
+    // 恢复该设备上次设置的上报频率；未配置上报间隔命令字时不发送
+    let report_rate = config.report_rates.get(&config.serial_matrix.port)
+        .filter(|_| config.protocol.commands.report_interval.is_some());
+    if let Some(&hz) = report_rate {
+        let result = match report_rate_command(hz, parser.protocol()) {
+            Ok(command) => parser.send_command(&command).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to apply report rate {} Hz: {}", hz, e);
+        }
+    }
+    
+    // 查询设备信息，应答到达后按其中的能力字段更新协议配置
+    if config.protocol.commands.info.is_some() {
+        send_info_query(parser).await;
+    }
+    Ok(())
+}
+
+async fn send_info_query(parser: &DataParser) {
+    let result = match info_command(parser.protocol()) {
+        Ok(command) => parser.send_command(&command).await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to query device info: {}", e);
+    }
+}
+
+// 按设备信息应答中的按键、ADC、LED 数量和调光支持更新协议配置
+async fn apply_device_info(state: &AppState, info: &DecodedResponse) {
+    let current = state.config.lock().await.protocol.clone();
+    let protocol = crate::response::apply_capabilities(info, &current);
+    if protocol == current {
+        return;
+    }
+    let result = update_config(state, |config| {
+        Ok(MatrixConfig { protocol, ..config.clone() })
+    }).await;
+    match result {
+        Ok(()) => state.bus.publish(AppEvent::ConfigChanged { pointer: "/protocol".to_string() }),
+        Err(e) => eprintln!("Failed to apply device info: {}", e),
+    }
+}
+
+// 指定 device_id 时连接为额外的设备，不修改主设备的配置
 #[tauri::command]
 async fn connect_matrix(
+    app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
     port: String,
     baud_rate: u32,
-) -> Result<(), String> {
+    device_id: Option<String>,
+) -> Result<(), AppError> {
+    if let Some(id) = device_id {
+        // 先复制配置和主设备的暂停、演练状态，连接期间不持有这些锁
+        let config = state.config.lock().await.clone();
+        let mut output = OutputEngine::for_device(&id, &config, state.action_log.clone(), state.variables.clone());
+        {
+            let primary = state.output.lock().await;
+            output.set_paused(primary.is_paused());
+            output.set_rehearsal(primary.is_rehearsal());
+        }
+        return state.devices.connect(&app, id, port, baud_rate, &config, output).await;
+    }
+    // 手动连接时取消正在进行的自动重连
+    state.reconnect_generation.fetch_add(1, Ordering::SeqCst);
     let mut parser = state.parser.lock().await;
     let mut config = state.config.lock().await;
     
     // 更新配置
-    config.serial_matrix.port = port.clone();
+    config.serial_matrix.port = port;
     config.serial_matrix.baud_rate = baud_rate;
     config.save();
     
     // 连接串口
-    let serial = SerialManager::new(SerialConfig {
-        port,
-        baud_rate,
-        data_bits: 8,
-        stop_bits: 1,
-        parity: "None".to_string(),
-    }).await?;
-    
-    parser.connect(serial).await;
+    open_matrix(&state, &mut parser, &config).await
+}
+
+const FORCE_RECONNECT_ATTEMPTS: u32 = 10;
+const FORCE_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+// 串口被占用时反复尝试打开，等待其他程序释放；其他错误立即返回
+#[tauri::command]
+async fn force_reconnect(
+    state: tauri::State<'_, AppState>,
+    attempts: Option<u32>,
+) -> Result<(), AppError> {
+    let generation = state.reconnect_generation.fetch_add(1, Ordering::SeqCst) + 1;
+    let attempts = attempts.unwrap_or(FORCE_RECONNECT_ATTEMPTS).max(1);
+    {
+        let mut parser = state.parser.lock().await;
+        if parser.is_connected().await {
+            parser.disconnect().await;
+            let port = state.config.lock().await.serial_matrix.port.clone();
+            state.bus.publish(AppEvent::Connection {
+                connected: false,
+                port,
+                reason: Some(DisconnectReason::ForceReconnect),
+            });
+        }
+    }
     
+    // 每次尝试时重新锁定解析器，等待期间不持有锁，其他命令和读取任务照常运行
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let result = {
+            let mut parser = state.parser.lock().await;
+            let config = state.config.lock().await.clone();
+            open_matrix(&state, &mut parser, &config).await
+        };
+        match result {
+            Err(e) if e.code == msg::SERIAL_PORT_BUSY && attempt < attempts => {
+                println!("Port busy, retrying ({}/{})", attempt, attempts);
+                tokio::time::sleep(FORCE_RECONNECT_DELAY).await;
+                // 等待期间用户手动连接或断开时停止重试
+                if state.reconnect_generation.load(Ordering::SeqCst) != generation {
+                    return Err(e);
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+const ACTION_LOG_LIMIT: usize = 100;
+
+#[tauri::command]
+async fn get_variables(
+    state: tauri::State<'_, AppState>,
+) -> Result<BTreeMap<String, i64>, AppError> {
+    Ok(state.variables.snapshot())
+}
+
+#[tauri::command]
+async fn set_variable(
+    state: tauri::State<'_, AppState>,
+    name: String,
+    value: i64,
+) -> Result<(), AppError> {
+    if !is_variable_name(&name) {
+        return Err(config_invalid(format!("变量名无效: {}", name)));
+    }
+    state.variables.set(&name, value);
     Ok(())
 }
 
+// 最近执行的动作及耗时和结果，新的在前
+#[tauri::command]
+async fn get_action_log(
+    state: tauri::State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<ActionRecord>, AppError> {
+    Ok(state.action_log.recent(limit.unwrap_or(ACTION_LOG_LIMIT)))
+}
+
+const CONNECTION_HISTORY_LIMIT: usize = 50;
+
+// 最近的连接记录，新的在前
+#[tauri::command]
+async fn get_connection_history(
+    state: tauri::State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<ConnectionRecord>, AppError> {
+    let limit = limit.unwrap_or(CONNECTION_HISTORY_LIMIT);
+    Ok(state.connection_log.lock().await.recent(limit))
+}
+
+#[tauri::command]
+async fn is_matrix_connected(
+    state: tauri::State<'_, AppState>,
+    device_id: Option<String>,
+) -> Result<bool, AppError> {
+    if let Some(id) = device_id {
+        return state.devices.is_connected(&id).await;
+    }
+    Ok(state.parser.lock().await.is_connected().await)
+}
+
+// 同时连接的其他设备
+#[tauri::command]
+async fn list_matrix_devices(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<DeviceInfo>, AppError> {
+    Ok(state.devices.list().await)
+}
+
 #[tauri::command]
 async fn disconnect_matrix(
     state: tauri::State<'_, AppState>,
-) -> Result<(), String> {
+    device_id: Option<String>,
+) -> Result<(), AppError> {
+    if let Some(id) = device_id {
+        state.aggregator.lock().unwrap().remove(&id);
+        return state.devices.disconnect(&id, &state.bus).await;
+    }
+    state.reconnect_generation.fetch_add(1, Ordering::SeqCst);
     let mut parser = state.parser.lock().await;
     parser.disconnect().await;
+    
+    let port = state.config.lock().await.serial_matrix.port.clone();
+    state.bus.publish(AppEvent::Connection { connected: false, port, reason: Some(DisconnectReason::User) });
+    Ok(())
+}
+
+// 连接意外断开后按重连策略重试；每次重试前重新读取策略，修改即时生效
+fn spawn_reconnect(app: tauri::AppHandle) {
+    let generation = app.state::<AppState>().reconnect_generation.fetch_add(1, Ordering::SeqCst) + 1;
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let mut attempt = 0;
+        loop {
+            let (policy, port) = {
+                let config = state.config.lock().await;
+                (config.retry.clone(), config.serial_matrix.port.clone())
+            };
+            if !policy.enabled {
+                return;
+            }
+            
+            attempt += 1;
+            let exhausted = attempt > policy.max_attempts;
+            if exhausted && policy.give_up == GiveUpBehavior::StayDisconnected {
+                state.bus.publish(AppEvent::Reconnect(ReconnectState::GaveUp {
+                    port,
+                    attempts: attempt - 1,
+                }));
+                return;
+            }
+            let delay = if exhausted {
+                Duration::from_millis(policy.max_delay_ms)
+            } else {
+                policy.delay(attempt)
+            };
+            state.bus.publish(AppEvent::Reconnect(ReconnectState::Waiting {
+                port: port.clone(),
+                attempt,
+                max_attempts: policy.max_attempts,
+                next_retry_ms: delay.as_millis() as u64,
+            }));
+            tokio::time::sleep(delay).await;
+            
+            let mut parser = state.parser.lock().await;
+            // 等待期间用户手动连接或断开时停止重连
+            if state.reconnect_generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            let config = state.config.lock().await.clone();
+            match open_matrix(&state, &mut parser, &config).await {
+                Ok(()) => {
+                    state.bus.publish(AppEvent::Reconnect(ReconnectState::Reconnected { port, attempt }));
+                    return;
+                }
+                Err(e) => eprintln!("Reconnect attempt {} failed: {}", attempt, e),
+            }
+        }
+    });
+}
+
+fn show_main_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn toggle_main_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        if window.is_visible().unwrap_or(false) {
+            let _ = window.hide();
+        } else {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+    }
+}
+
+async fn send_led(parser: &DataParser, index: usize, on: bool, brightness: u8) {
+    if let Err(e) = parser.send_command(&led_command(index, on, brightness, parser.protocol())).await {
+        eprintln!("Failed to set LED {}: {}", index + 1, e);
+    }
+}
+
+// 主设备的PWM命令经过限速后发送，间隔内的值留到之后由 flush_pwm 发送；
+// 其他设备没有跟随ADC的PWM来源，只有动作和界面设置的PWM，直接发送
+async fn send_pwm(state: &AppState, device: Option<&str>, parser: &DataParser, channel: usize, duty: u8) {
+    let due = match device {
+        None => state.pwm.lock().await.request(channel, duty, Instant::now()),
+        Some(_) => Some(duty),
+    };
+    if let Some(duty) = due {
+        let result = match pwm_command(channel, duty, parser.protocol()) {
+            Ok(command) => parser.send_command(&command).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to set PWM {}: {}", channel + 1, e);
+        }
+    }
+}
+
+async fn flush_pwm(state: &AppState, parser: &DataParser) {
+    let due = state.pwm.lock().await.take_due(Instant::now());
+    for (channel, duty) in due {
+        let result = match pwm_command(channel, duty, parser.protocol()) {
+            Ok(command) => parser.send_command(&command).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to set PWM {}: {}", channel + 1, e);
+        }
+    }
+}
+
+// 由界面的滑块等直接设置PWM输出
+#[tauri::command]
+async fn set_pwm(
+    state: tauri::State<'_, AppState>,
+    channel: usize,
+    duty: u8,
+    device_id: Option<String>,
+) -> Result<(), AppError> {
+    let device = device_handle(&state, device_id).await?;
+    let parser = device.parser.lock().await;
+    let channels = state.config.lock().await.pwm.channels;
+    if channel >= channels {
+        return Err(format!("PWM通道 {} 不存在", channel + 1).into());
+    }
+    send_pwm(&state, device.id.as_deref(), &parser, channel, duty).await;
+    Ok(())
+}
+
+// 按 id 找到设备，未指定时为主设备
+async fn device_handle(state: &AppState, device_id: Option<String>) -> Result<DeviceHandle, AppError> {
+    match device_id {
+        Some(id) => state.devices.handle(&id),
+        None => Ok(DeviceHandle {
+            id: None,
+            port: state.config.lock().await.serial_matrix.port.clone(),
+            parser: Arc::clone(&state.parser),
+            output: Arc::clone(&state.output),
+        }),
+    }
+}
+
+// 游戏状态各字段的最新值，键为 "来源.字段"
+#[tauri::command]
+async fn get_game_state(
+    state: tauri::State<'_, AppState>,
+) -> Result<BTreeMap<String, serde_json::Value>, AppError> {
+    Ok(state.game_state.lock().await.values())
+}
+
+// 每种设备应答最近一次的解码结果
+#[tauri::command]
+async fn get_device_responses(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<DecodedResponse>, AppError> {
+    let mut responses: Vec<DecodedResponse> = state.responses.lock().await.values().cloned().collect();
+    responses.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(responses)
+}
+
+// 按名称发送配置中的自定义命令模板，返回实际发送的字节
+#[tauri::command]
+async fn send_named_command(
+    state: tauri::State<'_, AppState>,
+    name: String,
+    params: Option<HashMap<String, i64>>,
+    device_id: Option<String>,
+) -> Result<Vec<u8>, AppError> {
+    let device = device_handle(&state, device_id).await?;
+    let parser = device.parser.lock().await;
+    let pattern = {
+        let config = state.config.lock().await;
+        config.commands.iter()
+            .find(|template| template.name == name)
+            .map(|template| template.pattern.clone())
+            .ok_or_else(|| format!("命令 {} 不存在", name))?
+    };
+    let bytes = command::render_template(&pattern, &params.unwrap_or_default())?;
+    parser.send_raw(&bytes).await?;
+    Ok(bytes)
+}
+
+// 切换LED总开关：关闭时熄灭所有LED，重新打开时恢复之前请求的状态
+async fn set_leds_enabled(state: &AppState, enabled: bool) {
+    let parser = state.parser.lock().await;
+    let brightness = {
+        let mut config = state.config.lock().await;
+        config.leds_enabled = enabled;
+        config.save();
+        config.led_brightness
+    };
+    let targets = state.led_targets.lock().await.clone();
+    for (index, on) in targets {
+        send_led(&parser, index, on && enabled, brightness).await;
+    }
+}
+
+// 开启或关闭性能模式，成功后写入配置
+#[tauri::command]
+async fn set_performance_mode(
+    state: tauri::State<'_, AppState>,
+    enabled: bool,
+) -> Result<PerformanceStatus, AppError> {
+    let mut performance = state.performance.lock().await;
+    performance.set_enabled(enabled)?;
+    update_config(&state, |config| {
+        let mut config = config.clone();
+        config.performance_mode = enabled;
+        Ok(config)
+    }).await?;
+    state.bus.publish(AppEvent::ConfigChanged { pointer: "/performance_mode".to_string() });
+    Ok(performance.status().await)
+}
+
+// 性能模式的状态，并测量当前的计时器精度
+#[tauri::command]
+async fn get_performance_status(
+    state: tauri::State<'_, AppState>,
+) -> Result<PerformanceStatus, AppError> {
+    Ok(state.performance.lock().await.status().await)
+}
+
+// 暂停或恢复输出，同步托盘菜单并通知前端
+async fn set_paused<R: tauri::Runtime>(app: &tauri::AppHandle<R>, state: &AppState, paused: bool) {
+    state.output.lock().await.set_paused(paused);
+    state.devices.set_paused(paused).await;
+    crate::tray::set_paused_checked(app, paused);
+    state.bus.publish(AppEvent::PauseChanged { paused });
+}
+
+#[tauri::command]
+async fn pause_processing(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    set_paused(&app, &state, true).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn resume_processing(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    set_paused(&app, &state, false).await;
+    Ok(())
+}
+
+// 演练模式：绑定和规则照常判断，动作只作为 rehearsal-action 事件报告，不实际执行
+#[tauri::command]
+async fn set_rehearsal_mode(
+    state: tauri::State<'_, AppState>,
+    enabled: bool,
+) -> Result<(), AppError> {
+    state.output.lock().await.set_rehearsal(enabled);
+    state.devices.set_rehearsal(enabled).await;
     Ok(())
 }
 
+#[tauri::command]
+async fn is_rehearsal_mode(
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, AppError> {
+    Ok(state.output.lock().await.is_rehearsal())
+}
+
+// 执行的动作数量，以及因冷却和限速跳过的数量
+#[tauri::command]
+async fn get_action_stats(
+    state: tauri::State<'_, AppState>,
+) -> Result<ActionStats, AppError> {
+    let mut stats = state.output.lock().await.action_stats();
+    stats.webhooks_skipped = state.webhooks_skipped.load(Ordering::Relaxed);
+    Ok(stats)
+}
+
+#[tauri::command]
+async fn is_processing_paused(
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, AppError> {
+    Ok(state.output.lock().await.is_paused())
+}
+
+// 处理输出引擎产生的应用层请求；device 为产生请求的设备（主设备为 None），
+// LED、PWM 和设备命令发往 parser 对应的设备
+async fn handle_app_requests(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    device: Option<&str>,
+    parser: &DataParser,
+    requests: Vec<AppRequest>,
+) {
+    for request in requests {
+        match request {
+            AppRequest::ShowWindow => show_main_window(app),
+            AppRequest::ToggleWindow => toggle_main_window(app),
+            AppRequest::SetLed { index, on } => {
+                // 总开关重新打开时只恢复主设备的LED
+                if device.is_none() {
+                    state.led_targets.lock().await.insert(index, on);
+                }
+                let (enabled, brightness) = {
+                    let config = state.config.lock().await;
+                    (config.leds_enabled, config.led_brightness)
+                };
+                send_led(parser, index, on && enabled, brightness).await;
+            }
+            AppRequest::SetPwm { channel, duty } => send_pwm(state, device, parser, channel, duty).await,
+            AppRequest::SendCommand(bytes) => {
+                if let Err(e) = parser.send_raw(&bytes).await {
+                    eprintln!("Failed to send command: {}", e);
+                }
+            }
+            AppRequest::SaveMacro(recorded) => {
+                let mut config = state.config.lock().await;
+                config.macros.push(recorded);
+                config.save();
+            }
+            AppRequest::BankChanged { index, name } => {
+                state.bus.publish(AppEvent::BankChanged { index, name });
+            }
+            AppRequest::PauseChanged(paused) => {
+                state.devices.set_paused(paused).await;
+                crate::tray::set_paused_checked(app, paused);
+                state.bus.publish(AppEvent::PauseChanged { paused });
+            }
+            AppRequest::Rehearsed(action) => state.bus.publish(AppEvent::Rehearsal(action)),
+            AppRequest::RunSequence { source, steps } => {
+                spawn_sequence(app.clone(), device.map(str::to_string), source, steps);
+            }
+        }
+    }
+}
+
+// 按顺序执行动作序列，每步等待后检查条件；条件不满足、序列被取消或设备已断开时停止。
+// 序列在触发它的设备上执行，按键条件也按该设备的输入判断
+fn spawn_sequence(app: tauri::AppHandle, device: Option<String>, source: String, steps: Vec<SequenceStep>) {
+    let generation = app.state::<AppState>().sequence_generation.load(Ordering::SeqCst);
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        for (i, step) in steps.iter().enumerate() {
+            tokio::time::sleep(Duration::from_millis(step.delay_ms)).await;
+            if state.sequence_generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            let Ok(target) = device_handle(&state, device.clone()).await else {
+                return;
+            };
+            if let Some(condition) = &step.condition {
+                let data = target.parser.lock().await.get_parsed_data().await;
+                let host = state.host_state.lock().await.clone();
+                if !condition.is_met(&data.keys, &host, chrono::Local::now().time()) {
+                    return;
+                }
+            }
+            let step_source = format!("{} step {}", source, i + 1);
+            let requests = target.output.lock().await.run_action(&step_source, &step.action);
+            let parser = target.parser.lock().await;
+            handle_app_requests(&app, &state, device.as_deref(), &parser, requests).await;
+        }
+    });
+}
+
+// 停止所有正在执行的动作序列
+#[tauri::command]
+async fn cancel_sequences(
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    state.sequence_generation.fetch_add(1, Ordering::SeqCst);
+    Ok(())
+}
+
+// 串口由后台任务持续读取，此命令只返回最新的数据；未连接时返回错误
 #[tauri::command]
 async fn read_and_parse_data(
     state: tauri::State<'_, AppState>,
-) -> Result<ParsedData, String> {
+) -> Result<Arc<ParsedData>, AppError> {
+    let parser = state.parser.lock().await;
+    if !parser.is_connected().await {
+        return Err(AppError::new(msg::SERIAL_NOT_CONNECTED, "串口未连接"));
+    }
+    Ok(parser.get_parsed_data().await)
+}
+
+const READ_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+// 后台读取任务：连接期间持续读取串口并处理，界面卡顿时也不会丢帧；
+// 未连接时等待连接状态变化，不占用CPU
+fn spawn_serial_reader(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let mut connection = state.parser.lock().await.subscribe_connection();
+        loop {
+            if *connection.borrow_and_update() == ConnectionState::Disconnected {
+                if connection.changed().await.is_err() {
+                    return;
+                }
+                continue;
+            }
+            match read_once(&app, &state).await {
+                Ok(()) => tokio::task::yield_now().await,
+                Err(e) => {
+                    if e.code != msg::SERIAL_NOT_CONNECTED {
+                        eprintln!("Failed to read serial data: {}", e);
+                    }
+                    tokio::time::sleep(READ_ERROR_DELAY).await;
+                }
+            }
+        }
+    });
+}
+
+// 读取并处理一次数据；等待串口数据时不持有解析器的锁，其他命令可以继续使用解析器
+async fn read_once(app: &tauri::AppHandle, state: &AppState) -> Result<(), AppError> {
+    let pending = state.parser.lock().await.start_read().await?;
+    let read = pending.read().await;
     let mut parser = state.parser.lock().await;
-    parser.read_and_parse().await?;
+    let outcome = match parser.finish_read(read).await {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            if e.code == msg::SERIAL_CONNECTION_LOST {
+                let port = state.config.lock().await.serial_matrix.port.clone();
+                state.bus.publish(AppEvent::Connection { connected: false, port, reason: Some(DisconnectReason::Lost) });
+                spawn_reconnect(app.clone());
+            }
+            return Err(e);
+        }
+    };
     let data = parser.get_parsed_data().await;
-    Ok(data)
+    
+    // 新帧、按键变化、校验失败、波特率诊断和设备应答发布到事件总线
+    for error in outcome.frame_errors {
+        state.bus.publish(AppEvent::FrameError(error));
+    }
+    let mut info = None;
+    if !outcome.responses.is_empty() {
+        let info_response = parser.protocol().commands.info_response.clone();
+        let mut latest = state.responses.lock().await;
+        for response in outcome.responses {
+            if info_response.as_ref() == Some(&response.name) {
+                info = Some(response.clone());
+            }
+            latest.insert(response.name.clone(), response.clone());
+            state.bus.publish(AppEvent::DeviceResponse(response));
+        }
+    }
+    if let Some(diagnosis) = outcome.baud_diagnosis {
+        state.bus.publish(AppEvent::WrongBaud(diagnosis));
+    }
+    process_frame(app, state, &parser, data, outcome.key_edges).await;
+    // 更新配置时要锁定解析器，先释放
+    drop(parser);
+    if let Some(info) = info {
+        apply_device_info(state, &info).await;
+    }
+    Ok(())
+}
+
+// 发布新帧和按键变化，并根据绑定产生系统输出；edges 为 None 表示没有新的有效帧
+async fn process_frame(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    parser: &DataParser,
+    data: Arc<ParsedData>,
+    edges: Option<Vec<KeyEdge>>,
+) {
+    // 启用聚合时绑定处理合并后的数据
+    let merged = state.aggregator.lock().unwrap().merge(&data).map(Arc::new);
+    if let Some(edges) = edges {
+        for edge in edges {
+            state.bus.publish(AppEvent::KeyEdge(edge));
+        }
+        state.bus.publish(AppEvent::Frame(Arc::clone(&data)));
+        if let Some(merged) = &merged {
+            state.bus.publish(AppEvent::AggregateFrame(Arc::clone(merged)));
+        }
+    }
+    
+    if !state.simon_running.load(Ordering::SeqCst) {
+        let input = merged.unwrap_or(data);
+        let requests = state.output.lock().await.process(&input);
+        handle_app_requests(app, state, None, parser, requests).await;
+    }
+    flush_pwm(state, parser).await;
+}
+
+// 参与聚合的设备收到新帧后，与主设备当前的数据合并，按主设备的绑定处理；
+// 调用前必须释放该设备的解析器
+async fn process_aggregate_frame(app: &tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    let parser = state.parser.lock().await;
+    let data = parser.get_parsed_data().await;
+    let Some(merged) = state.aggregator.lock().unwrap().merge(&data).map(Arc::new) else {
+        return;
+    };
+    state.bus.publish(AppEvent::AggregateFrame(Arc::clone(&merged)));
+    if !state.simon_running.load(Ordering::SeqCst) {
+        let requests = state.output.lock().await.process(&merged);
+        handle_app_requests(app, &state, None, &parser, requests).await;
+    }
+    flush_pwm(&state, &parser).await;
+}
+
+// 其他设备的新帧：按该设备的绑定产生输出，LED、PWM 和设备命令发往该设备
+async fn process_device_frame(
+    app: &tauri::AppHandle,
+    id: &str,
+    output: &Mutex<OutputEngine>,
+    parser: &DataParser,
+    data: &ParsedData,
+) {
+    let state = app.state::<AppState>();
+    let requests = output.lock().await.process(data);
+    handle_app_requests(app, &state, Some(id), parser, requests).await;
+}
+
+// 模拟按键按下/松开，经过与真实数据相同的处理，便于不接设备测试绑定、规则和LED
+#[tauri::command]
+async fn simulate_key(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    index: usize,
+    pressed: bool,
+) -> Result<(), AppError> {
+    let mut parser = state.parser.lock().await;
+    let key_count = parser.protocol().key_count;
+    if index >= key_count {
+        return Err(format!("按键编号 {} 超出范围（共 {} 个）", index, key_count).into());
+    }
+    let edges = parser.inject(|data| data.keys[index] = pressed).await;
+    let data = parser.get_parsed_data().await;
+    process_frame(&app, &state, &parser, data, Some(edges)).await;
+    Ok(())
+}
+
+// 模拟ADC通道的数值，处理方式同 simulate_key
+#[tauri::command]
+async fn simulate_adc(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    channel: usize,
+    value: u8,
+) -> Result<(), AppError> {
+    let mut parser = state.parser.lock().await;
+    let adc_count = parser.protocol().adc_count;
+    if channel >= adc_count {
+        return Err(format!("ADC通道 {} 超出范围（共 {} 个）", channel, adc_count).into());
+    }
+    let edges = parser.inject(|data| data.adc[channel] = value).await;
+    let data = parser.get_parsed_data().await;
+    process_frame(&app, &state, &parser, data, Some(edges)).await;
+    Ok(())
+}
+
+// 合并后的逻辑设备的数据，未启用聚合时为 None
+#[tauri::command]
+async fn get_aggregate_data(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<ParsedData>, AppError> {
+    let data = state.parser.lock().await.get_parsed_data().await;
+    let merged = state.aggregator.lock().unwrap().merge(&data);
+    Ok(merged)
 }
 
 #[tauri::command]
 async fn get_parsed_data(
     state: tauri::State<'_, AppState>,
-) -> Result<ParsedData, String> {
+    device_id: Option<String>,
+) -> Result<Arc<ParsedData>, AppError> {
+    if let Some(id) = device_id {
+        return state.devices.parsed_data(&id).await;
+    }
     let parser = state.parser.lock().await;
     let data = parser.get_parsed_data().await;
     Ok(data)
 }
 
+#[tauri::command]
+async fn get_recent_frame_errors(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<FrameError>, AppError> {
+    let parser = state.parser.lock().await;
+    Ok(parser.get_frame_errors().await)
+}
+
+#[tauri::command]
+async fn run_protocol_conformance(
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<ConformanceReport, AppError> {
+    let parser = state.parser.lock().await;
+    Ok(crate::conformance::run(&parser, Path::new(&path))?)
+}
+
 #[tauri::command]
 async fn get_config(
     state: tauri::State<'_, AppState>,
-) -> Result<MatrixConfig, String> {
+) -> Result<MatrixConfig, AppError> {
     let config = state.config.lock().await;
     Ok(config.clone())
 }
@@ -88,10 +959,495 @@ async fn get_config(
 async fn save_config(
     state: tauri::State<'_, AppState>,
     new_config: MatrixConfig,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
+    apply_config(&state, new_config).await?;
+    state.bus.publish(AppEvent::ConfigChanged { pointer: String::new() });
+    Ok(())
+}
+
+// 配置和配置方案文件的 JSON Schema，前端据此生成表单，外部编辑器可用于校验
+#[tauri::command]
+fn get_config_schema() -> serde_json::Value {
+    serde_json::json!({
+        "config": schemars::schema_for!(MatrixConfig),
+        "profile": schemars::schema_for!(DeviceSnapshot),
+    })
+}
+
+// 只修改配置中 JSON Pointer 指向的部分（如 "/labels/keys/3"），
+// 不同界面各自修改不同部分时不会互相覆盖
+#[tauri::command]
+async fn patch_config(
+    state: tauri::State<'_, AppState>,
+    pointer: String,
+    value: serde_json::Value,
+) -> Result<(), AppError> {
+    update_config(&state, |config| {
+        let mut document = serde_json::to_value(config).map_err(|e| e.to_string())?;
+        set_pointer(&mut document, &pointer, value)?;
+        serde_json::from_value(document).map_err(|e| config_invalid(e.to_string()))
+    }).await?;
+    state.bus.publish(AppEvent::ConfigChanged { pointer });
+    Ok(())
+}
+
+// 设置 JSON Pointer 指向的值；最后一级不存在时，若上一级是对象则新增该字段
+fn set_pointer(document: &mut serde_json::Value, pointer: &str, value: serde_json::Value) -> Result<(), AppError> {
+    let not_found = || AppError::new(msg::CONFIG_PATH_NOT_FOUND, format!("配置中不存在 {}", pointer))
+        .param("pointer", pointer);
+    if let Some(target) = document.pointer_mut(pointer) {
+        *target = value;
+        return Ok(());
+    }
+    let (parent, key) = pointer.rsplit_once('/').ok_or_else(not_found)?;
+    let key = key.replace("~1", "/").replace("~0", "~");
+    match document.pointer_mut(parent) {
+        Some(serde_json::Value::Object(object)) => {
+            object.insert(key, value);
+            Ok(())
+        }
+        _ => Err(not_found()),
+    }
+}
+
+fn config_invalid(detail: String) -> AppError {
+    AppError::new(msg::CONFIG_INVALID, format!("配置无效: {}", detail)).param("detail", detail)
+}
+
+// 校验并保存新配置，同时更新各子系统
+async fn apply_config(state: &AppState, new_config: MatrixConfig) -> Result<(), AppError> {
+    update_config(state, |_| Ok(new_config)).await
+}
+
+// 在持有配置锁期间由当前配置生成新配置，再校验保存，保证读取和写回之间不会被其他修改插入
+async fn update_config<F>(state: &AppState, change: F) -> Result<(), AppError>
+where
+    F: FnOnce(&MatrixConfig) -> Result<MatrixConfig, AppError>,
+{
+    let mut parser = state.parser.lock().await;
     let mut config = state.config.lock().await;
+    let mut new_config = change(&config)?;
+    
+    // 协议数量变化而名称/布局未改动时，自动调整数量；否则数量必须与协议一致
+    if new_config.protocol != config.protocol {
+        if new_config.labels == config.labels {
+            new_config.labels.resize(&new_config.protocol);
+        }
+        if new_config.layout == config.layout {
+            new_config.layout.resize(&new_config.protocol);
+        }
+    }
+    // 所有保存配置的命令都经过这里，引入新问题的修改不会被保存；
+    // 加载时已有的问题不阻止修改其他部分，由启动检查报告
+    let existing = config.validate();
+    let problems: Vec<String> = new_config.validate()
+        .into_iter()
+        .filter(|problem| !existing.contains(problem))
+        .collect();
+    if !problems.is_empty() {
+        return Err(config_invalid(problems.join("; ")));
+    }
+    
     *config = new_config;
     config.save();
+    parser.update_config(config.clone()).await;
+    state.output.lock().await.update_config(&config);
+    state.pwm.lock().await.update_config(&config.pwm);
+    state.range_learner.lock().await.update_config(&config.range_learning, &config.axis_settings);
+    state.ghost_detector.lock().await.update_config(&config.ghosting);
+    *state.watches.lock().await = WatchSet::new(&config.watches);
+    if let Some(history) = state.history.lock().await.as_mut() {
+        history.update_config(&config.history);
+    }
+    state.aggregator.lock().unwrap().update_config(&config.aggregate);
+    // 设备处理帧时先锁设备解析器再读配置，这里先释放配置锁再更新设备，避免互相等待
+    let snapshot = config.clone();
+    drop(config);
+    drop(parser);
+    state.devices.update_config(&snapshot).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn export_device_snapshot(
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<(), AppError> {
+    let config = state.config.lock().await.clone();
+    let info = match &config.protocol.commands.info_response {
+        Some(name) => state.responses.lock().await.get(name).cloned(),
+        None => None,
+    };
+    Ok(DeviceSnapshot::capture(&config, info.as_ref()).write(Path::new(&path))?)
+}
+
+#[tauri::command]
+async fn apply_device_snapshot(
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<(), AppError> {
+    let snapshot = DeviceSnapshot::read(Path::new(&path))?;
+    apply_snapshot(&state, snapshot).await
+}
+
+async fn apply_snapshot(state: &AppState, snapshot: DeviceSnapshot) -> Result<(), AppError> {
+    let report_rate = snapshot.report_rate;
+    let current = state.config.lock().await.clone();
+    // 固件版本不同时仍然应用，只提示
+    if let (Some(expected), Some(name)) = (snapshot.firmware_version, &current.protocol.commands.info_response) {
+        let version = state.responses.lock().await.get(name)
+            .and_then(|info| info.fields.get(crate::snapshot::FIRMWARE_VERSION_FIELD))
+            .map(|&version| version as u32);
+        if let Some(version) = version.filter(|&version| version != expected) {
+            eprintln!("Snapshot was taken on firmware {}, device reports {}", expected, version);
+        }
+    }
+    let new_config = snapshot.into_config_for(&current);
+    apply_config(state, new_config).await?;
+    state.bus.publish(AppEvent::ConfigChanged { pointer: String::new() });
+    
+    // 已连接时立即下发设备设置，否则在下次连接时应用
+    if let Some(hz) = report_rate {
+        let parser = state.parser.lock().await;
+        if parser.protocol().commands.report_interval.is_some() {
+            let result = match report_rate_command(hz, parser.protocol()) {
+                Ok(command) => parser.send_command(&command).await.map(|_| ()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                eprintln!("Report rate will be applied on next connect: {}", e);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_layout(
+    state: tauri::State<'_, AppState>,
+) -> Result<LayoutConfig, AppError> {
+    Ok(state.config.lock().await.layout.clone())
+}
+
+#[tauri::command]
+async fn set_layout(
+    state: tauri::State<'_, AppState>,
+    layout: LayoutConfig,
+) -> Result<(), AppError> {
+    update_config(&state, |config| {
+        let mut config = config.clone();
+        config.layout = layout;
+        Ok(config)
+    }).await?;
+    state.bus.publish(AppEvent::ConfigChanged { pointer: "/layout".to_string() });
+    Ok(())
+}
+
+#[tauri::command]
+async fn query_history(
+    state: tauri::State<'_, AppState>,
+    kind: String,
+    from: i64,
+    to: i64,
+) -> Result<Vec<HistoryRecord>, AppError> {
+    match state.history.lock().await.as_ref() {
+        Some(history) => Ok(history.query(&kind, from, to)?),
+        None => Err(AppError::new(msg::HISTORY_UNAVAILABLE, "历史数据库不可用")),
+    }
+}
+
+#[tauri::command]
+async fn take_flash_request(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<String>, AppError> {
+    Ok(state.flash_request.lock().await.take())
+}
+
+#[tauri::command]
+async fn take_profile_request(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<ProfilePreview>, AppError> {
+    Ok(state.profile_request.lock().await.take())
+}
+
+#[tauri::command]
+async fn take_link_request(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<LinkRequest>, AppError> {
+    Ok(state.link_request.lock().await.take())
+}
+
+// 切换到已保存的配置方案，由前端确认链接后调用
+#[tauri::command]
+async fn load_profile(
+    state: tauri::State<'_, AppState>,
+    name: String,
+) -> Result<(), AppError> {
+    let snapshot = crate::profile::load_profile(&name)?;
+    apply_snapshot(&state, snapshot).await
+}
+
+// 导入打开的配置方案文件并立即切换到该方案
+#[tauri::command]
+async fn import_profile(
+    state: tauri::State<'_, AppState>,
+    file: String,
+) -> Result<String, AppError> {
+    let (name, snapshot) = crate::profile::import(Path::new(&file))?;
+    apply_snapshot(&state, snapshot).await?;
+    Ok(name)
+}
+
+#[tauri::command]
+async fn get_startup_report(
+    state: tauri::State<'_, AppState>,
+) -> Result<StartupReport, AppError> {
+    Ok(state.startup_report.clone())
+}
+
+#[tauri::command]
+async fn start_soak_test(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    duration_secs: Option<u64>,
+) -> Result<(), AppError> {
+    let soak_config = state.config.lock().await.soak.clone();
+    let duration = Duration::from_secs(duration_secs.unwrap_or(soak_config.duration_secs));
+    let mut soak = state.soak.lock().await;
+    if soak.is_some() {
+        return Err(AppError::new(msg::SOAK_RUNNING, "老化测试正在进行"));
+    }
+    let id = soak_id();
+    *soak = Some(SoakTest::new(id, &soak_config, duration));
+    
+    // 到时自动结束并发布报告
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(duration).await;
+        let state = app.state::<AppState>();
+        let mut soak = state.soak.lock().await;
+        if soak.as_ref().is_some_and(|test| test.id == id) {
+            if let Some(test) = soak.take() {
+                state.bus.publish(AppEvent::SoakReport(test.report(true)));
+            }
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_soak_test(
+    state: tauri::State<'_, AppState>,
+) -> Result<SoakReport, AppError> {
+    let test = state
+        .soak
+        .lock()
+        .await
+        .take()
+        .ok_or_else(|| AppError::new(msg::SOAK_NOT_RUNNING, "没有正在进行的老化测试"))?;
+    let report = test.report(true);
+    state.bus.publish(AppEvent::SoakReport(report.clone()));
+    Ok(report)
+}
+
+#[tauri::command]
+async fn get_event_stats(
+    state: tauri::State<'_, AppState>,
+) -> Result<EmitStatsReport, AppError> {
+    Ok(state.bus.emit_stats())
+}
+
+#[tauri::command]
+async fn get_axis_compensation(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<AxisCompensation>, AppError> {
+    Ok(state.output.lock().await.axis_compensation())
+}
+
+#[tauri::command]
+async fn get_soak_status(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<SoakReport>, AppError> {
+    Ok(state.soak.lock().await.as_ref().map(|test| test.report(false)))
+}
+
+// 某个ADC通道的读数分布，以及 min/max/p1/p99
+#[tauri::command]
+async fn get_adc_histogram(
+    state: tauri::State<'_, AppState>,
+    channel: usize,
+) -> Result<AdcHistogram, AppError> {
+    let (adc_count, bins) = {
+        let config = state.config.lock().await;
+        (config.protocol.adc_count, config.histogram.bins)
+    };
+    if channel >= adc_count {
+        return Err(format!("ADC {} 不存在", channel + 1).into());
+    }
+    Ok(state.adc_histograms.lock().await.report(channel, bins))
+}
+
+#[tauri::command]
+async fn reset_adc_histograms(
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    state.adc_histograms.lock().await.reset();
+    Ok(())
+}
+
+// 等待用户确认的行程更新
+#[tauri::command]
+async fn get_range_proposals(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<RangeProposal>, AppError> {
+    Ok(state.range_learner.lock().await.proposals())
+}
+
+// 把学到的范围写入该轴的行程设置
+#[tauri::command]
+async fn accept_range_proposal(
+    state: tauri::State<'_, AppState>,
+    channel: usize,
+) -> Result<(), AppError> {
+    let range = state.range_learner.lock().await.accept(channel);
+    let Some((min, max)) = range else {
+        return Err(format!("ADC {} 没有学到的行程", channel + 1).into());
+    };
+    update_config(&state, |config| {
+        let mut config = config.clone();
+        if config.axis_settings.len() <= channel {
+            config.axis_settings.resize(channel + 1, AxisSettings::default());
+        }
+        let axis = &mut config.axis_settings[channel];
+        axis.min = min;
+        axis.max = max;
+        axis.center = axis.center.clamp(min, max);
+        Ok(config)
+    }).await?;
+    state.bus.publish(AppEvent::ConfigChanged { pointer: format!("/axis_settings/{}", channel) });
+    Ok(())
+}
+
+#[tauri::command]
+async fn reject_range_proposal(
+    state: tauri::State<'_, AppState>,
+    channel: usize,
+) -> Result<(), AppError> {
+    state.range_learner.lock().await.reject(channel);
+    Ok(())
+}
+
+// 疑似鬼键的按键组合，按出现次数排列
+#[tauri::command]
+async fn get_ghosting_report(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<GhostGroup>, AppError> {
+    Ok(state.ghost_detector.lock().await.report())
+}
+
+#[tauri::command]
+async fn reset_ghosting_report(
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    state.ghost_detector.lock().await.reset();
+    Ok(())
+}
+
+// 老化测试编号，取启动时的毫秒时间戳
+fn soak_id() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[tauri::command]
+async fn start_simon(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    rounds: Option<u32>,
+) -> Result<(), AppError> {
+    if state.simon_running.swap(true, Ordering::SeqCst) {
+        return Err(AppError::new(msg::SIMON_RUNNING, "Simon 模式正在进行"));
+    }
+    let generation = state.simon_generation.fetch_add(1, Ordering::SeqCst) + 1;
+    let config = state.config.lock().await;
+    let mut simon = config.simon.clone();
+    if let Some(rounds) = rounds {
+        simon.rounds = rounds;
+    }
+    let pairs = config.protocol.key_count.min(config.protocol.led_count);
+    let brightness = config.led_brightness;
+    if pairs == 0 {
+        state.simon_running.store(false, Ordering::SeqCst);
+        return Err(AppError::new(msg::SIMON_NO_PAIRS, "没有可对应的按键和LED"));
+    }
+    
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let cancelled = || state.simon_generation.load(Ordering::SeqCst) != generation;
+        let timeout = Duration::from_millis(simon.timeout_ms);
+        let mut picker = Picker::new();
+        let mut summary = SimonSummary::new(simon.rounds);
+        let mut previous = None;
+        
+        for round in 1..=simon.rounds {
+            if cancelled() {
+                break;
+            }
+            let led = picker.pick(pairs, previous);
+            previous = Some(led);
+            
+            // 每轮重新订阅，丢弃点亮之前的按键
+            let mut rx = state.bus.subscribe();
+            // 质检需要看到LED，不受LED总开关影响
+            send_led(&*state.parser.lock().await, led, true, brightness).await;
+            state.bus.publish(AppEvent::Simon(SimonEvent::Prompt {
+                round,
+                rounds: simon.rounds,
+                led,
+                timeout_ms: simon.timeout_ms,
+            }));
+            
+            let started = tokio::time::Instant::now();
+            let deadline = started + timeout;
+            let pressed = loop {
+                match tokio::time::timeout_at(deadline, crate::events::next_event(&mut rx)).await {
+                    Ok(Some(AppEvent::KeyEdge(edge))) if edge.pressed => break Some(edge.index),
+                    Ok(Some(_)) if !cancelled() => {}
+                    _ => break None,
+                }
+            };
+            send_led(&*state.parser.lock().await, led, false, brightness).await;
+            if cancelled() {
+                break;
+            }
+            
+            let reaction_ms = pressed.map(|_| started.elapsed().as_millis() as u64);
+            let hit = summary.record(led, pressed, reaction_ms);
+            state.bus.publish(AppEvent::Simon(SimonEvent::Result {
+                round,
+                expected: led,
+                pressed,
+                hit,
+                reaction_ms,
+            }));
+        }
+        
+        summary.cancelled = cancelled();
+        state.bus.publish(AppEvent::Simon(SimonEvent::Finished(summary)));
+        if !cancelled() {
+            state.simon_running.store(false, Ordering::SeqCst);
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_simon(
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    state.simon_generation.fetch_add(1, Ordering::SeqCst);
+    state.simon_running.store(false, Ordering::SeqCst);
     Ok(())
 }
 
@@ -99,40 +1455,725 @@ async fn save_config(
 async fn send_calibration_command(
     state: tauri::State<'_, AppState>,
     command: Vec<u8>,
-) -> Result<(), String> {
-    let parser = state.parser.lock().await;
+    device_id: Option<String>,
+) -> Result<(), AppError> {
+    let device = device_handle(&state, device_id).await?;
+    let parser = device.parser.lock().await;
+    parser.send_raw(&command).await?;
+    Ok(())
+}
+
+// 设置 DTR/RTS 控制线，用于复位设备或进入 bootloader
+#[tauri::command]
+async fn set_dtr(
+    state: tauri::State<'_, AppState>,
+    level: bool,
+    device_id: Option<String>,
+) -> Result<(), AppError> {
+    let device = device_handle(&state, device_id).await?;
+    let parser = device.parser.lock().await;
+    parser.set_dtr(level).await
+}
+
+#[tauri::command]
+async fn set_rts(
+    state: tauri::State<'_, AppState>,
+    level: bool,
+    device_id: Option<String>,
+) -> Result<(), AppError> {
+    let device = device_handle(&state, device_id).await?;
+    let parser = device.parser.lock().await;
+    parser.set_rts(level).await
+}
+
+#[tauri::command]
+async fn set_report_rate(
+    state: tauri::State<'_, AppState>,
+    hz: u32,
+    device_id: Option<String>,
+) -> Result<(), AppError> {
+    let device = device_handle(&state, device_id).await?;
+    let parser = device.parser.lock().await;
+    let command = report_rate_command(hz, parser.protocol())?;
     parser.send_command(&command).await?;
+    
+    // 按串口保存，下次连接时自动应用
+    let mut config = state.config.lock().await;
+    config.report_rates.insert(device.port, hz);
+    config.save();
+    Ok(())
+}
+
+// 重新查询主设备的信息，应答通过 device-response 事件返回并更新协议配置
+#[tauri::command]
+async fn query_device_info(
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    let parser = state.parser.lock().await;
+    parser.send_command(&info_command(parser.protocol())?).await?;
     Ok(())
 }
 
+#[cfg(feature = "flash")]
+#[tauri::command]
+async fn flash_firmware(
+    state: tauri::State<'_, AppState>,
+    file_path: String,
+    port: String,
+    use_crc: bool,
+    dry_run: Option<bool>,
+) -> Result<Option<FlashPlan>, AppError> {
+    // 试运行只生成下载计划，不打开串口
+    if dry_run.unwrap_or(false) {
+        return Ok(Some(bootloader::plan_firmware(Path::new(&file_path), use_crc)?));
+    }
+    let bus = state.bus.clone();
+    
+    // 下载到已连接的矩阵设备时直接接管串口句柄，不关闭后重新打开
+    let handoff = {
+        let mut parser = state.parser.lock().await;
+        let config = state.config.lock().await;
+        let connected_port = serial::normalize_port_name(&config.serial_matrix.port);
+        if serial::normalize_port_name(&port).eq_ignore_ascii_case(&connected_port) {
+            state.reconnect_generation.fetch_add(1, Ordering::SeqCst);
+            let handle = parser.take_port().await;
+            if handle.is_some() {
+                bus.publish(AppEvent::Connection {
+                    connected: false,
+                    port: config.serial_matrix.port.clone(),
+                    reason: Some(DisconnectReason::Flash),
+                });
+            }
+            handle
+        } else {
+            None
+        }
+    };
+    
+    // 下载过程是阻塞的串口读写，放到阻塞线程池中执行
+    tauri::async_runtime::spawn_blocking(move || {
+        // 诊断日志和进度发布到事件总线
+        let on_event = |event| {
+            bus.publish(AppEvent::Bootloader(event));
+        };
+        let mut client = match handoff {
+            Some(handle) => BootloaderClient::from_port(handle, use_crc, on_event)?,
+            None => BootloaderClient::new(&port, use_crc, on_event)?,
+        };
+        client.download_firmware(Path::new(&file_path))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    Ok(None)
+}
+
+// 未编译固件下载功能时保留命令，前端收到明确的错误
+#[cfg(not(feature = "flash"))]
+#[tauri::command]
+async fn flash_firmware(
+    file_path: String,
+    port: String,
+    use_crc: bool,
+    dry_run: Option<bool>,
+) -> Result<(), AppError> {
+    let _ = (file_path, port, use_crc, dry_run);
+    Err(AppError::new(msg::FEATURE_DISABLED, "编译时未启用固件下载功能").param("feature", "flash"))
+}
+
+// 编译时启用的可选功能，前端据此隐藏不可用的选项
+#[tauri::command]
+fn get_enabled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "history") {
+        features.push("history");
+    }
+    if cfg!(feature = "flash") {
+        features.push("flash");
+    }
+    features
+}
+
+// 历史记录作为事件总线的订阅者
+fn spawn_history_recorder(app: tauri::AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = crate::events::next_event(&mut rx).await {
+            let state = app.state::<AppState>();
+            let mut history = state.history.lock().await;
+            if let Some(history) = history.as_mut() {
+                history.handle_event(&event);
+            }
+        }
+    });
+}
+
+// 连接记录作为事件总线的订阅者
+fn spawn_connection_log(app: tauri::AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = crate::events::next_event(&mut rx).await {
+            app.state::<AppState>().connection_log.lock().await.handle_event(&event);
+        }
+    });
+}
+
+// Discord 状态作为事件总线的订阅者，配置变化时重新读取 Discord 设置
+fn spawn_discord_presence(app: tauri::AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        let config = app.state::<AppState>().config.lock().await.discord.clone();
+        let mut presence = DiscordPresence::new(&config);
+        while let Some(event) = crate::events::next_event(&mut rx).await {
+            if let AppEvent::ConfigChanged { .. } = event {
+                let config = app.state::<AppState>().config.lock().await.discord.clone();
+                presence.update_config(&config).await;
+            } else {
+                presence.handle_event(&event).await;
+            }
+        }
+    });
+}
+
+// Webhook 作为事件总线的订阅者
+fn spawn_webhooks(app: tauri::AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        let client = crate::webhooks::client();
+        let mut throttle = WebhookThrottle::default();
+        while let Some(event) = crate::events::next_event(&mut rx).await {
+            // 数据帧不会触发 Webhook，避免按帧率锁定配置
+            if matches!(event, AppEvent::Frame(_) | AppEvent::DeviceFrame { .. } | AppEvent::AggregateFrame(_)) {
+                continue;
+            }
+            let state = app.state::<AppState>();
+            let requests = crate::webhooks::requests_for(&state.config.lock().await.webhooks, &event);
+            for (webhook, body) in requests {
+                if !throttle.allow(&webhook) {
+                    state.webhooks_skipped.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+                tauri::async_runtime::spawn(crate::webhooks::deliver(client.clone(), webhook, body));
+            }
+        }
+    });
+}
+
+// 老化测试作为事件总线的订阅者
+fn spawn_soak_monitor(app: tauri::AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = crate::events::next_event(&mut rx).await {
+            let state = app.state::<AppState>();
+            let mut soak = state.soak.lock().await;
+            if let Some(test) = soak.as_mut() {
+                test.handle_event(&event);
+            }
+        }
+    });
+}
+
+// ADC直方图作为事件总线的订阅者
+fn spawn_histogram_recorder(app: tauri::AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = crate::events::next_event(&mut rx).await {
+            if let AppEvent::Frame(data) = event {
+                let state = app.state::<AppState>();
+                state.adc_histograms.lock().await.record(&data);
+            }
+        }
+    });
+}
+
+// 行程学习作为事件总线的订阅者，出现新的提示时发布到总线
+fn spawn_range_learner(app: tauri::AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    let bus = bus.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = crate::events::next_event(&mut rx).await {
+            if let AppEvent::Frame(data) = event {
+                if !data.valid {
+                    continue;
+                }
+                let state = app.state::<AppState>();
+                let proposals = state.range_learner.lock().await.observe(&data.adc);
+                for proposal in proposals {
+                    bus.publish(AppEvent::RangeProposal(proposal));
+                }
+            }
+        }
+    });
+}
+
+// 鬼键检测作为事件总线的订阅者，组合达到提示次数时发布到总线
+fn spawn_ghost_detector(app: tauri::AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    let bus = bus.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = crate::events::next_event(&mut rx).await {
+            if let AppEvent::Frame(data) = event {
+                if !data.valid {
+                    continue;
+                }
+                let state = app.state::<AppState>();
+                let groups = state.ghost_detector.lock().await.observe(&data.keys);
+                for group in groups {
+                    bus.publish(AppEvent::Ghosting(group));
+                }
+            }
+        }
+    });
+}
+
+// 监视表达式作为事件总线的订阅者，对每个新帧求值，结果再发布回总线
+fn spawn_watch_evaluator(app: tauri::AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    let bus = bus.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = crate::events::next_event(&mut rx).await {
+            if let AppEvent::Frame(data) = event {
+                let state = app.state::<AppState>();
+                let vars = state.variables.snapshot();
+                let signals = state.watches.lock().await.evaluate(&data, &vars);
+                if let Some(signals) = signals {
+                    bus.publish(AppEvent::WatchSignals(signals));
+                }
+            }
+        }
+    });
+}
+
+const VARIABLE_SAVE_DELAY: Duration = Duration::from_secs(1);
+
+// 配置了保存变量时，变量变化后写回配置，下次启动时恢复。
+// 第一次变化后等待 VARIABLE_SAVE_DELAY 再写文件，期间的变化合并为一次保存
+fn spawn_variable_saver(app: tauri::AppHandle, bus: &EventBus) {
+    let mut rx = bus.subscribe();
+    tauri::async_runtime::spawn(async move {
+        let mut save_at: Option<tokio::time::Instant> = None;
+        loop {
+            let event = match save_at {
+                Some(deadline) => match tokio::time::timeout_at(deadline, crate::events::next_event(&mut rx)).await {
+                    Ok(event) => event,
+                    Err(_) => {
+                        app.state::<AppState>().config.lock().await.save();
+                        save_at = None;
+                        continue;
+                    }
+                },
+                None => crate::events::next_event(&mut rx).await,
+            };
+            let Some(event) = event else {
+                break;
+            };
+            if let AppEvent::VariableChanged { name, value } = event {
+                let state = app.state::<AppState>();
+                let mut config = state.config.lock().await;
+                if config.variables.persist {
+                    config.variables.values.insert(name, value);
+                    save_at.get_or_insert_with(|| tokio::time::Instant::now() + VARIABLE_SAVE_DELAY);
+                }
+            }
+        }
+        if save_at.is_some() {
+            app.state::<AppState>().config.lock().await.save();
+        }
+    });
+}
+
+// 每分钟检查一次定时任务，配置修改后下一分钟即生效
+fn spawn_scheduler(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(crate::scheduler::until_next_minute()).await;
+            let now = chrono::Local::now();
+            let state = app.state::<AppState>();
+            let schedules = state.config.lock().await.schedules.clone();
+            for schedule in schedules.iter().filter(|s| s.enabled) {
+                match CronExpr::parse(&schedule.cron) {
+                    Ok(cron) if cron.matches(&now) => {
+                        if let Some(condition) = &schedule.condition {
+                            let keys = state.parser.lock().await.get_parsed_data().await.keys.clone();
+                            let host = state.host_state.lock().await.clone();
+                            if !condition.is_met(&keys, &host, now.time()) {
+                                continue;
+                            }
+                        }
+                        let source = format!("schedule {}", schedule.name);
+                        let requests = state.output.lock().await.run_action(&source, &schedule.action);
+                        let parser = state.parser.lock().await;
+                        handle_app_requests(&app, &state, None, &parser, requests).await;
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Invalid schedule {}: {}", schedule.name, e),
+                }
+            }
+        }
+    });
+}
+
+const GAME_STATE_TICK: Duration = Duration::from_millis(50);
+
+// 轮询游戏状态来源，字段变化时发布事件并更新对应的LED
+fn spawn_game_state_poller(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut poller = GameStatePoller::new();
+        loop {
+            tokio::time::sleep(GAME_STATE_TICK).await;
+            let state = app.state::<AppState>();
+            let config = state.config.lock().await.game_state.clone();
+            if config.sources.is_empty() {
+                continue;
+            }
+            let documents = poller.poll(&config).await;
+            
+            let (changed, leds) = {
+                let mut game_state = state.game_state.lock().await;
+                game_state.retain_sources(&config);
+                let mut changed = BTreeMap::new();
+                for (source, document) in &documents {
+                    changed.append(&mut game_state.update(source, document));
+                }
+                (changed, game_state.led_changes(&config.leds))
+            };
+            if !changed.is_empty() {
+                state.bus.publish(AppEvent::GameState(changed));
+            }
+            if !leds.is_empty() {
+                let requests = leds.into_iter().map(|(index, on)| AppRequest::SetLed { index, on }).collect();
+                let parser = state.parser.lock().await;
+                handle_app_requests(&app, &state, None, &parser, requests).await;
+            }
+        }
+    });
+}
+
+const HOST_STATE_INTERVAL: Duration = Duration::from_secs(5);
+
+// 定期采样主机状态，变化时发布事件
+fn spawn_host_sampler(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let Ok(sample) = tokio::task::spawn_blocking(crate::host_state::sample).await else {
+                return;
+            };
+            let state = app.state::<AppState>();
+            let mut host = state.host_state.lock().await;
+            if *host != sample {
+                *host = sample.clone();
+                state.bus.publish(AppEvent::HostState(sample));
+            }
+            drop(host);
+            tokio::time::sleep(HOST_STATE_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+async fn get_host_state(
+    state: tauri::State<'_, AppState>,
+) -> Result<HostState, AppError> {
+    Ok(state.host_state.lock().await.clone())
+}
+
+const PORT_WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+// 定期比较系统的串口列表，出现或消失时发布事件，前端据此刷新串口下拉框
+fn spawn_port_watcher(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut known = SerialManager::list_ports();
+        loop {
+            tokio::time::sleep(PORT_WATCH_INTERVAL).await;
+            let ports = match tauri::async_runtime::spawn_blocking(SerialManager::list_ports).await {
+                Ok(ports) => ports,
+                Err(e) => {
+                    eprintln!("Failed to list serial ports: {}", e);
+                    continue;
+                }
+            };
+            let state = app.state::<AppState>();
+            for port in ports.iter().filter(|p| !known.contains(p)) {
+                state.bus.publish(AppEvent::PortAdded { port: port.clone() });
+            }
+            for port in known.iter().filter(|p| !ports.contains(p)) {
+                state.bus.publish(AppEvent::PortRemoved { port: port.clone() });
+            }
+            known = ports;
+        }
+    });
+}
+
+// 按命令行参数或链接切换配置方案、修改串口设置并连接，打开的固件和方案文件交给前端确认
+async fn apply_launch_args(app: tauri::AppHandle, args: LaunchArgs) {
+    let state = app.state::<AppState>();
+    if let Some(name) = &args.profile {
+        let result = match crate::profile::load_profile(name) {
+            Ok(snapshot) => apply_snapshot(&state, snapshot).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to load profile {}: {}", name, e);
+        }
+    }
+    
+    // 指定的串口和波特率先用于连接，连接成功后才写入配置，错误的参数不会覆盖原来的设置
+    if args.connect || args.port.is_some() || args.baud.is_some() {
+        state.reconnect_generation.fetch_add(1, Ordering::SeqCst);
+        let mut parser = state.parser.lock().await;
+        let mut config = state.config.lock().await.clone();
+        if let Some(port) = args.port {
+            config.serial_matrix.port = port;
+        }
+        if let Some(baud) = args.baud {
+            config.serial_matrix.baud_rate = baud;
+        }
+        match open_matrix(&state, &mut parser, &config).await {
+            Ok(()) => {
+                let mut saved = state.config.lock().await;
+                if saved.serial_matrix.port != config.serial_matrix.port
+                    || saved.serial_matrix.baud_rate != config.serial_matrix.baud_rate
+                {
+                    saved.serial_matrix.port = config.serial_matrix.port;
+                    saved.serial_matrix.baud_rate = config.serial_matrix.baud_rate;
+                    saved.save();
+                }
+            }
+            Err(e) => eprintln!("Failed to connect {}: {}", config.serial_matrix.port, e),
+        }
+    }
+    
+    if let Some(file) = args.firmware {
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+        // 前端可能尚未监听，启动时通过 take_flash_request 取回
+        *state.flash_request.lock().await = Some(file.clone());
+        state.bus.publish(AppEvent::FlashRequested { file });
+    }
+    
+    if let Some(request) = args.link {
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+        *state.link_request.lock().await = Some(request.clone());
+        state.bus.publish(AppEvent::LinkRequested(request));
+    }
+    
+    if let Some(file) = args.profile_file {
+        let current = state.config.lock().await.clone();
+        match crate::profile::preview(Path::new(&file), &current) {
+            Ok(preview) => {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+                *state.profile_request.lock().await = Some(preview.clone());
+                state.bus.publish(AppEvent::ProfileOpened(preview));
+            }
+            Err(e) => eprintln!("Failed to open profile {}: {}", file, e),
+        }
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let (mut config, config_status) = MatrixConfig::load_with_status();
+    if config.migrate_port() {
+        config.save();
+    }
+    
+    // 启动健康检查需在创建运行标记之前完成
+    let startup_report = StartupReport::collect(&config, &config_status);
+    crate::health::mark_session_start();
+    
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let launch_args = LaunchArgs::parse(std::env::args().skip(1), &cwd);
+    let bus = EventBus::new();
+    let leds_enabled = config.leds_enabled;
+    let mut performance = PerformanceMode::default();
+    if let Err(e) = performance.set_enabled(config.performance_mode) {
+        eprintln!("Failed to enable performance mode: {}", e);
+    }
+    let action_log = ActionLog::new(&bus);
+    let variables = Variables::new(&config.variables.values, &bus);
+    let history = match HistoryStore::open(&config.history) {
+        Ok(history) => Some(history),
+        Err(e) => {
+            eprintln!("Failed to open history database: {}", e);
+            None
+        }
+    };
+    
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_single_instance::init(|app, _, _| {
-            // 当检测到新实例启动时，显示已存在的窗口
-            if let Some(window) = app.get_webview_window("main") {
-                let _ = window.show();
-                let _ = window.set_focus();
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            // 当检测到新实例启动时，显示已存在的窗口，并按新实例的工作目录处理其命令行参数
+            let args = LaunchArgs::parse(argv.into_iter().skip(1), Path::new(&cwd));
+            if !args.minimized {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
             }
+            tauri::async_runtime::spawn(apply_launch_args(app.clone(), args));
         }))
         .manage(AppState {
-            parser: Mutex::new(DataParser::new(MatrixConfig::load())),
-            config: Mutex::new(MatrixConfig::load()),
+            parser: Arc::new(Mutex::new(DataParser::new(config.clone()))),
+            devices: DeviceSet::default(),
+            aggregator: std::sync::Mutex::new(Aggregator::new(&config.aggregate)),
+            output: Arc::new(Mutex::new(OutputEngine::new(&config, action_log.clone(), variables.clone()))),
+            history: Mutex::new(history),
+            watches: Mutex::new(WatchSet::new(&config.watches)),
+            soak: Mutex::new(None),
+            adc_histograms: Mutex::new(AdcHistograms::default()),
+            range_learner: Mutex::new(RangeLearner::new(&config.range_learning, &config.axis_settings)),
+            ghost_detector: Mutex::new(GhostDetector::new(&config.ghosting)),
+            simon_generation: AtomicU64::new(0),
+            simon_running: AtomicBool::new(false),
+            reconnect_generation: AtomicU64::new(0),
+            sequence_generation: AtomicU64::new(0),
+            led_targets: Mutex::new(HashMap::new()),
+            pwm: Mutex::new(PwmLimiter::new(&config.pwm)),
+            responses: Mutex::new(HashMap::new()),
+            action_log,
+            variables,
+            webhooks_skipped: AtomicU64::new(0),
+            connection_log: Mutex::new(ConnectionLog::load()),
+            game_state: Mutex::new(GameState::default()),
+            host_state: Mutex::new(HostState::default()),
+            performance: Mutex::new(performance),
+            startup_report: startup_report.clone(),
+            flash_request: Mutex::new(None),
+            link_request: Mutex::new(None),
+            profile_request: Mutex::new(None),
+            bus: bus.clone(),
+            config: Mutex::new(config),
         })
         .invoke_handler(tauri::generate_handler![
             list_serial_ports,
+            check_port_permissions,
+            resolve_serial_port,
+            force_reconnect,
             connect_matrix,
             disconnect_matrix,
+            is_matrix_connected,
+            list_matrix_devices,
+            get_connection_history,
+            get_action_log,
             read_and_parse_data,
+            simulate_key,
+            set_rehearsal_mode,
+            is_rehearsal_mode,
+            get_action_stats,
+            get_variables,
+            get_host_state,
+            set_variable,
+            cancel_sequences,
+            simulate_adc,
             get_parsed_data,
+            get_aggregate_data,
+            get_recent_frame_errors,
+            run_protocol_conformance,
             get_config,
             save_config,
+            patch_config,
+            get_config_schema,
+            export_device_snapshot,
+            apply_device_snapshot,
+            get_layout,
+            set_layout,
+            query_history,
+            get_startup_report,
+            take_flash_request,
+            take_profile_request,
+            take_link_request,
+            load_profile,
+            import_profile,
+            start_soak_test,
+            stop_soak_test,
+            get_soak_status,
+            get_adc_histogram,
+            reset_adc_histograms,
+            get_range_proposals,
+            accept_range_proposal,
+            reject_range_proposal,
+            get_ghosting_report,
+            set_pwm,
+            send_named_command,
+            get_device_responses,
+            get_game_state,
+            set_performance_mode,
+            get_performance_status,
+            pause_processing,
+            resume_processing,
+            is_processing_paused,
+            reset_ghosting_report,
+            get_axis_compensation,
+            get_event_stats,
+            start_simon,
+            stop_simon,
             send_calibration_command,
+            set_report_rate,
+            query_device_info,
+            set_dtr,
+            set_rts,
+            flash_firmware,
+            get_enabled_features,
         ])
-        .setup(|app| {
+        .setup(move |app| {
             // 创建系统托盘
-            crate::tray::create_tray(app.handle())?;
+            crate::tray::create_tray(app.handle(), leds_enabled)?;
+            
+            // 事件总线的订阅者
+            crate::events::spawn_tauri_forwarder(app.handle().clone(), &bus);
+            crate::events::spawn_logger(&bus);
+            spawn_history_recorder(app.handle().clone(), &bus);
+            spawn_connection_log(app.handle().clone(), &bus);
+            spawn_webhooks(app.handle().clone(), &bus);
+            spawn_discord_presence(app.handle().clone(), &bus);
+            spawn_watch_evaluator(app.handle().clone(), &bus);
+            spawn_variable_saver(app.handle().clone(), &bus);
+            spawn_soak_monitor(app.handle().clone(), &bus);
+            spawn_histogram_recorder(app.handle().clone(), &bus);
+            spawn_range_learner(app.handle().clone(), &bus);
+            spawn_ghost_detector(app.handle().clone(), &bus);
+            
+            spawn_scheduler(app.handle().clone());
+            spawn_serial_reader(app.handle().clone());
+            spawn_port_watcher(app.handle().clone());
+            spawn_host_sampler(app.handle().clone());
+            spawn_game_state_poller(app.handle().clone());
+            
+            // 前端可能尚未监听，稍后也可通过 get_startup_report 获取
+            bus.publish(AppEvent::StartupReport(startup_report));
+            
+            // macOS 通过事件传入链接；Windows/Linux 的链接在命令行参数中
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    match LaunchArgs::from_link(url.as_str()) {
+                        Ok(args) => {
+                            tauri::async_runtime::spawn(apply_launch_args(handle.clone(), args));
+                        }
+                        Err(e) => eprintln!("Ignoring link: {}", e),
+                    }
+                }
+            });
+            // 开发时安装包尚未注册协议，运行时注册
+            #[cfg(all(debug_assertions, any(windows, target_os = "linux")))]
+            if let Err(e) = app.deep_link().register_all() {
+                eprintln!("Failed to register {}:// scheme: {}", crate::deeplink::SCHEME, e);
+            }
+            
+            if launch_args.minimized {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.hide();
+                }
+            }
+            tauri::async_runtime::spawn(apply_launch_args(app.handle().clone(), launch_args));
             Ok(())
         })
         .on_window_event(|window, event| {
@@ -142,6 +2183,27 @@ pub fn run() {
                 api.prevent_close();
             }
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, event| match event {
+            tauri::RunEvent::Exit => {
+                // 变量的保存有延迟，退出前写回
+                let state = _app.state::<AppState>();
+                let config = tauri::async_runtime::block_on(state.config.lock());
+                if config.variables.persist {
+                    config.save();
+                }
+                crate::health::mark_session_end();
+            }
+            // macOS 上通过文件关联打开的文件
+            #[cfg(target_os = "macos")]
+            tauri::RunEvent::Opened { urls } => {
+                let mut args = LaunchArgs::default();
+                for path in urls.iter().filter_map(|url| url.to_file_path().ok()) {
+                    args.open_file(&path);
+                }
+                tauri::async_runtime::spawn(apply_launch_args(_app.clone(), args));
+            }
+            _ => {}
+        });
 }
@@ -0,0 +1,940 @@
+use arboard::Clipboard;
+use enigo::{Axis, Direction, Enigo, Key, Keyboard, Mouse, Settings};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{self, Sender};
+use std::time::{Duration, Instant};
+use crate::action_log::ActionLog;
+use crate::command::render_template;
+use crate::config::{ActionConfig, ActionLimits, AxisSettings, BankConfig, BindingConfig, CommandTemplate, GestureDirection, LedMirror, MacroConfig, MacroStep, MatrixConfig, PwmSource, SequenceStep};
+use crate::drift::{AxisCompensation, DriftCompensator};
+use crate::matrix::ParsedData;
+use crate::variables::Variables;
+use crate::window_actions;
+
+// 计算轴偏移量，去掉死区后归一化到 -1.0 ~ 1.0
+pub fn axis_deflection(value: u8, axis: &AxisSettings) -> f32 {
+    let offset = value as f32 - axis.center as f32;
+    let deadzone = axis.deadzone as f32;
+    if offset.abs() <= deadzone {
+        return 0.0;
+    }
+
+    // 中心值两侧的行程可能不同，分别归一化
+    let span = if offset > 0.0 {
+        axis.max as f32 - axis.center as f32
+    } else {
+        axis.center as f32 - axis.min as f32
+    } - deadzone;
+    if span <= 0.0 {
+        return offset.signum();
+    }
+
+    ((offset.abs() - deadzone) / span).min(1.0) * offset.signum()
+}
+
+// 无限位编码器相邻两帧的有符号增量，读数在 255 与 0 之间回绕时按最短方向计算
+pub fn wrapping_delta(previous: u8, current: u8) -> i32 {
+    current.wrapping_sub(previous) as i8 as i32
+}
+
+// 解析组合键字符串，如 "Ctrl+Shift+A"、"PageUp"、"Ctrl+Plus"
+pub fn parse_key_combo(combo: &str) -> Result<Vec<Key>, String> {
+    let mut keys = Vec::new();
+    for part in combo.split('+').map(|p| p.trim()) {
+        if part.is_empty() {
+            continue;
+        }
+        keys.push(parse_key(part)?);
+    }
+    if keys.is_empty() {
+        return Err(format!("无效的按键: {}", combo));
+    }
+    Ok(keys)
+}
+
+fn parse_key(name: &str) -> Result<Key, String> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(Key::Unicode(c.to_ascii_lowercase()));
+    }
+
+    let key = match name.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => Key::Control,
+        "shift" => Key::Shift,
+        "alt" => Key::Alt,
+        "meta" | "win" | "cmd" | "super" => Key::Meta,
+        "plus" => Key::Unicode('='),
+        "minus" => Key::Unicode('-'),
+        "space" => Key::Space,
+        "tab" => Key::Tab,
+        "enter" | "return" => Key::Return,
+        "esc" | "escape" => Key::Escape,
+        "backspace" => Key::Backspace,
+        "delete" | "del" => Key::Delete,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "up" => Key::UpArrow,
+        "down" => Key::DownArrow,
+        "left" => Key::LeftArrow,
+        "right" => Key::RightArrow,
+        "volumeup" => Key::VolumeUp,
+        "volumedown" => Key::VolumeDown,
+        "volumemute" => Key::VolumeMute,
+        "playpause" => Key::MediaPlayPause,
+        "nexttrack" => Key::MediaNextTrack,
+        "prevtrack" => Key::MediaPrevTrack,
+        other => match other.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+            Some(1) => Key::F1,
+            Some(2) => Key::F2,
+            Some(3) => Key::F3,
+            Some(4) => Key::F4,
+            Some(5) => Key::F5,
+            Some(6) => Key::F6,
+            Some(7) => Key::F7,
+            Some(8) => Key::F8,
+            Some(9) => Key::F9,
+            Some(10) => Key::F10,
+            Some(11) => Key::F11,
+            Some(12) => Key::F12,
+            _ => return Err(format!("无效的按键: {}", name)),
+        },
+    };
+    Ok(key)
+}
+
+// 系统快捷键使用的修饰键（macOS 为 Cmd，其它平台为 Ctrl）
+#[cfg(target_os = "macos")]
+const SHORTCUT_MODIFIER: Key = Key::Meta;
+#[cfg(not(target_os = "macos"))]
+const SHORTCUT_MODIFIER: Key = Key::Control;
+
+// 发送给输出线程的命令
+enum OutputCommand {
+    KeyCombo(Vec<Key>),
+    PasteText(String),  // 写入剪贴板后模拟粘贴
+    FocusApp(String),
+    MinimizeWindow,
+    MaximizeWindow,
+    SwitchDesktop(bool),
+    Scroll(i32),
+}
+
+// 输出命令及其对应的动作（来源, 动作），执行后写入动作日志
+type OutputJob = (OutputCommand, String, ActionConfig);
+
+// 模拟输入在独立线程中执行，避免阻塞串口读取
+struct KeySender {
+    tx: Sender<OutputJob>,
+}
+
+impl KeySender {
+    fn spawn(log: ActionLog) -> Self {
+        let (tx, rx) = mpsc::channel::<OutputJob>();
+        std::thread::spawn(move || {
+            let mut enigo = match Enigo::new(&Settings::default()) {
+                Ok(enigo) => enigo,
+                Err(e) => {
+                    eprintln!("Failed to initialize input simulation: {}", e);
+                    return;
+                }
+            };
+            let mut clipboard: Option<Clipboard> = None;
+            for (command, source, action) in rx {
+                let started = Instant::now();
+                let result = match command {
+                    OutputCommand::KeyCombo(combo) => click_combo(&mut enigo, &combo),
+                    OutputCommand::PasteText(text) => {
+                        paste_text(&mut enigo, &mut clipboard, text)
+                    }
+                    OutputCommand::FocusApp(name) => window_actions::focus_app(&mut enigo, &name),
+                    OutputCommand::MinimizeWindow => window_actions::minimize_window(&mut enigo),
+                    OutputCommand::MaximizeWindow => window_actions::maximize_window(&mut enigo),
+                    OutputCommand::SwitchDesktop(next) => {
+                        window_actions::switch_desktop(&mut enigo, next)
+                    }
+                    OutputCommand::Scroll(lines) => {
+                        enigo.scroll(lines, Axis::Vertical).map_err(|e| e.to_string())
+                    }
+                };
+                if let Err(e) = &result {
+                    eprintln!("Failed to send output: {}", e);
+                }
+                log.record(&source, &action, started, result);
+            }
+        });
+        Self { tx }
+    }
+}
+
+fn paste_text(
+    enigo: &mut Enigo,
+    clipboard: &mut Option<Clipboard>,
+    text: String,
+) -> Result<(), String> {
+    // 剪贴板句柄在第一次使用时创建
+    let clipboard = match clipboard {
+        Some(clipboard) => clipboard,
+        None => clipboard.insert(Clipboard::new().map_err(|e| e.to_string())?),
+    };
+    clipboard.set_text(text).map_err(|e| e.to_string())?;
+    click_combo(enigo, &[SHORTCUT_MODIFIER, Key::Unicode('v')])
+}
+
+// 按下修饰键 -> 点击最后一个键 -> 逆序松开修饰键
+pub fn click_combo(enigo: &mut Enigo, combo: &[Key]) -> Result<(), String> {
+    let (last, modifiers) = combo.split_last().ok_or("空的组合键")?;
+    for key in modifiers {
+        enigo.key(*key, Direction::Press).map_err(|e| e.to_string())?;
+    }
+    let result = enigo.key(*last, Direction::Click).map_err(|e| e.to_string());
+    for key in modifiers.iter().rev() {
+        let _ = enigo.key(*key, Direction::Release);
+    }
+    result
+}
+
+// 需要由应用层（窗口管理等）完成的请求
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppRequest {
+    ShowWindow,
+    ToggleWindow,
+    SetLed { index: usize, on: bool },
+    SetPwm { channel: usize, duty: u8 },
+    SendCommand(Vec<u8>),  // 自定义命令模板生成的字节
+    SaveMacro(MacroConfig),  // 录制结束，需要写入配置
+    BankChanged { index: usize, name: String },
+    PauseChanged(bool),  // 暂停按键切换了暂停状态
+    Rehearsed(ActionConfig),  // 演练模式下本应执行的动作
+    RunSequence { source: String, steps: Vec<SequenceStep> },  // 步骤之间需要等待，由应用层的任务执行
+}
+
+const BANK_HYSTERESIS: i32 = 4;  // 旋钮在档位边界附近时的回差，避免来回跳动
+
+// 绑定的描述，作为动作日志中的来源
+fn binding_source(binding: &BindingConfig) -> String {
+    match binding {
+        BindingConfig::KeyPress { key, .. } => format!("key {}", key + 1),
+        BindingConfig::AxisRepeat { channel, .. } => format!("axis repeat on ADC {}", channel + 1),
+        BindingConfig::DualStage { channel, .. } => format!("dual stage on ADC {}", channel + 1),
+        BindingConfig::Encoder { channel, .. } => format!("encoder on ADC {}", channel + 1),
+        BindingConfig::Flick { channel, .. } => format!("flick on ADC {}", channel + 1),
+        BindingConfig::Spin { channel, .. } => format!("spin on ADC {}", channel + 1),
+    }
+}
+
+// 把旋钮的ADC值量化为档位，越过当前档位边界一定距离后才切换
+pub fn quantize_bank(value: u8, count: usize, current: usize) -> usize {
+    if count == 0 {
+        return 0;
+    }
+    let position = (value as usize * count / 256).min(count - 1);
+    if position == current || current >= count {
+        return position;
+    }
+    let lower = (current * 256 / count) as i32;
+    let upper = ((current + 1) * 256 / count) as i32;
+    let value = value as i32;
+    if value < lower - BANK_HYSTERESIS || value >= upper + BANK_HYSTERESIS {
+        position
+    } else {
+        current
+    }
+}
+
+const RECORD_BLINK_INTERVAL: Duration = Duration::from_millis(500);
+
+// 正在录制的宏
+struct Recording {
+    last_press: Instant,
+    last_blink: Instant,
+    led_on: bool,
+    steps: Vec<MacroStep>,
+}
+
+// 每个绑定的运行时状态
+#[derive(Default)]
+struct BindingState {
+    last_fire: Option<Instant>,
+    stage: u8,  // 两段式扳机当前所处阶段
+    samples: VecDeque<(Instant, i32)>,  // 手势时间窗口内的读数，旋转手势为累计位置
+    position: Option<(u8, i32)>,  // 旋转手势上一帧的读数和累计位置
+    last_value: Option<u8>,  // 编码器上一帧的读数
+    travel: i32,  // 编码器尚未达到一步的累计增量
+}
+
+// 时间窗口内朝 direction 变化达到 threshold 时返回 true，触发后从当前位置重新计算
+fn detect_gesture(
+    samples: &mut VecDeque<(Instant, i32)>,
+    now: Instant,
+    position: i32,
+    window: Duration,
+    direction: GestureDirection,
+    threshold: i32,
+) -> bool {
+    samples.push_back((now, position));
+    while samples.front().is_some_and(|&(at, _)| now.duration_since(at) > window) {
+        samples.pop_front();
+    }
+    let travel = match direction {
+        GestureDirection::Up => position - samples.iter().map(|&(_, p)| p).min().unwrap_or(position),
+        GestureDirection::Down => samples.iter().map(|&(_, p)| p).max().unwrap_or(position) - position,
+    };
+    if travel < threshold {
+        return false;
+    }
+    samples.clear();
+    samples.push_back((now, position));
+    true
+}
+
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+fn primary_only<T: Clone>(primary: bool, items: &[T]) -> Vec<T> {
+    if primary { items.to_vec() } else { Vec::new() }
+}
+
+// 动作执行计数，冷却和限速跳过的次数分别统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ActionStats {
+    pub executed: u64,
+    pub cooldown_skipped: u64,
+    pub rate_limited: u64,
+    pub webhooks_skipped: u64,  // 冷却期间丢弃的 Webhook，由事件订阅者统计
+}
+
+pub struct OutputEngine {
+    device: Option<String>,  // 其他设备的 id，主设备为 None
+    bindings: Vec<BindingConfig>,  // 全局绑定加上当前分组的绑定
+    base_bindings: Vec<BindingConfig>,
+    bank_channel: Option<usize>,
+    banks: Vec<BankConfig>,
+    active_bank: Option<usize>,
+    axis_settings: Vec<AxisSettings>,
+    drift: DriftCompensator,
+    led_count: usize,
+    led_mirror: Vec<LedMirror>,
+    pwm_sources: Vec<PwmSource>,
+    pwm_duties: Vec<Option<u8>>,  // 各ADC驱动的PWM上次计算的占空比
+    wake_key: Option<usize>,
+    pause_key: Option<usize>,
+    pause_leds: bool,
+    paused: bool,
+    rehearsal: bool,  // 演练模式：照常判断绑定，只报告动作而不执行
+    safety_key: Option<usize>,
+    arm_window: Duration,
+    armed_at: Option<Instant>,  // 最近一次按下安全键的时间，执行一次受保护的动作后清除
+    record_key: Option<usize>,
+    record_led: Option<usize>,
+    macros: Vec<MacroConfig>,
+    snippets: Vec<String>,
+    commands: Vec<CommandTemplate>,
+    states: Vec<BindingState>,
+    recording: Option<Recording>,
+    prev_keys: Vec<bool>,
+    sender: Option<KeySender>,
+    log: ActionLog,
+    variables: Variables,
+    limits: ActionLimits,
+    last_run: HashMap<(String, &'static str), Instant>,  // 各绑定各类动作上次执行的时间
+    recent_runs: VecDeque<Instant>,  // 最近一秒内执行动作的时间
+    stats: ActionStats,
+}
+
+impl OutputEngine {
+    pub fn new(config: &MatrixConfig, log: ActionLog, variables: Variables) -> Self {
+        Self::with_device(None, config, log, variables)
+    }
+
+    // 其他设备的输出引擎：只使用该设备的绑定，动作日志和变量与主设备共用
+    pub fn for_device(id: &str, config: &MatrixConfig, log: ActionLog, variables: Variables) -> Self {
+        Self::with_device(Some(id.to_string()), config, log, variables)
+    }
+
+    fn with_device(device: Option<String>, config: &MatrixConfig, log: ActionLog, variables: Variables) -> Self {
+        let mut engine = Self {
+            device,
+            bindings: Vec::new(),
+            base_bindings: Vec::new(),
+            bank_channel: None,
+            banks: Vec::new(),
+            active_bank: None,
+            axis_settings: Vec::new(),
+            drift: DriftCompensator::new(&config.drift, config.protocol.adc_count),
+            led_count: 0,
+            led_mirror: Vec::new(),
+            pwm_sources: Vec::new(),
+            pwm_duties: Vec::new(),
+            wake_key: None,
+            pause_key: None,
+            pause_leds: false,
+            paused: false,
+            rehearsal: false,
+            safety_key: None,
+            arm_window: Duration::ZERO,
+            armed_at: None,
+            record_key: None,
+            record_led: None,
+            macros: Vec::new(),
+            snippets: Vec::new(),
+            commands: Vec::new(),
+            states: Vec::new(),
+            recording: None,
+            prev_keys: Vec::new(),
+            sender: None,
+            log,
+            variables,
+            limits: ActionLimits::default(),
+            last_run: HashMap::new(),
+            recent_runs: VecDeque::new(),
+            stats: ActionStats::default(),
+        };
+        engine.update_config(config);
+        engine
+    }
+
+    // 配置变化时重新加载绑定，并清空运行时状态。分组、唤醒、暂停、安全和录制按键，
+    // LED 跟随和 PWM 来源引用的都是主设备的输入，其他设备不使用
+    pub fn update_config(&mut self, config: &MatrixConfig) {
+        let primary = self.device.is_none();
+        self.base_bindings = match &self.device {
+            None => config.bindings.clone(),
+            Some(id) => config.device_bindings.get(id).cloned().unwrap_or_default(),
+        };
+        self.bank_channel = config.bank_channel.filter(|_| primary);
+        self.banks = primary_only(primary, &config.banks);
+        // 下一帧按旋钮位置重新选择分组
+        self.active_bank = None;
+        self.axis_settings = config.axis_settings.clone();
+        self.drift.update_config(&config.drift, config.protocol.adc_count);
+        self.led_count = config.protocol.led_count;
+        self.led_mirror = primary_only(primary, &config.led_mirror.pairs(&config.protocol));
+        self.pwm_sources = primary_only(primary, &config.pwm.sources);
+        self.pwm_duties = vec![None; self.pwm_sources.len()];
+        self.wake_key = config.wake_key.filter(|_| primary);
+        self.pause_key = config.pause.key.filter(|_| primary);
+        self.pause_leds = config.pause.leds;
+        self.safety_key = config.safety.key.filter(|_| primary);
+        self.arm_window = Duration::from_millis(config.safety.arm_window_ms);
+        self.armed_at = None;
+        self.record_key = config.record_key.filter(|_| primary);
+        self.record_led = config.record_led.filter(|_| primary);
+        self.macros = primary_only(primary, &config.macros);
+        self.snippets = config.snippets.clone();
+        self.commands = config.commands.clone();
+        self.limits = config.action_limits.clone();
+        self.last_run.clear();
+        self.recent_runs.clear();
+        self.rebuild_bindings();
+    }
+
+    // 暂停期间仍跟踪按键和旋钮状态，恢复时不会把暂停期间的按下当作新的按下
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_rehearsal(&mut self, enabled: bool) {
+        self.rehearsal = enabled;
+    }
+
+    pub fn is_rehearsal(&self) -> bool {
+        self.rehearsal
+    }
+
+    pub fn action_stats(&self) -> ActionStats {
+        self.stats.clone()
+    }
+
+    pub fn axis_compensation(&self) -> Vec<AxisCompensation> {
+        self.drift.compensation(&self.axis_settings)
+    }
+
+    // 温漂补偿后的轴参数
+    fn axis(&self, channel: usize) -> AxisSettings {
+        let axis = self.axis_settings.get(channel).cloned().unwrap_or_default();
+        self.drift.compensate(channel, &axis)
+    }
+
+    fn rebuild_bindings(&mut self) {
+        self.bindings = self.base_bindings.clone();
+        if let Some(bank) = self.active_bank.and_then(|i| self.banks.get(i)) {
+            self.bindings.extend(bank.bindings.iter().cloned());
+        }
+        self.states = self.bindings.iter().map(|_| BindingState::default()).collect();
+    }
+
+    // 根据旋钮位置切换分组，切换时更新绑定和分组指示LED
+    fn update_bank(&mut self, data: &ParsedData, requests: &mut Vec<AppRequest>) {
+        let Some(&value) = self.bank_channel.and_then(|channel| data.adc.get(channel)) else {
+            return;
+        };
+        let current = self.active_bank.unwrap_or(usize::MAX);
+        let bank = quantize_bank(value, self.banks.len(), current);
+        if self.banks.is_empty() || Some(bank) == self.active_bank {
+            return;
+        }
+
+        for (index, other) in self.banks.iter().enumerate() {
+            if let Some(led) = other.led {
+                requests.push(AppRequest::SetLed { index: led, on: index == bank });
+            }
+        }
+        requests.push(AppRequest::BankChanged {
+            index: bank,
+            name: self.banks[bank].name.clone(),
+        });
+        self.active_bank = Some(bank);
+        self.rebuild_bindings();
+    }
+
+    // 每次读取到数据后调用，根据绑定产生输出，返回需要应用层处理的请求
+    pub fn process(&mut self, data: &ParsedData) -> Vec<AppRequest> {
+        let mut requests = Vec::new();
+        if !data.valid {
+            return requests;
+        }
+        self.update_bank(data, &mut requests);
+        self.drift.observe(&data.adc, &self.axis_settings, data.keys.contains(&true));
+
+        let pressed = |key: usize| {
+            data.keys.get(key) == Some(&true) && self.prev_keys.get(key) != Some(&true)
+        };
+
+        if self.pause_key.is_some_and(pressed) {
+            self.paused = !self.paused;
+            requests.push(AppRequest::PauseChanged(self.paused));
+        }
+        if self.wake_key.is_some_and(pressed) {
+            requests.push(AppRequest::ShowWindow);
+        }
+        let presses: Vec<usize> = (0..data.keys.len()).filter(|&key| pressed(key)).collect();
+        
+        // 按键状态变化时更新跟随的LED
+        for mirror in &self.led_mirror {
+            let Some(&on) = data.keys.get(mirror.key) else {
+                continue;
+            };
+            if self.prev_keys.get(mirror.key) != Some(&on) {
+                requests.push(AppRequest::SetLed { index: mirror.led, on });
+            }
+        }
+
+        // ADC驱动的PWM输出，占空比变化时才请求发送
+        for (source, last) in self.pwm_sources.iter().zip(self.pwm_duties.iter_mut()) {
+            let Some(&value) = data.adc.get(source.adc) else {
+                continue;
+            };
+            let duty = source.duty(value);
+            if *last != Some(duty) {
+                *last = Some(duty);
+                requests.push(AppRequest::SetPwm { channel: source.channel, duty });
+            }
+        }
+
+        let axes: Vec<AxisSettings> = (0..data.adc.len()).map(|channel| self.axis(channel)).collect();
+        let now = Instant::now();
+        
+        // 受保护的绑定需要按住安全键，或在按下安全键后的时间窗口内
+        if self.safety_key.is_some_and(pressed) {
+            self.armed_at = Some(now);
+        }
+        let safety_held = self.safety_key.is_some_and(|key| data.keys.get(key) == Some(&true));
+        let armed = self.armed_at.is_some_and(|at| now.duration_since(at) <= self.arm_window);
+        let guard_open = safety_held || armed;
+        let mut guard_used = false;
+        
+        let mut actions = Vec::new();
+        for (binding, state) in self.bindings.iter().zip(self.states.iter_mut()) {
+            match binding {
+                BindingConfig::KeyPress { key, action, guarded } => {
+                    if !pressed(*key) {
+                        continue;
+                    }
+                    if *guarded && !guard_open {
+                        continue;
+                    }
+                    guard_used |= *guarded;
+                    actions.push((binding_source(binding), action.clone()));
+                }
+                BindingConfig::AxisRepeat { channel, positive_key, negative_key, min_rate, max_rate } => {
+                    let Some(&value) = data.adc.get(*channel) else {
+                        continue;
+                    };
+                    let axis = axes.get(*channel).cloned().unwrap_or_default();
+                    let deflection = axis_deflection(value, &axis);
+                    if deflection == 0.0 {
+                        state.last_fire = None;
+                        continue;
+                    }
+
+                    // 频率随偏移量线性变化
+                    let rate = min_rate + (max_rate - min_rate) * deflection.abs();
+                    let Ok(interval) = Duration::try_from_secs_f32(1.0 / rate) else {
+                        continue;
+                    };
+                    if let Some(last) = state.last_fire {
+                        if now.duration_since(last) < interval {
+                            continue;
+                        }
+                    }
+                    state.last_fire = Some(now);
+
+                    let key = if deflection > 0.0 { positive_key } else { negative_key };
+                    if let Some(keys) = key {
+                        actions.push((binding_source(binding), ActionConfig::KeyCombo { keys: keys.clone() }));
+                    }
+                }
+                BindingConfig::DualStage {
+                    channel,
+                    stage1_threshold,
+                    stage2_threshold,
+                    hysteresis,
+                    stage1_action,
+                    stage2_action,
+                    stage1_led,
+                    stage2_led,
+                    guarded,
+                } => {
+                    let Some(&value) = data.adc.get(*channel) else {
+                        continue;
+                    };
+                    let axis = axes.get(*channel).cloned().unwrap_or_default();
+                    let deflection = axis_deflection(value, &axis).abs();
+
+                    // 上升时越过阈值进入，下降时需低于 阈值-回差 才退出
+                    let mut stage = state.stage;
+                    if stage < 2 && deflection >= *stage2_threshold {
+                        stage = 2;
+                    } else if stage < 1 && deflection >= *stage1_threshold {
+                        stage = 1;
+                    } else if stage == 2 && deflection < stage2_threshold - hysteresis {
+                        stage = if deflection >= stage1_threshold - hysteresis { 1 } else { 0 };
+                    } else if stage == 1 && deflection < stage1_threshold - hysteresis {
+                        stage = 0;
+                    }
+                    if stage == state.stage {
+                        continue;
+                    }
+
+                    // 只在进入更高阶段时触发动作，一次越过两级时两个动作依次触发
+                    let allowed = !*guarded || guard_open;
+                    if stage > state.stage && allowed {
+                        guard_used |= *guarded;
+                        if state.stage < 1 {
+                            actions.extend(stage1_action.clone().map(|action| (binding_source(binding), action)));
+                        }
+                        if stage == 2 {
+                            actions.extend(stage2_action.clone().map(|action| (binding_source(binding), action)));
+                        }
+                    }
+                    if let Some(index) = *stage1_led {
+                        if (state.stage >= 1) != (stage >= 1) {
+                            requests.push(AppRequest::SetLed { index, on: stage >= 1 });
+                        }
+                    }
+                    if let Some(index) = *stage2_led {
+                        if (state.stage == 2) != (stage == 2) {
+                            requests.push(AppRequest::SetLed { index, on: stage == 2 });
+                        }
+                    }
+                    state.stage = stage;
+                }
+                BindingConfig::Encoder { channel, step, increment_action, decrement_action } => {
+                    let Some(&value) = data.adc.get(*channel) else {
+                        continue;
+                    };
+                    let Some(last) = state.last_value.replace(value) else {
+                        continue;
+                    };
+                    let step = (*step).max(1) as i32;
+                    state.travel += wrapping_delta(last, value);
+                    while state.travel.abs() >= step {
+                        let action = if state.travel > 0 {
+                            state.travel -= step;
+                            increment_action
+                        } else {
+                            state.travel += step;
+                            decrement_action
+                        };
+                        actions.extend(action.clone().map(|action| (binding_source(binding), action)));
+                    }
+                }
+                BindingConfig::Flick { channel, direction, min_delta, window_ms, action, guarded } => {
+                    let Some(&value) = data.adc.get(*channel) else {
+                        continue;
+                    };
+                    let window = Duration::from_millis(*window_ms);
+                    if !detect_gesture(&mut state.samples, now, value as i32, window, *direction, *min_delta as i32) {
+                        continue;
+                    }
+                    if *guarded && !guard_open {
+                        continue;
+                    }
+                    guard_used |= *guarded;
+                    actions.push((binding_source(binding), action.clone()));
+                }
+                BindingConfig::Spin { channel, direction, min_travel, window_ms, action, guarded } => {
+                    let Some(&value) = data.adc.get(*channel) else {
+                        continue;
+                    };
+                    // 相邻两帧的差值按最短方向计算，跨过 255/0 时不会被当成反向转了一整圈
+                    let position = match state.position {
+                        Some((last, position)) => position + wrapping_delta(last, value),
+                        None => 0,
+                    };
+                    state.position = Some((value, position));
+                    let window = Duration::from_millis(*window_ms);
+                    let threshold = (*min_travel).min(i32::MAX as u32) as i32;
+                    if !detect_gesture(&mut state.samples, now, position, window, *direction, threshold) {
+                        continue;
+                    }
+                    if *guarded && !guard_open {
+                        continue;
+                    }
+                    guard_used |= *guarded;
+                    actions.push((binding_source(binding), action.clone()));
+                }
+            }
+        }
+        if guard_used && !safety_held {
+            self.armed_at = None;
+        }
+        self.prev_keys = data.keys.clone();
+        if self.paused {
+            if self.pause_leds {
+                requests.retain(|request| !matches!(request, AppRequest::SetLed { .. } | AppRequest::SetPwm { .. }));
+            }
+            return requests;
+        }
+        self.update_recording(&presses, now, &mut requests);
+
+        for (source, action) in actions {
+            self.dispatch(&source, &action, &mut requests);
+        }
+        requests
+    }
+
+    // 检查冷却和全局限速，允许执行时记下执行时间
+    fn allow(&mut self, source: &str, action: &ActionConfig) -> bool {
+        let now = Instant::now();
+        let kind = action.kind();
+        let cooldown = self.limits.cooldown(kind);
+        let key = (source.to_string(), kind);
+        if !cooldown.is_zero() {
+            if let Some(last) = self.last_run.get(&key) {
+                if now.duration_since(*last) < cooldown {
+                    self.stats.cooldown_skipped += 1;
+                    return false;
+                }
+            }
+        }
+        if self.limits.max_per_second > 0 {
+            while self.recent_runs.front().is_some_and(|t| now.duration_since(*t) >= RATE_WINDOW) {
+                self.recent_runs.pop_front();
+            }
+            if self.recent_runs.len() >= self.limits.max_per_second as usize {
+                self.stats.rate_limited += 1;
+                return false;
+            }
+            self.recent_runs.push_back(now);
+        }
+        if !cooldown.is_zero() {
+            self.last_run.insert(key, now);
+        }
+        self.stats.executed += 1;
+        true
+    }
+
+    // 演练模式下只报告动作，否则执行；冷却或限速期间的动作被跳过
+    fn dispatch(&mut self, source: &str, action: &ActionConfig, requests: &mut Vec<AppRequest>) {
+        if !self.allow(source, action) {
+            return;
+        }
+        if self.rehearsal {
+            requests.push(AppRequest::Rehearsed(action.clone()));
+        } else {
+            self.execute(source, action, requests);
+        }
+    }
+
+    // 模拟输入交给输出线程，执行后记录；其余动作在此完成，立即记录
+    fn execute(&mut self, source: &str, action: &ActionConfig, requests: &mut Vec<AppRequest>) {
+        let started = Instant::now();
+        let command = match action {
+            ActionConfig::KeyCombo { keys } => parse_key_combo(keys).map(|combo| Some(OutputCommand::KeyCombo(combo))),
+            ActionConfig::ToggleWindow => {
+                requests.push(AppRequest::ToggleWindow);
+                Ok(None)
+            }
+            ActionConfig::CopySelection => Ok(Some(OutputCommand::KeyCombo(vec![SHORTCUT_MODIFIER, Key::Unicode('c')]))),
+            ActionConfig::PasteClipboard => Ok(Some(OutputCommand::KeyCombo(vec![SHORTCUT_MODIFIER, Key::Unicode('v')]))),
+            ActionConfig::PasteSnippet { index } => match self.snippets.get(*index) {
+                Some(text) => Ok(Some(OutputCommand::PasteText(text.clone()))),
+                None => Err(format!("Snippet {} not found", index)),
+            },
+            ActionConfig::FocusApp { name } => Ok(Some(OutputCommand::FocusApp(name.clone()))),
+            ActionConfig::MinimizeWindow => Ok(Some(OutputCommand::MinimizeWindow)),
+            ActionConfig::MaximizeWindow => Ok(Some(OutputCommand::MaximizeWindow)),
+            ActionConfig::SwitchDesktop { next } => Ok(Some(OutputCommand::SwitchDesktop(*next))),
+            ActionConfig::Scroll { lines } => Ok(Some(OutputCommand::Scroll(*lines))),
+            ActionConfig::PlayMacro { index } => match self.macros.get(*index).cloned() {
+                Some(recorded) => {
+                    self.play_macro(*index, &recorded, requests);
+                    Ok(None)
+                }
+                None => Err(format!("Macro {} not found", index)),
+            },
+            ActionConfig::SetLed { index, on } => {
+                requests.push(AppRequest::SetLed { index: *index, on: *on });
+                Ok(None)
+            }
+            ActionConfig::SetPwm { channel, duty } => {
+                requests.push(AppRequest::SetPwm { channel: *channel, duty: *duty });
+                Ok(None)
+            }
+            ActionConfig::SendCommand { name, params } => self.commands.iter()
+                .find(|template| &template.name == name)
+                .ok_or_else(|| format!("Command {} not found", name))
+                .and_then(|template| {
+                    // 模板中的 {$name} 取变量的当前值
+                    let mut params = params.clone();
+                    for (name, value) in self.variables.snapshot() {
+                        params.insert(format!("${}", name), value);
+                    }
+                    render_template(&template.pattern, &params)
+                })
+                .map(|bytes| {
+                    requests.push(AppRequest::SendCommand(bytes));
+                    None
+                }),
+            ActionConfig::AllLedsOff => {
+                requests.extend((0..self.led_count).map(|index| AppRequest::SetLed { index, on: false }));
+                Ok(None)
+            }
+            ActionConfig::Sequence { steps } => {
+                requests.push(AppRequest::RunSequence { source: source.to_string(), steps: steps.clone() });
+                Ok(None)
+            }
+            ActionConfig::SetVariable { name, value } => {
+                self.variables.set(name, *value);
+                Ok(None)
+            }
+            ActionConfig::AddVariable { name, delta } => {
+                self.variables.add(name, *delta);
+                Ok(None)
+            }
+            ActionConfig::ToggleVariable { name } => {
+                self.variables.toggle(name);
+                Ok(None)
+            }
+        };
+        match command {
+            Ok(Some(command)) => self.send(command, source, action),
+            Ok(None) => self.log.record(source, action, started, Ok(())),
+            Err(e) => {
+                eprintln!("{}", e);
+                self.log.record(source, action, started, Err(e));
+            }
+        }
+    }
+
+    // 执行不由输入触发的动作（如定时任务），source 为记录到动作日志中的来源
+    pub fn run_action(&mut self, source: &str, action: &ActionConfig) -> Vec<AppRequest> {
+        let mut requests = Vec::new();
+        if self.paused {
+            return requests;
+        }
+        self.dispatch(source, action, &mut requests);
+        requests
+    }
+
+    // 录制按键按下 -> 开始录制并点亮LED；再次按下 -> 结束录制并保存
+    fn update_recording(&mut self, presses: &[usize], now: Instant, requests: &mut Vec<AppRequest>) {
+        if self.record_key.is_some_and(|key| presses.contains(&key)) {
+            match self.recording.take() {
+                None => {
+                    self.recording = Some(Recording {
+                        last_press: now,
+                        last_blink: now,
+                        led_on: true,
+                        steps: Vec::new(),
+                    });
+                    if let Some(index) = self.record_led {
+                        requests.push(AppRequest::SetLed { index, on: true });
+                    }
+                }
+                Some(recording) => {
+                    if let Some(index) = self.record_led {
+                        requests.push(AppRequest::SetLed { index, on: false });
+                    }
+                    if !recording.steps.is_empty() {
+                        let recorded = MacroConfig {
+                            name: format!("宏 {}", self.macros.len() + 1),
+                            steps: recording.steps,
+                        };
+                        self.macros.push(recorded.clone());
+                        requests.push(AppRequest::SaveMacro(recorded));
+                    }
+                }
+            }
+            return;
+        }
+
+        let Some(recording) = self.recording.as_mut() else {
+            return;
+        };
+        for &key in presses {
+            recording.steps.push(MacroStep {
+                key,
+                delay_ms: now.duration_since(recording.last_press).as_millis() as u64,
+            });
+            recording.last_press = now;
+        }
+
+        // 录制期间LED闪烁提示
+        if let Some(index) = self.record_led {
+            if now.duration_since(recording.last_blink) >= RECORD_BLINK_INTERVAL {
+                recording.led_on = !recording.led_on;
+                recording.last_blink = now;
+                requests.push(AppRequest::SetLed { index, on: recording.led_on });
+            }
+        }
+    }
+
+    // 按录制的间隔依次触发各按键绑定的动作；宏转换为动作序列由应用层执行，
+    // 每步与绑定触发的动作一样经过冷却、限速和演练模式，LED 和设备命令也按录制的间隔发出。
+    // 宏中不再嵌套回放其它宏
+    fn play_macro(&mut self, index: usize, recorded: &MacroConfig, requests: &mut Vec<AppRequest>) {
+        let mut steps = Vec::new();
+        for step in &recorded.steps {
+            let mut delay_ms = step.delay_ms;
+            for binding in &self.bindings {
+                // 回放时不执行需要安全键的绑定
+                let BindingConfig::KeyPress { key, action, guarded: false } = binding else {
+                    continue;
+                };
+                if *key != step.key || matches!(action, ActionConfig::PlayMacro { .. }) {
+                    continue;
+                }
+                steps.push(SequenceStep { delay_ms, action: action.clone(), condition: None });
+                delay_ms = 0;
+            }
+        }
+        requests.push(AppRequest::RunSequence { source: format!("macro {}", index + 1), steps });
+    }
+
+    fn send(&mut self, command: OutputCommand, source: &str, action: &ActionConfig) {
+        let log = &self.log;
+        let sender = self.sender.get_or_insert_with(|| KeySender::spawn(log.clone()));
+        if sender.tx.send((command, source.to_string(), action.clone())).is_err() {
+            // 输出线程已退出，下次重新创建
+            self.sender = None;
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// 性能模式：提高进程优先级，并在 Windows 上把系统计时器精度提高到 1 ms，
+// 让串口读取和定时任务按时唤醒，降低输入延迟；关闭时恢复默认设置。
+// 其他平台的计时器精度已足够，只支持 Windows
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+const SLEEP_SAMPLES: u32 = 20;  // 测量计时器精度时的采样次数
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PerformanceStatus {
+    pub enabled: bool,
+    pub supported: bool,
+    pub sleep_1ms_us: f64,  // 请求睡眠 1 ms 时实际经过的平均时间（微秒），越接近 1000 越好
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows_sys::Win32::Media::{timeBeginPeriod, timeEndPeriod, TIMERR_NOERROR};
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentProcess, SetPriorityClass, HIGH_PRIORITY_CLASS, NORMAL_PRIORITY_CLASS,
+    };
+
+    pub const SUPPORTED: bool = true;
+
+    pub fn apply(enabled: bool) -> Result<(), String> {
+        unsafe {
+            if enabled {
+                if timeBeginPeriod(1) != TIMERR_NOERROR {
+                    return Err("无法设置 1 ms 计时器精度".to_string());
+                }
+                if SetPriorityClass(GetCurrentProcess(), HIGH_PRIORITY_CLASS) == 0 {
+                    timeEndPeriod(1);
+                    return Err("无法提高进程优先级".to_string());
+                }
+            } else {
+                timeEndPeriod(1);
+                SetPriorityClass(GetCurrentProcess(), NORMAL_PRIORITY_CLASS);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+mod platform {
+    pub const SUPPORTED: bool = false;
+
+    pub fn apply(enabled: bool) -> Result<(), String> {
+        if enabled {
+            return Err("性能模式仅支持 Windows".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct PerformanceMode {
+    enabled: bool,
+}
+
+impl PerformanceMode {
+    // timeBeginPeriod/timeEndPeriod 需要成对调用，状态未变化时不重复设置
+    pub fn set_enabled(&mut self, enabled: bool) -> Result<(), String> {
+        if enabled == self.enabled {
+            return Ok(());
+        }
+        platform::apply(enabled)?;
+        self.enabled = enabled;
+        Ok(())
+    }
+
+    pub async fn status(&self) -> PerformanceStatus {
+        PerformanceStatus {
+            enabled: self.enabled,
+            supported: platform::SUPPORTED,
+            sleep_1ms_us: measure_sleep().await,
+        }
+    }
+}
+
+// 测量请求睡眠 1 ms 时实际睡眠的平均时间，用于比较开启前后的效果
+async fn measure_sleep() -> f64 {
+    let mut total = Duration::ZERO;
+    for _ in 0..SLEEP_SAMPLES {
+        let start = Instant::now();
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        total += start.elapsed();
+    }
+    total.as_secs_f64() * 1_000_000.0 / SLEEP_SAMPLES as f64
+}
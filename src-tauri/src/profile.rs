@@ -0,0 +1,83 @@
+// 配置方案：保存在 profiles 目录下的设备配置快照（.sjprofile），
+// 可按名称切换，例如命令行的 --profile Racing
+
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::config::MatrixConfig;
+use crate::snapshot::DeviceSnapshot;
+
+pub const PROFILE_EXTENSION: &str = "sjprofile";
+const PROFILE_DIR: &str = "profiles";
+
+pub fn profile_path(name: &str) -> Result<PathBuf, String> {
+    // 名称只能是文件名，不能指向其他目录
+    if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+        return Err(format!("无效的配置方案名称: {}", name));
+    }
+    let file = format!("{}/{}.{}", PROFILE_DIR, name, PROFILE_EXTENSION);
+    Ok(PathBuf::from(MatrixConfig::data_file_path(&file)))
+}
+
+pub fn load_profile(name: &str) -> Result<DeviceSnapshot, String> {
+    let path = profile_path(name)?;
+    if !path.exists() {
+        return Err(format!("配置方案 {} 不存在", name));
+    }
+    DeviceSnapshot::read(&path)
+}
+
+// 导入后的方案名称取文件名
+fn profile_name(file: &Path) -> Result<String, String> {
+    file.file_stem()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_string())
+        .ok_or_else(|| format!("无效的文件名: {}", file.display()))
+}
+
+// 打开 .sjprofile 文件时给前端确认用的摘要
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfilePreview {
+    pub file: String,
+    pub name: String,  // 导入后的方案名称（文件名）
+    pub created_at: u64,
+    pub source_port: String,  // 导出时设备所在的串口
+    pub report_rate: Option<u32>,
+    pub bindings: usize,
+    pub banks: usize,
+    pub macros: usize,
+    pub schedules: usize,
+    pub protocol_matches: bool,  // 帧结构与当前配置一致
+    pub exists: bool,  // 已有同名方案，导入会覆盖
+}
+
+pub fn preview(file: &Path, current: &MatrixConfig) -> Result<ProfilePreview, String> {
+    let snapshot = DeviceSnapshot::read(file)?;
+    let name = profile_name(file)?;
+    let config = &snapshot.config;
+    Ok(ProfilePreview {
+        file: file.to_string_lossy().into_owned(),
+        exists: profile_path(&name)?.exists(),
+        name,
+        created_at: snapshot.created_at,
+        source_port: snapshot.port.clone(),
+        report_rate: snapshot.report_rate,
+        bindings: config.bindings.len(),
+        banks: config.banks.len(),
+        macros: config.macros.len(),
+        schedules: config.schedules.len(),
+        protocol_matches: config.protocol == current.protocol,
+    })
+}
+
+// 把文件复制到方案目录，返回导入后的快照
+pub fn import(file: &Path) -> Result<(String, DeviceSnapshot), String> {
+    let snapshot = DeviceSnapshot::read(file)?;
+    let name = profile_name(file)?;
+    let target = profile_path(&name)?;
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("创建方案目录失败: {}", e))?;
+    }
+    snapshot.write(&target)?;
+    Ok((name, snapshot))
+}
@@ -0,0 +1,77 @@
+// PWM输出限速：每个通道最多按 max_rate_hz 发送命令，
+// 间隔内的新值只保留最后一个，间隔到达后再发送，避免旋钮快速变化时占满串口
+
+use std::time::{Duration, Instant};
+use crate::config::PwmConfig;
+
+#[derive(Default, Clone)]
+struct PwmChannel {
+    last_sent: Option<Instant>,
+    sent: Option<u8>,
+    pending: Option<u8>,
+}
+
+pub struct PwmLimiter {
+    interval: Duration,
+    channels: Vec<PwmChannel>,
+}
+
+impl PwmLimiter {
+    pub fn new(config: &PwmConfig) -> Self {
+        let mut limiter = Self {
+            interval: Duration::ZERO,
+            channels: Vec::new(),
+        };
+        limiter.update_config(config);
+        limiter
+    }
+
+    pub fn update_config(&mut self, config: &PwmConfig) {
+        self.interval = Duration::from_secs_f64(1.0 / config.max_rate_hz.max(1) as f64);
+        self.channels.resize(config.channels, PwmChannel::default());
+    }
+
+    // 请求设置占空比，返回需要立即发送的值；与已发送的值相同时不发送
+    pub fn request(&mut self, channel: usize, duty: u8, now: Instant) -> Option<u8> {
+        let interval = self.interval;
+        let state = self.channels.get_mut(channel)?;
+        if state.sent == Some(duty) {
+            state.pending = None;
+            return None;
+        }
+        if state.last_sent.is_some_and(|last| now.duration_since(last) < interval) {
+            state.pending = Some(duty);
+            return None;
+        }
+        state.pending = None;
+        state.last_sent = Some(now);
+        state.sent = Some(duty);
+        Some(duty)
+    }
+
+    // 间隔已到、仍在等待发送的值
+    pub fn take_due(&mut self, now: Instant) -> Vec<(usize, u8)> {
+        let interval = self.interval;
+        let mut due = Vec::new();
+        for (channel, state) in self.channels.iter_mut().enumerate() {
+            let Some(duty) = state.pending else {
+                continue;
+            };
+            if state.last_sent.is_some_and(|last| now.duration_since(last) < interval) {
+                continue;
+            }
+            state.pending = None;
+            state.last_sent = Some(now);
+            state.sent = Some(duty);
+            due.push((channel, duty));
+        }
+        due
+    }
+
+    // 连接断开后设备上的输出状态未知，下次请求总是发送
+    pub fn reset(&mut self) {
+        for state in &mut self.channels {
+            *state = PwmChannel::default();
+        }
+    }
+}
@@ -0,0 +1,127 @@
+// 行程学习：正常使用时记录每个轴实际到达的最小/最大读数，
+// 与配置的行程相差超过阈值时提示用户更新，由用户确认或忽略
+
+use serde::Serialize;
+use crate::config::{AxisSettings, RangeLearningConfig};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RangeProposal {
+    pub channel: usize,
+    pub stored_min: u8,
+    pub stored_max: u8,
+    pub observed_min: u8,
+    pub observed_max: u8,
+}
+
+pub struct RangeLearner {
+    config: RangeLearningConfig,
+    axes: Vec<AxisSettings>,
+    samples: u64,
+    observed: Vec<Option<(u8, u8)>>,
+    dismissed: Vec<Option<(u8, u8)>>,  // 用户忽略时的观测范围，范围继续扩大才再次提示
+    prompted: Vec<bool>,  // 已提示且尚未处理
+}
+
+impl RangeLearner {
+    pub fn new(config: &RangeLearningConfig, axes: &[AxisSettings]) -> Self {
+        Self {
+            config: config.clone(),
+            axes: axes.to_vec(),
+            samples: 0,
+            observed: Vec::new(),
+            dismissed: Vec::new(),
+            prompted: Vec::new(),
+        }
+    }
+
+    // 配置变化时保留已观测的范围
+    pub fn update_config(&mut self, config: &RangeLearningConfig, axes: &[AxisSettings]) {
+        self.config = config.clone();
+        self.axes = axes.to_vec();
+    }
+
+    // 记录一帧读数，返回新出现的提示
+    pub fn observe(&mut self, adc: &[u8]) -> Vec<RangeProposal> {
+        if !self.config.enabled {
+            return Vec::new();
+        }
+        if self.observed.len() < adc.len() {
+            self.observed.resize(adc.len(), None);
+            self.dismissed.resize(adc.len(), None);
+            self.prompted.resize(adc.len(), false);
+        }
+        for (range, &value) in self.observed.iter_mut().zip(adc) {
+            *range = Some(match *range {
+                Some((min, max)) => (min.min(value), max.max(value)),
+                None => (value, value),
+            });
+        }
+        self.samples += 1;
+        if self.samples < self.config.min_samples {
+            return Vec::new();
+        }
+
+        let mut new = Vec::new();
+        for channel in 0..self.observed.len() {
+            let proposal = self.proposal(channel);
+            // 已提示的通道在行程被修改后不再需要处理
+            if self.prompted[channel] {
+                self.prompted[channel] = proposal.is_some();
+                continue;
+            }
+            if let Some(proposal) = proposal {
+                self.prompted[channel] = true;
+                new.push(proposal);
+            }
+        }
+        new
+    }
+
+    pub fn proposals(&self) -> Vec<RangeProposal> {
+        (0..self.observed.len())
+            .filter(|&channel| self.prompted[channel])
+            .filter_map(|channel| self.proposal(channel))
+            .collect()
+    }
+
+    // 接受后返回学到的范围，之后从头观测
+    pub fn accept(&mut self, channel: usize) -> Option<(u8, u8)> {
+        let range = self.observed.get_mut(channel)?.take()?;
+        self.dismissed[channel] = None;
+        self.prompted[channel] = false;
+        Some(range)
+    }
+
+    pub fn reject(&mut self, channel: usize) {
+        if let Some(range) = self.observed.get(channel) {
+            self.dismissed[channel] = *range;
+            self.prompted[channel] = false;
+        }
+    }
+
+    fn proposal(&self, channel: usize) -> Option<RangeProposal> {
+        let (observed_min, observed_max) = self.observed[channel]?;
+        // 读数变化很小的通道没有在使用，不作判断
+        if observed_max - observed_min < self.config.min_span {
+            return None;
+        }
+        let axis = self.axes.get(channel).cloned().unwrap_or_default();
+        let threshold = self.config.threshold;
+        let differs = |a: u8, b: u8| a.abs_diff(b) >= threshold;
+        if !differs(observed_min, axis.min) && !differs(observed_max, axis.max) {
+            return None;
+        }
+        if let Some((min, max)) = self.dismissed[channel] {
+            if !differs(observed_min, min) && !differs(observed_max, max) {
+                return None;
+            }
+        }
+        Some(RangeProposal {
+            channel,
+            stored_min: axis.min,
+            stored_max: axis.max,
+            observed_min,
+            observed_max,
+        })
+    }
+}
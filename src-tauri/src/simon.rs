@@ -0,0 +1,83 @@
+// "Simon" 质检模式：随机点亮一个LED，要求操作员在限定时间内按下对应按键并计分，
+// 用于快速检查组装好的设备按键与LED接线是否正确。LED i 对应按键 i
+
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SimonEvent {
+    // 点亮了一个LED，等待按下对应按键
+    Prompt { round: u32, rounds: u32, led: usize, timeout_ms: u64 },
+    // 一轮结束；pressed 为 None 表示超时
+    Result { round: u32, expected: usize, pressed: Option<usize>, hit: bool, reaction_ms: Option<u64> },
+    Finished(SimonSummary),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimonSummary {
+    pub rounds: u32,
+    pub hits: u32,
+    pub wrong_keys: Vec<(usize, usize)>,  // （期望按键，实际按键）
+    pub timeouts: Vec<usize>,  // 超时未按下的按键
+    pub average_reaction_ms: Option<u64>,
+    pub cancelled: bool,
+}
+
+impl SimonSummary {
+    pub fn new(rounds: u32) -> Self {
+        Self {
+            rounds,
+            hits: 0,
+            wrong_keys: Vec::new(),
+            timeouts: Vec::new(),
+            average_reaction_ms: None,
+            cancelled: false,
+        }
+    }
+
+    // 记录一轮结果
+    pub fn record(&mut self, expected: usize, pressed: Option<usize>, reaction_ms: Option<u64>) -> bool {
+        let hit = pressed == Some(expected);
+        match pressed {
+            Some(key) if key != expected => self.wrong_keys.push((expected, key)),
+            None => self.timeouts.push(expected),
+            _ => {}
+        }
+        if hit {
+            // 只统计按对的反应时间
+            let total = self.average_reaction_ms.unwrap_or(0) * self.hits as u64;
+            self.hits += 1;
+            self.average_reaction_ms = Some((total + reaction_ms.unwrap_or(0)) / self.hits as u64);
+        }
+        hit
+    }
+}
+
+// 简单的 xorshift 随机数，仅用于挑选LED
+pub struct Picker {
+    state: u64,
+}
+
+impl Picker {
+    pub fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Self { state: seed | 1 }
+    }
+
+    // 在 0..count 中挑选，尽量不与上一次相同
+    pub fn pick(&mut self, count: usize, previous: Option<usize>) -> usize {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        let index = (self.state % count as u64) as usize;
+        if count > 1 && previous == Some(index) {
+            (index + 1) % count
+        } else {
+            index
+        }
+    }
+}
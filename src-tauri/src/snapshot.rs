@@ -0,0 +1,90 @@
+// 设备配置快照：把一台调好的设备的上位机配置和设备设置打包成一个文件，
+// 用于在新设备上复制相同的设置
+// 固件版本和 EEPROM 中的设置取自设备信息应答（协议配置中的 info 命令），未配置或尚未收到应答时为空；
+// 固件目前没有读取校准数据的命令，校准数据始终为空。这些字段只用于核对，应用快照时不写入设备
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::config::MatrixConfig;
+use crate::response::DecodedResponse;
+
+const SNAPSHOT_FORMAT_VERSION: u32 = 2;
+
+// 设备信息应答中的固件版本字段，其余非数量字段视为 EEPROM 中的设置
+pub const FIRMWARE_VERSION_FIELD: &str = "firmware_version";
+const CAPABILITY_FIELDS: [&str; 4] = ["key_count", "adc_count", "led_count", "led_dimming"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceSnapshot {
+    pub format_version: u32,
+    pub created_at: u64,  // 毫秒时间戳
+    pub port: String,  // 导出时设备所在的串口
+    pub report_rate: Option<u32>,  // 设备的上报频率（Hz）
+    #[serde(default)]
+    pub firmware_version: Option<u32>,
+    #[serde(default)]
+    pub eeprom: Option<BTreeMap<String, f64>>,  // 设备信息应答中的设置
+    #[serde(default)]
+    pub calibration: Option<BTreeMap<String, f64>>,  // 预留，固件支持读取校准数据后填写
+    pub config: MatrixConfig,  // 上位机配置
+}
+
+impl DeviceSnapshot {
+    // info 为设备最近一次的信息应答
+    pub fn capture(config: &MatrixConfig, info: Option<&DecodedResponse>) -> Self {
+        let port = config.serial_matrix.port.clone();
+        Self {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            report_rate: config.report_rates.get(&port).copied(),
+            firmware_version: info
+                .and_then(|info| info.fields.get(FIRMWARE_VERSION_FIELD))
+                .map(|&version| version as u32),
+            eeprom: info.map(|info| {
+                info.fields
+                    .iter()
+                    .filter(|(name, _)| *name != FIRMWARE_VERSION_FIELD && !CAPABILITY_FIELDS.contains(&name.as_str()))
+                    .map(|(name, &value)| (name.clone(), value))
+                    .collect()
+            }),
+            calibration: None,
+            port,
+            config: config.clone(),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, content).map_err(|e| format!("写入快照失败: {}", e))
+    }
+
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("读取快照失败: {}", e))?;
+        let snapshot: Self =
+            serde_json::from_str(&content).map_err(|e| format!("快照格式错误: {}", e))?;
+        if snapshot.format_version > SNAPSHOT_FORMAT_VERSION {
+            return Err(format!("不支持的快照版本 {}", snapshot.format_version));
+        }
+        Ok(snapshot)
+    }
+
+    // 生成应用到目标设备的配置：快照中的串口（port 和 serial_matrix）不会应用，
+    // 保留目标设备自己的串口设置，设备设置按目标串口记录
+    pub fn into_config_for(self, current: &MatrixConfig) -> MatrixConfig {
+        let mut config = self.config;
+        config.serial_matrix = current.serial_matrix.clone();
+        config.serial_screen = current.serial_screen.clone();
+        config.report_rates = current.report_rates.clone();
+        if let Some(hz) = self.report_rate {
+            config.report_rates.insert(current.serial_matrix.port.clone(), hz);
+        }
+        config
+    }
+}
@@ -0,0 +1,114 @@
+// 老化测试：在设定时长内统计帧数、错误、重连次数和帧间隔，
+// 结束后按阈值给出是否通过，出货前用于检验设备
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use crate::config::SoakConfig;
+use crate::events::AppEvent;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SoakReport {
+    pub finished: bool,
+    pub passed: bool,
+    pub elapsed_secs: u64,
+    pub duration_secs: u64,
+    pub frames: u64,
+    pub frame_errors: u64,
+    pub reconnects: u64,
+    pub disconnects: u64,
+    pub min_gap_ms: Option<u64>,  // 相邻有效帧的最小间隔
+    pub max_gap_ms: Option<u64>,  // 相邻有效帧的最大间隔
+    pub failures: Vec<String>,  // 未达标的项目
+}
+
+pub struct SoakTest {
+    pub id: u64,  // 区分先后启动的测试，避免旧测试的计时结束新测试
+    config: SoakConfig,
+    duration: Duration,
+    started: Instant,
+    frames: u64,
+    frame_errors: u64,
+    reconnects: u64,
+    disconnects: u64,
+    last_frame: Option<Instant>,
+    min_gap: Option<Duration>,
+    max_gap: Option<Duration>,
+}
+
+impl SoakTest {
+    pub fn new(id: u64, config: &SoakConfig, duration: Duration) -> Self {
+        Self {
+            id,
+            config: config.clone(),
+            duration,
+            started: Instant::now(),
+            frames: 0,
+            frame_errors: 0,
+            reconnects: 0,
+            disconnects: 0,
+            last_frame: None,
+            min_gap: None,
+            max_gap: None,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::Frame(_) => {
+                let now = Instant::now();
+                if let Some(last) = self.last_frame {
+                    let gap = now.duration_since(last);
+                    self.min_gap = Some(self.min_gap.map_or(gap, |g| g.min(gap)));
+                    self.max_gap = Some(self.max_gap.map_or(gap, |g| g.max(gap)));
+                }
+                self.last_frame = Some(now);
+                self.frames += 1;
+            }
+            AppEvent::FrameError(_) => self.frame_errors += 1,
+            AppEvent::Connection { connected: true, .. } => {
+                self.reconnects += 1;
+                // 断线期间的间隔不计入帧间隔
+                self.last_frame = None;
+            }
+            AppEvent::Connection { connected: false, .. } => self.disconnects += 1,
+            _ => {}
+        }
+    }
+
+    // 生成当前的报告；finished 为 true 时按阈值判定
+    pub fn report(&self, finished: bool) -> SoakReport {
+        let elapsed = self.started.elapsed();
+        let max_gap_ms = self.max_gap.map(|g| g.as_millis() as u64);
+        let mut failures = Vec::new();
+        if self.frames < self.config.min_frames {
+            failures.push(format!("有效帧 {} 少于 {}", self.frames, self.config.min_frames));
+        }
+        if self.frame_errors > self.config.max_frame_errors {
+            failures.push(format!("校验错误 {} 超过 {}", self.frame_errors, self.config.max_frame_errors));
+        }
+        if self.reconnects > self.config.max_reconnects {
+            failures.push(format!("重连 {} 次超过 {}", self.reconnects, self.config.max_reconnects));
+        }
+        if let Some(gap) = max_gap_ms.filter(|gap| *gap > self.config.max_gap_ms) {
+            failures.push(format!("最大帧间隔 {} ms 超过 {} ms", gap, self.config.max_gap_ms));
+        }
+        // 提前停止的测试视为未通过
+        if finished && elapsed < self.duration {
+            failures.push(format!("测试在 {} 秒时被提前停止", elapsed.as_secs()));
+        }
+
+        SoakReport {
+            finished,
+            passed: finished && failures.is_empty(),
+            elapsed_secs: elapsed.as_secs(),
+            duration_secs: self.duration.as_secs(),
+            frames: self.frames,
+            frame_errors: self.frame_errors,
+            reconnects: self.reconnects,
+            disconnects: self.disconnects,
+            min_gap_ms: self.min_gap.map(|g| g.as_millis() as u64),
+            max_gap_ms,
+            failures,
+        }
+    }
+}
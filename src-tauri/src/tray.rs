@@ -1,8 +1,10 @@
-use tauri::{menu::{Menu, MenuItem, PredefinedMenuItem}, tray::TrayIconBuilder, Manager, Runtime};
+use tauri::{menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem}, tray::TrayIconBuilder, Manager, Runtime};
 
 // 托盘文本配置
 struct TrayTexts {
     show_window: String,
+    leds_off: String,
+    pause_output: String,
     quit: String,
 }
 
@@ -10,38 +12,72 @@ impl Default for TrayTexts {
     fn default() -> Self {
         TrayTexts {
             show_window: "显示主窗口".to_string(),
+            leds_off: "关闭所有LED".to_string(),
+            pause_output: "暂停输出".to_string(),
             quit: "退出应用 (Exit)".to_string(),
         }
     }
 }
 
-pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
+// 暂停菜单项，按键或命令切换暂停时同步勾选状态
+pub struct PauseMenuItem<R: Runtime>(CheckMenuItem<R>);
+
+pub fn set_paused_checked<R: Runtime>(app: &tauri::AppHandle<R>, paused: bool) {
+    if let Some(item) = app.try_state::<PauseMenuItem<R>>() {
+        let _ = item.0.set_checked(paused);
+    }
+}
+
+pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>, leds_enabled: bool) -> tauri::Result<()> {
     // 获取托盘文本（目前固定为中文）
     let texts = TrayTexts::default();
 
     // 定义菜单项
     let show_window = MenuItem::with_id(app, "show_window", &texts.show_window, true, None::<&str>)?;
+    let leds_off = CheckMenuItem::with_id(app, "leds_off", &texts.leds_off, true, !leds_enabled, None::<&str>)?;
+    let pause_output = CheckMenuItem::with_id(app, "pause_output", &texts.pause_output, true, false, None::<&str>)?;
     let quit = MenuItem::with_id(app, "quit", &texts.quit, true, None::<&str>)?;
     let separator = PredefinedMenuItem::separator(app)?;
 
     // 构建菜单
     let menu = Menu::with_items(app, &[
         &show_window,
+        &leds_off,
+        &pause_output,
         &separator,
         &quit,
     ])?;
 
+    app.manage(PauseMenuItem(pause_output.clone()));
+
     // 构建托盘图标
     let _ = TrayIconBuilder::with_id("main")
         .menu(&menu)
         .icon(app.default_window_icon().unwrap().clone())
-        .on_menu_event(|app: &tauri::AppHandle<R>, event: tauri::menu::MenuEvent| match event.id().as_ref() {
+        .on_menu_event(move |app: &tauri::AppHandle<R>, event: tauri::menu::MenuEvent| match event.id().as_ref() {
             "show_window" => {
                 if let Some(window) = app.get_webview_window("main") {
                     let _ = window.show();
                     let _ = window.set_focus();
                 }
             }
+            "leds_off" => {
+                // 勾选状态在点击时已自动切换
+                let enabled = !leds_off.is_checked().unwrap_or(false);
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    let state = app.state::<crate::AppState>();
+                    crate::set_leds_enabled(&state, enabled).await;
+                });
+            }
+            "pause_output" => {
+                let paused = pause_output.is_checked().unwrap_or(false);
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    let state = app.state::<crate::AppState>();
+                    crate::set_paused(&app, &state, paused).await;
+                });
+            }
             "quit" => {
                 app.exit(0);
             }
@@ -0,0 +1,50 @@
+// 变量存储：动作设置的整数变量（计数器、开关等），供监视表达式和命令模板读取；
+// 值变化时发布事件。输出引擎和事件订阅者都会访问，使用同步锁
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use crate::events::{AppEvent, EventBus};
+
+#[derive(Clone)]
+pub struct Variables {
+    values: Arc<Mutex<BTreeMap<String, i64>>>,
+    bus: EventBus,
+}
+
+impl Variables {
+    pub fn new(initial: &BTreeMap<String, i64>, bus: &EventBus) -> Self {
+        Self {
+            values: Arc::new(Mutex::new(initial.clone())),
+            bus: bus.clone(),
+        }
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, i64> {
+        self.values.lock().unwrap().clone()
+    }
+
+    // 由当前值（不存在时为 0）计算新值，返回新值
+    fn update(&self, name: &str, change: impl FnOnce(i64) -> i64) -> i64 {
+        let mut values = self.values.lock().unwrap();
+        let current = values.get(name).copied();
+        let value = change(current.unwrap_or(0));
+        if current != Some(value) {
+            values.insert(name.to_string(), value);
+            drop(values);
+            self.bus.publish(AppEvent::VariableChanged { name: name.to_string(), value });
+        }
+        value
+    }
+
+    pub fn set(&self, name: &str, value: i64) -> i64 {
+        self.update(name, |_| value)
+    }
+
+    pub fn add(&self, name: &str, delta: i64) -> i64 {
+        self.update(name, |current| current.saturating_add(delta))
+    }
+
+    pub fn toggle(&self, name: &str) -> i64 {
+        self.update(name, |current| if current == 0 { 1 } else { 0 })
+    }
+}
@@ -0,0 +1,144 @@
+// Webhook：作为事件总线的订阅者，把选定的事件以 JSON POST 到配置的地址，
+// 便于接入 IFTTT、n8n 等自动化工具；每个请求在独立任务中发送和重试，不阻塞事件处理
+
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use crate::config::{WebhookConfig, WebhookTrigger};
+use crate::events::{AppEvent, DisconnectReason};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// 事件满足触发条件时返回事件名称和模板变量
+fn event_variables(trigger: &WebhookTrigger, event: &AppEvent) -> Option<(&'static str, Map<String, Value>)> {
+    let mut variables = Map::new();
+    let name = match (trigger, event) {
+        (WebhookTrigger::KeyPressed { key }, AppEvent::KeyEdge(edge)) if edge.pressed && edge.index == *key => {
+            variables.insert("key".into(), json!(edge.index));
+            "key_pressed"
+        }
+        (WebhookTrigger::KeyReleased { key }, AppEvent::KeyEdge(edge)) if !edge.pressed && edge.index == *key => {
+            variables.insert("key".into(), json!(edge.index));
+            "key_released"
+        }
+        (WebhookTrigger::Connected, AppEvent::Connection { connected: true, port, .. }) => {
+            variables.insert("port".into(), json!(port));
+            "connected"
+        }
+        (WebhookTrigger::Disconnected, AppEvent::Connection { connected: false, port, reason }) => {
+            variables.insert("port".into(), json!(port));
+            variables.insert("reason".into(), json!(reason));
+            "disconnected"
+        }
+        (
+            WebhookTrigger::ConnectionLost,
+            AppEvent::Connection { connected: false, port, reason: Some(DisconnectReason::Lost) },
+        ) => {
+            variables.insert("port".into(), json!(port));
+            "connection_lost"
+        }
+        (WebhookTrigger::BankChanged, AppEvent::BankChanged { index, name }) => {
+            variables.insert("bank".into(), json!(name));
+            variables.insert("bank_index".into(), json!(index));
+            "bank_changed"
+        }
+        (WebhookTrigger::ProfileChanged, AppEvent::ConfigChanged { pointer }) if pointer.is_empty() => "profile_changed",
+        _ => return None,
+    };
+    variables.insert("event".into(), json!(name));
+    variables.insert("timestamp".into(), json!(now_ms()));
+    Some((name, variables))
+}
+
+// 按模板生成请求体；变量按 JSON 字符串转义，可放在字符串内，数字也可直接放在值的位置
+fn render_body(template: &str, variables: &Map<String, Value>) -> String {
+    let mut body = template.to_string();
+    for (name, value) in variables {
+        let text = match value {
+            Value::String(s) => {
+                let quoted = Value::String(s.clone()).to_string();
+                quoted[1..quoted.len() - 1].to_string()
+            }
+            Value::Null => String::new(),
+            other => other.to_string(),
+        };
+        body = body.replace(&format!("{{{}}}", name), &text);
+    }
+    body
+}
+
+// 配置中与该事件匹配的请求：(Webhook, 请求体)
+pub fn requests_for(webhooks: &[WebhookConfig], event: &AppEvent) -> Vec<(WebhookConfig, String)> {
+    let mut requests = Vec::new();
+    for webhook in webhooks.iter().filter(|w| w.enabled) {
+        let Some((_, mut variables)) = webhook.events.iter().find_map(|trigger| event_variables(trigger, event)) else {
+            continue;
+        };
+        variables.insert("webhook".into(), json!(webhook.name));
+        let body = match &webhook.body {
+            Some(template) => render_body(template, &variables),
+            None => Value::Object(variables).to_string(),
+        };
+        requests.push((webhook.clone(), body));
+    }
+    requests
+}
+
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+}
+
+// 按各 Webhook 的 cooldown_ms 限制发送频率
+#[derive(Default)]
+pub struct WebhookThrottle {
+    last_sent: HashMap<String, Instant>,
+}
+
+impl WebhookThrottle {
+    // 允许发送时记下发送时间
+    pub fn allow(&mut self, webhook: &WebhookConfig) -> bool {
+        if webhook.cooldown_ms == 0 {
+            return true;
+        }
+        let now = Instant::now();
+        let cooldown = Duration::from_millis(webhook.cooldown_ms);
+        if self.last_sent.get(&webhook.name).is_some_and(|last| now.duration_since(*last) < cooldown) {
+            return false;
+        }
+        self.last_sent.insert(webhook.name.clone(), now);
+        true
+    }
+}
+
+// 发送一次 Webhook，失败时按配置重试
+pub async fn deliver(client: reqwest::Client, webhook: WebhookConfig, body: String) {
+    let attempts = webhook.attempts.max(1);
+    for attempt in 1..=attempts {
+        let result = client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .body(body.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => return,
+            Err(e) => {
+                eprintln!("Webhook {} failed (attempt {}/{}): {}", webhook.name, attempt, attempts, e);
+                if attempt < attempts {
+                    tokio::time::sleep(Duration::from_millis(webhook.retry_delay_ms)).await;
+                }
+            }
+        }
+    }
+}
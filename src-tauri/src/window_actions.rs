@@ -0,0 +1,155 @@
+// 窗口管理动作：聚焦应用、最小化/最大化前台窗口、切换虚拟桌面
+// 各平台实现不同：Windows 直接调用 Win32 API，macOS 使用 osascript 和系统快捷键，
+// Linux 依赖 wmctrl / xdotool
+
+use enigo::{Enigo, Key};
+use crate::output::click_combo;
+
+// ========== Windows ==========
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::*;
+    use windows_sys::Win32::Foundation::{BOOL, HWND, LPARAM};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        EnumWindows, GetForegroundWindow, GetWindowTextW, IsIconic, IsWindowVisible,
+        SetForegroundWindow, ShowWindow, SW_MAXIMIZE, SW_MINIMIZE, SW_RESTORE,
+    };
+
+    struct Search {
+        name: String,
+        found: HWND,
+    }
+
+    // 枚举顶层窗口，查找标题中包含指定名称的可见窗口
+    unsafe extern "system" fn enum_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let search = &mut *(lparam as *mut Search);
+        if IsWindowVisible(hwnd) == 0 {
+            return 1;
+        }
+        let mut buf = [0u16; 512];
+        let len = GetWindowTextW(hwnd, buf.as_mut_ptr(), buf.len() as i32);
+        if len <= 0 {
+            return 1;
+        }
+        let title = String::from_utf16_lossy(&buf[..len as usize]).to_lowercase();
+        if title.contains(&search.name) {
+            search.found = hwnd;
+            return 0;
+        }
+        1
+    }
+
+    pub fn focus_app(_enigo: &mut Enigo, name: &str) -> Result<(), String> {
+        let mut search = Search {
+            name: name.to_lowercase(),
+            found: std::ptr::null_mut(),
+        };
+        unsafe {
+            EnumWindows(Some(enum_callback), &mut search as *mut Search as LPARAM);
+            if search.found.is_null() {
+                return Err(format!("未找到窗口: {}", name));
+            }
+            if IsIconic(search.found) != 0 {
+                ShowWindow(search.found, SW_RESTORE);
+            }
+            SetForegroundWindow(search.found);
+        }
+        Ok(())
+    }
+
+    fn show_foreground(cmd: i32) -> Result<(), String> {
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.is_null() {
+                return Err("没有前台窗口".to_string());
+            }
+            ShowWindow(hwnd, cmd);
+        }
+        Ok(())
+    }
+
+    pub fn minimize_window(_enigo: &mut Enigo) -> Result<(), String> {
+        show_foreground(SW_MINIMIZE)
+    }
+
+    pub fn maximize_window(_enigo: &mut Enigo) -> Result<(), String> {
+        show_foreground(SW_MAXIMIZE)
+    }
+
+    pub fn switch_desktop(enigo: &mut Enigo, next: bool) -> Result<(), String> {
+        let arrow = if next { Key::RightArrow } else { Key::LeftArrow };
+        click_combo(enigo, &[Key::Control, Key::Meta, arrow])
+    }
+}
+
+// ========== macOS ==========
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+    use std::process::Command;
+
+    pub fn focus_app(_enigo: &mut Enigo, name: &str) -> Result<(), String> {
+        let script = format!("tell application \"{}\" to activate", name.replace('"', "\\\""));
+        let status = Command::new("osascript")
+            .args(["-e", &script])
+            .status()
+            .map_err(|e| e.to_string())?;
+        if !status.success() {
+            return Err(format!("未找到应用: {}", name));
+        }
+        Ok(())
+    }
+
+    pub fn minimize_window(enigo: &mut Enigo) -> Result<(), String> {
+        click_combo(enigo, &[Key::Meta, Key::Unicode('m')])
+    }
+
+    pub fn maximize_window(enigo: &mut Enigo) -> Result<(), String> {
+        click_combo(enigo, &[Key::Control, Key::Meta, Key::Unicode('f')])
+    }
+
+    pub fn switch_desktop(enigo: &mut Enigo, next: bool) -> Result<(), String> {
+        let arrow = if next { Key::RightArrow } else { Key::LeftArrow };
+        click_combo(enigo, &[Key::Control, arrow])
+    }
+}
+
+// ========== Linux ==========
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    use super::*;
+    use std::process::Command;
+
+    fn run(program: &str, args: &[&str]) -> Result<bool, String> {
+        let status = Command::new(program)
+            .args(args)
+            .status()
+            .map_err(|e| format!("无法执行 {}（是否已安装？）: {}", program, e))?;
+        Ok(status.success())
+    }
+
+    pub fn focus_app(_enigo: &mut Enigo, name: &str) -> Result<(), String> {
+        if !run("wmctrl", &["-a", name])? {
+            return Err(format!("未找到窗口: {}", name));
+        }
+        Ok(())
+    }
+
+    pub fn minimize_window(_enigo: &mut Enigo) -> Result<(), String> {
+        run("xdotool", &["getactivewindow", "windowminimize"]).map(|_| ())
+    }
+
+    pub fn maximize_window(_enigo: &mut Enigo) -> Result<(), String> {
+        run("wmctrl", &["-r", ":ACTIVE:", "-b", "add,maximized_vert,maximized_horz"]).map(|_| ())
+    }
+
+    pub fn switch_desktop(enigo: &mut Enigo, next: bool) -> Result<(), String> {
+        let arrow = if next { Key::RightArrow } else { Key::LeftArrow };
+        click_combo(enigo, &[Key::Control, Key::Alt, arrow])
+    }
+}
+
+pub use platform::{focus_app, maximize_window, minimize_window, switch_desktop};
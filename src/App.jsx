@@ -1,19 +1,31 @@
 import { useState, useEffect } from 'react';
-import { Card, Button, Select, message, Row, Col, Space, Tabs, Typography, Statistic, Progress } from 'antd';
+import { Card, Button, Select, message, Modal, Row, Col, Space, Tabs, Typography, Statistic, Progress } from 'antd';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { useTranslation } from 'react-i18next';
 import './App.css';
 import './i18n';
 
 const { Title, Text } = Typography;
 
+const isVirtualPort = (name) => /^(tcp|rfc2217|mock):\/\//.test(name || '');
+
 function App() {
   // 翻译钩子
   const { t, i18n } = useTranslation();
   
+  // 后端错误带有消息代码，按当前语言显示；没有代码时显示原文
+  const formatError = (err) => {
+    if (err && typeof err === 'object' && err.code) {
+      return t(`backend.${err.code}`, { ...err.params, defaultValue: err.message });
+    }
+    return String(err);
+  };
+  
   // 状态管理
   const [ports, setPorts] = useState([]);
   const [selectedPort, setSelectedPort] = useState('');
+  const [portSearch, setPortSearch] = useState('');  // 串口框中输入的文字，可以是远程串口地址
   const [baudRate, setBaudRate] = useState(9600);
   const [isConnected, setIsConnected] = useState(false);
   const [isLoading, setIsLoading] = useState(false);
@@ -24,6 +36,7 @@ function App() {
   const [keyNames, setKeyNames] = useState(Array(24).fill(''));
   const [adcNames, setAdcNames] = useState(Array(14).fill(''));
   const [ledNames, setLedNames] = useState(Array(20).fill(''));
+  const [labelMeta, setLabelMeta] = useState({ key_meta: {}, adc_meta: {}, led_meta: {} });
   const [isEditingNames, setIsEditingNames] = useState(false);
   
   // 设备校准状态
@@ -85,6 +98,11 @@ function App() {
     try {
       const portList = await invoke('list_serial_ports');
       setPorts(portList);
+      // 尚未选择串口且只有一个 USB 串口时自动选中
+      const usbPorts = portList.filter(port => port.vid != null);
+      if (usbPorts.length === 1) {
+        setSelectedPort(prev => prev || usbPorts[0].name);
+      }
     } catch (err) {
       message.error(t('serial.refreshPortsError'));
     }
@@ -98,15 +116,20 @@ function App() {
       setBaudRate(config.serial_matrix.baud_rate);
       
       // 加载自定义名称
-      if (config.key_names && config.key_names.length === 24) {
-        setKeyNames(config.key_names);
+      if (config.labels.keys.length === 24) {
+        setKeyNames(config.labels.keys);
       }
-      if (config.adc_names && config.adc_names.length === 14) {
-        setAdcNames(config.adc_names);
+      if (config.labels.adcs.length === 14) {
+        setAdcNames(config.labels.adcs);
       }
-      if (config.led_names && config.led_names.length === 20) {
-        setLedNames(config.led_names);
+      if (config.labels.leds.length === 20) {
+        setLedNames(config.labels.leds);
       }
+      setLabelMeta({
+        key_meta: config.labels.key_meta,
+        adc_meta: config.labels.adc_meta,
+        led_meta: config.labels.led_meta
+      });
     } catch (err) {
       message.error(t('serial.loadConfigError'));
     }
@@ -119,15 +142,18 @@ function App() {
       await invoke('save_config', {
         newConfig: {
           ...config,
-          key_names: keyNames,
-          adc_names: adcNames,
-          led_names: ledNames
+          labels: {
+            ...config.labels,
+            keys: keyNames,
+            adcs: adcNames,
+            leds: ledNames
+          }
         }
       });
       setIsEditingNames(false);
       message.success(t('naming.saveSuccess'));
     } catch (err) {
-      message.error(t('naming.saveError', { error: err }));
+      message.error(t('naming.saveError', { error: formatError(err) }));
     }
   };
   
@@ -165,7 +191,7 @@ function App() {
       await invoke('send_calibration_command', { command });
       message.success(t('calibration.sendSuccess'));
     } catch (err) {
-      message.error(t('calibration.sendError', { error: err }));
+      message.error(t('calibration.sendError', { error: formatError(err) }));
     }
   };
   
@@ -180,7 +206,7 @@ function App() {
       await invoke('send_calibration_command', { command });
       message.success(t('ledTest.sendSuccess'));
     } catch (err) {
-      message.error(t('ledTest.sendError', { error: err }));
+      message.error(t('ledTest.sendError', { error: formatError(err) }));
     }
   };
   
@@ -349,7 +375,7 @@ function App() {
               <Button onClick={() => {
                 navigator.clipboard.writeText(calibrationCommand)
                   .then(() => message.success(t('calibration.copySuccess')))
-                  .catch(err => message.error(t('calibration.copyError', { error: err })));
+                  .catch(err => message.error(t('calibration.copyError', { error: formatError(err) })));
               }} disabled={!calibrationCommand}>
                 {t('calibration.copyCommand')}
               </Button>
@@ -474,6 +500,20 @@ function App() {
       return;
     }
 
+    // 先检查权限，Linux 上无权访问时直接给出处理方法
+    try {
+      const permission = await invoke('check_port_permissions', { port: selectedPort });
+      if (permission.exists && !permission.accessible) {
+        Modal.warning({
+          title: t('serial.permissionTitle', { port: selectedPort }),
+          content: permission.hint
+        });
+        return;
+      }
+    } catch (err) {
+      // 检查失败不影响连接
+    }
+
     setIsLoading(true);
     try {
       await invoke('connect_matrix', {
@@ -483,7 +523,25 @@ function App() {
       setIsConnected(true);
       message.success(t('serial.connectSuccess'));
     } catch (err) {
-      message.error(t('serial.connectError', { error: err }));
+      if (err?.code === 'serial.portBusy') {
+        // 被其他程序占用时提供等待释放后重连
+        Modal.confirm({
+          title: t('serial.busyTitle', { port: selectedPort }),
+          content: err.params?.hint,
+          okText: t('serial.forceReconnect'),
+          onOk: async () => {
+            try {
+              await invoke('force_reconnect');
+              setIsConnected(true);
+              message.success(t('serial.connectSuccess'));
+            } catch (retryErr) {
+              message.error(t('serial.forceReconnectError', { error: formatError(retryErr) }));
+            }
+          }
+        });
+      } else {
+        message.error(t('serial.connectError', { error: formatError(err) }));
+      }
     } finally {
       setIsLoading(false);
     }
@@ -496,7 +554,7 @@ function App() {
       setIsConnected(false);
       message.success(t('serial.disconnectSuccess'));
     } catch (err) {
-      message.error(t('serial.disconnectError', { error: err }));
+      message.error(t('serial.disconnectError', { error: formatError(err) }));
     }
   };
 
@@ -513,7 +571,7 @@ function App() {
     } catch (err) {
       // 只在错误计数小于5时显示错误提示，最多显示5次
       if (refreshErrorCount < 5) {
-        message.error(t('data.refreshError', { error: err }));
+        message.error(t('data.refreshError', { error: formatError(err) }));
       }
       // 增加错误计数
       setRefreshErrorCount(prevCount => prevCount + 1);
@@ -540,6 +598,242 @@ function App() {
     };
   }, [isConnected, refreshInterval]);
   
+  // 配置文件损坏时提示用户（已从备份恢复、已使用默认配置或部分设置换成了默认值）
+  useEffect(() => {
+    invoke('get_startup_report').then((report) => {
+      const { status, error, corrupted_copy, repairs } = report.config_status;
+      if (status === 'recovered_from_backup' || status === 'defaulted') {
+        Modal.warning({
+          title: t(`configStatus.${status}.title`),
+          content: t(`configStatus.${status}.content`, { error, file: corrupted_copy })
+        });
+      } else if (status === 'repaired') {
+        Modal.warning({
+          title: t('configStatus.repaired.title'),
+          content: t('configStatus.repaired.content', { problems: repairs.join('; ') })
+        });
+      }
+    }).catch(() => {});
+  }, []);
+  
+  // 后端也可能自行连接或断开（命令行参数、自动重连），以后端的连接状态为准
+  useEffect(() => {
+    invoke('is_matrix_connected').then(setIsConnected).catch(() => {});
+    const unlisten = listen('connection-changed', (event) => {
+      setIsConnected(event.payload.connected);
+      loadConfig();
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+  
+  // 插拔串口设备时刷新串口列表
+  useEffect(() => {
+    const unlistenAdded = listen('port-added', refreshPorts);
+    const unlistenRemoved = listen('port-removed', refreshPorts);
+    return () => {
+      unlistenAdded.then((fn) => fn());
+      unlistenRemoved.then((fn) => fn());
+    };
+  }, []);
+  
+  // 行程学习发现某个轴的实际范围与配置不同，由用户决定是否更新
+  useEffect(() => {
+    const unlisten = listen('range-proposal', ({ payload }) => {
+      const channel = payload.channel;
+      Modal.confirm({
+        title: t('rangeLearning.title', { channel: channel + 1 }),
+        content: t('rangeLearning.content', {
+          storedMin: payload.stored_min,
+          storedMax: payload.stored_max,
+          observedMin: payload.observed_min,
+          observedMax: payload.observed_max
+        }),
+        okText: t('rangeLearning.accept'),
+        cancelText: t('rangeLearning.reject'),
+        onOk: () => invoke('accept_range_proposal', { channel })
+          .then(() => message.success(t('rangeLearning.accepted')))
+          .catch(err => message.error(t('rangeLearning.acceptError', { error: formatError(err) }))),
+        onCancel: () => invoke('reject_range_proposal', { channel }).catch(() => {})
+      });
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [t]);
+  
+  // 输出被暂停按键、托盘菜单或命令暂停/恢复
+  useEffect(() => {
+    const unlisten = listen('pause-changed', ({ payload }) => {
+      if (payload.paused) {
+        message.warning(t('pause.paused'));
+      } else {
+        message.info(t('pause.resumed'));
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [t]);
+  
+  // 按键矩阵中疑似鬼键的组合
+  useEffect(() => {
+    const unlisten = listen('ghosting-detected', ({ payload }) => {
+      message.warning(t('ghosting.detected', {
+        keys: payload.keys.map(key => key + 1).join(', '),
+        count: payload.occurrences
+      }), 8);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [t]);
+  
+  // 后端下载固件的进度，显示速度和剩余时间
+  useEffect(() => {
+    const unlisten = listen('bootloader-progress', ({ payload }) => {
+      setUpgradeProgress(Math.round((payload.sent * 100) / payload.total));
+      setUpgradeMessage(t('firmwareUpgrade.progressDetail', {
+        sent: payload.sent,
+        total: payload.total,
+        speed: (payload.bytes_per_sec / 1024).toFixed(1),
+        eta: payload.eta_ms == null ? '-' : Math.ceil(payload.eta_ms / 1000),
+        retries: payload.retries
+      }));
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [t]);
+
+  // 通过链接或文件打开的固件，确认后由后端下载到配置的串口
+  useEffect(() => {
+    const confirmFlash = async (file) => {
+      if (!file) return;
+      setActiveTab('firmwareUpgrade');
+      const config = await invoke('get_config');
+      // 先试运行，确认前显示分片数量和预计耗时
+      let plan = null;
+      try {
+        plan = await invoke('flash_firmware', { filePath: file, port: config.serial_matrix.port, useCrc: true, dryRun: true });
+      } catch (err) {
+        message.error(t('firmwareUpgrade.upgradeError', { error: formatError(err) }));
+        return;
+      }
+      Modal.confirm({
+        title: t('firmwareUpgrade.openedTitle'),
+        content: (
+          <>
+            <p>{t('firmwareUpgrade.openedContent', { file })}</p>
+            <p>{t('firmwareUpgrade.planSummary', {
+              size: plan.size,
+              chunks: plan.chunks.length,
+              seconds: Math.ceil(plan.estimated_ms / 1000)
+            })}</p>
+          </>
+        ),
+        onOk: async () => {
+          setUpgradeStatus('upgrading');
+          try {
+            await invoke('flash_firmware', { filePath: file, port: config.serial_matrix.port, useCrc: true });
+            setUpgradeStatus('completed');
+            message.success(t('firmwareUpgrade.upgradeSuccess'));
+          } catch (err) {
+            setUpgradeStatus('error');
+            message.error(t('firmwareUpgrade.upgradeError', { error: formatError(err) }));
+          }
+        }
+      });
+    };
+    const takeRequest = () => invoke('take_flash_request').then(confirmFlash).catch(() => {});
+    takeRequest();
+    const unlisten = listen('flash-requested', takeRequest);
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+  
+  // 打开的配置方案文件，显示摘要并确认后导入
+  useEffect(() => {
+    const confirmImport = (preview) => {
+      if (!preview) return;
+      Modal.confirm({
+        title: t('profile.importTitle', { name: preview.name }),
+        content: (
+          <Space direction="vertical">
+            <Text>{t('profile.summary', preview)}</Text>
+            <Text>{t('profile.source', { port: preview.source_port, date: new Date(preview.created_at).toLocaleString() })}</Text>
+            {!preview.protocol_matches && <Text type="warning">{t('profile.protocolMismatch')}</Text>}
+            {preview.exists && <Text type="warning">{t('profile.overwrite')}</Text>}
+          </Space>
+        ),
+        onOk: async () => {
+          try {
+            await invoke('import_profile', { file: preview.file });
+            await loadConfig();
+            message.success(t('profile.importSuccess', { name: preview.name }));
+          } catch (err) {
+            message.error(t('profile.importError', { error: formatError(err) }));
+          }
+        }
+      });
+    };
+    const takeRequest = () => invoke('take_profile_request').then(confirmImport).catch(() => {});
+    takeRequest();
+    const unlisten = listen('profile-opened', takeRequest);
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // serialjoy:// 链接请求的切换方案或连接，确认后才执行
+  useEffect(() => {
+    const confirmLink = async (request) => {
+      if (!request) return;
+      if (request.kind === 'profile') {
+        Modal.confirm({
+          title: t('profile.linkTitle', { name: request.name }),
+          content: t('profile.linkContent'),
+          onOk: async () => {
+            try {
+              await invoke('load_profile', { name: request.name });
+              await loadConfig();
+              message.success(t('profile.importSuccess', { name: request.name }));
+            } catch (err) {
+              message.error(t('profile.loadError', { error: formatError(err) }));
+            }
+          }
+        });
+        return;
+      }
+      const config = await invoke('get_config');
+      const port = request.port ?? config.serial_matrix.port;
+      const baud = request.baud ?? config.serial_matrix.baud_rate;
+      Modal.confirm({
+        title: t('serial.linkConnectTitle'),
+        content: t('serial.linkConnectContent', { port, baud }),
+        onOk: async () => {
+          try {
+            await invoke('connect_matrix', { port, baudRate: baud });
+            setSelectedPort(port);
+            setBaudRate(baud);
+            setIsConnected(true);
+            message.success(t('serial.connectSuccess'));
+          } catch (err) {
+            message.error(t('serial.connectError', { error: formatError(err) }));
+          }
+        }
+      });
+    };
+    const takeRequest = () => invoke('take_link_request').then(confirmLink).catch(() => {});
+    takeRequest();
+    const unlisten = listen('link-requested', takeRequest);
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+  
   // 当校准配置变化时自动生成指令
   useEffect(() => {
     generateCalibrationCommand();
@@ -558,14 +852,14 @@ function App() {
                   padding: '12px',
                   borderRadius: '8px',
                   textAlign: 'center',
-                  backgroundColor: key ? '#52c41a' : '#f0f0f0',
+                  backgroundColor: key ? (labelMeta.key_meta[index]?.color || '#52c41a') : '#f0f0f0',
                   color: key ? '#fff' : '#333',
                   fontWeight: key ? 'bold' : 'normal',
                   transition: 'all 0.3s ease',
                   boxShadow: key ? '0 2px 8px rgba(82, 196, 26, 0.4)' : 'none'
                 }}
               >
-                <div>{keyNames[index] || `${t('data.key')} ${index + 1}`}</div>
+                <div title={labelMeta.key_meta[index]?.group}>{keyNames[index] || `${t('data.key')} ${index + 1}`}</div>
                 <div style={{ fontSize: '24px', margin: '8px 0' }}>
                   {key ? '●' : '○'}
                 </div>
@@ -589,7 +883,7 @@ function App() {
             <Col key={index} xs={24} sm={12} md={8} lg={6} xl={4}>
               <div style={{ padding: '12px' }}>
                 <div style={{ display: 'flex', justifyContent: 'space-between', marginBottom: '8px' }}>
-                  <Text strong>{adcNames[index] || `${t('data.adc')} ${index + 1}`}</Text>
+                  <Text strong title={labelMeta.adc_meta[index]?.group}>{adcNames[index] || `${t('data.adc')} ${index + 1}`}</Text>
                   <Statistic 
                     value={value} 
                     suffix="/255" 
@@ -625,14 +919,14 @@ function App() {
                   padding: '12px',
                   borderRadius: '8px',
                   textAlign: 'center',
-                  backgroundColor: led ? '#ff4d4f' : '#f0f0f0',
+                  backgroundColor: led ? (labelMeta.led_meta[index]?.color || '#ff4d4f') : '#f0f0f0',
                   color: led ? '#fff' : '#333',
                   fontWeight: led ? 'bold' : 'normal',
                   transition: 'all 0.3s ease',
                   boxShadow: led ? '0 2px 8px rgba(255, 77, 79, 0.4)' : 'none'
                 }}
               >
-                <div>{ledNames[index] || `${t('data.led')} ${index + 1}`}</div>
+                <div title={labelMeta.led_meta[index]?.group}>{ledNames[index] || `${t('data.led')} ${index + 1}`}</div>
                 <div style={{ fontSize: '24px', margin: '8px 0' }}>
                   {led ? '●' : '○'}
                 </div>
@@ -716,7 +1010,7 @@ function App() {
         message.success(t('firmwareUpgrade.sendCommandSuccess'));
         setUpgradeStatus('sending');
       } catch (err) {
-        message.error(t('firmwareUpgrade.sendCommandError', { error: err }));
+        message.error(t('firmwareUpgrade.sendCommandError', { error: formatError(err) }));
         setUpgradeStatus('error');
       }
     };
@@ -938,8 +1232,8 @@ function App() {
       } catch (err) {
         console.error('升级失败:', err);
         setUpgradeStatus('error');
-        setUpgradeMessage(t('firmwareUpgrade.upgradeError', { error: err }));
-        message.error(t('firmwareUpgrade.upgradeError', { error: err }));
+        setUpgradeMessage(t('firmwareUpgrade.upgradeError', { error: formatError(err) }));
+        message.error(t('firmwareUpgrade.upgradeError', { error: formatError(err) }));
       }
     };
     
@@ -1180,14 +1474,22 @@ function App() {
                     <Card title={t('serial.title')} className="config-card">
                       <Space size="middle">
                         <Select
-                          style={{ width: 200 }}
+                          style={{ width: 280 }}
                           placeholder={t('placeholder.selectPort')}
                           value={selectedPort}
                           onChange={setSelectedPort}
+                          showSearch
+                          onSearch={setPortSearch}
                         >
                           {ports.map(port => (
-                            <Select.Option key={port} value={port}>{port}</Select.Option>
+                            <Select.Option key={port.name} value={port.name}>{port.label}</Select.Option>
                           ))}
+                          {/* 远程串口地址（tcp:// 或 rfc2217://）和模拟串口（mock://）不在串口列表中，输入或已选中时作为选项 */}
+                          {[...new Set([portSearch, selectedPort])]
+                            .filter(name => isVirtualPort(name) && !ports.some(port => port.name === name))
+                            .map(name => (
+                              <Select.Option key={name} value={name}>{name}</Select.Option>
+                            ))}
                         </Select>
                         <Select
                           style={{ width: 120 }}